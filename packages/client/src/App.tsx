@@ -1,6 +1,6 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
-import { api, federationApi, type ServerStatus } from './lib/api';
+import { api, federationApi, type ServerStatus, type SharedDocumentsResponse } from './lib/api';
 import { liveReload } from './lib/websocket';
 import { useTheme } from './lib/theme';
 import Dashboard from './components/Dashboard';
@@ -34,17 +34,20 @@ function App() {
   const { themeName } = useTheme();
   const retryIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
 
+  const countSyncConflicts = useCallback((shared: SharedDocumentsResponse) => {
+    const conflicts = shared.items.filter(
+      (d) => d.federation['sync-status'] === 'conflict' || d.federation['sync-status'] === 'origin-modified'
+    );
+    setSyncConflictCount(conflicts.length);
+  }, []);
+
   const fetchSyncCount = useCallback(async () => {
     try {
-      const shared = await federationApi.getSharedDocuments();
-      const conflicts = shared.items.filter(
-        (d) => d.federation['sync-status'] === 'conflict' || d.federation['sync-status'] === 'origin-modified'
-      );
-      setSyncConflictCount(conflicts.length);
+      countSyncConflicts(await federationApi.getSharedDocuments());
     } catch {
       // Federation may not be configured
     }
-  }, []);
+  }, [countSyncConflicts]);
 
   const fetchStatus = useCallback(async () => {
     try {
@@ -99,6 +102,8 @@ function App() {
     const unsubSync = liveReload.onMessage((msg) => {
       if (msg.type === 'sync-status-changed') {
         fetchSyncCount();
+      } else if (msg.type === 'sync-snapshot') {
+        countSyncConflicts(msg as unknown as SharedDocumentsResponse);
       }
     });
 
@@ -107,7 +112,7 @@ function App() {
       unsubSync();
       liveReload.disconnect();
     };
-  }, [fetchStatus, fetchSyncCount]);
+  }, [fetchStatus, fetchSyncCount, countSyncConflicts]);
 
   // Keyboard navigation
   useEffect(() => {
@@ -1,7 +1,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import ReactMarkdown from 'react-markdown';
 import remarkGfm from 'remark-gfm';
-import { api, type OrgDocument } from '../lib/api';
+import { api, ConflictError, type OrgDocument } from '../lib/api';
 import { liveReload } from '../lib/websocket';
 import TuiEditor, { type EditorData } from './TuiEditor';
 import { getEditorFields, documentToEditorData, editorDataToPayload } from '../lib/editor-helpers';
@@ -12,6 +12,19 @@ interface DocumentViewProps {
   onNavigate: (path: string) => void;
 }
 
+// Relative image links resolve against the document's folder and load through /api/raw,
+// so they work the same locally and over Tailscale
+function resolveImageSrc(docPath: string, src?: string): string | undefined {
+  if (!src || /^([a-z][a-z0-9+.-]*:|\/\/)/i.test(src)) return src;
+
+  const parts = src.startsWith('/') ? [] : docPath.split('/').slice(0, -1);
+  for (const part of src.split('/')) {
+    if (part === '..') parts.pop();
+    else if (part && part !== '.') parts.push(decodeURIComponent(part));
+  }
+  return api.rawUrl(parts.join('/'));
+}
+
 export default function DocumentView({ path, onBack, onNavigate }: DocumentViewProps) {
   const [document, setDocument] = useState<OrgDocument | null>(null);
   const [loading, setLoading] = useState(true);
@@ -71,7 +84,16 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
     try {
       setSaving(true);
       const { frontmatter, content } = editorDataToPayload(data, document);
-      await api.updateFile(path, frontmatter, content);
+      try {
+        await api.updateFile(path, frontmatter, content, document.checksum);
+      } catch (err) {
+        // Someone else saved this file since it was opened: overwrite only if asked to
+        if (!(err instanceof ConflictError)) throw err;
+        if (!confirm(`${path} was changed elsewhere since you opened it. Overwrite those changes?`)) {
+          return;
+        }
+        await api.updateFile(path, frontmatter, content, err.checksum ?? undefined);
+      }
       setIsEditing(false);
       // Refresh document to show updated content
       await fetchDocument();
@@ -242,6 +264,13 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
                   {children}
                 </a>
               ),
+              img: ({ src, alt }) => (
+                <img
+                  src={resolveImageSrc(document.path, src)}
+                  alt={alt}
+                  style={{ maxWidth: '100%' }}
+                />
+              ),
               h1: ({ children }) => (
                 <h1 style={{ color: 'var(--term-primary)', borderBottom: '1px solid var(--term-border)', paddingBottom: '0.5rem' }}>
                   {children}
@@ -44,10 +44,14 @@ export default function SharedDocsView({ onSelectDocument }: SharedDocsViewProps
   useEffect(() => {
     fetchShared();
 
-    // Refresh on sync status changes
+    // Refresh on sync status changes; the server sends a full snapshot on (re)connect
     const unsub = liveReload.onMessage((msg) => {
       if (msg.type === 'sync-status-changed') {
         fetchShared();
+      } else if (msg.type === 'sync-snapshot') {
+        const snapshot = msg as unknown as SharedDocumentsResponse;
+        setData({ count: snapshot.count, items: snapshot.items });
+        setLoading(false);
       }
     });
 
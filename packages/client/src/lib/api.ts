@@ -4,7 +4,10 @@
  * DIAGNOSTIC VERSION - logs via Tauri IPC
  */
 
-const SERVER_URL = 'http://127.0.0.1:3847';
+// Embedded server's origin inside the Tauri WebView, set by getTauriFetch from the
+// `get_server_port` command (the port is configurable). Empty in a browser, where
+// requests go to the page's own origin.
+let serverUrl = '';
 
 // Log via Tauri IPC (bypasses mixed content restrictions)
 async function log(msg: string) {
@@ -50,6 +53,11 @@ async function getTauriFetch(): Promise<typeof fetch | null> {
   }
 
   try {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const port = await invoke<number>('get_server_port');
+    serverUrl = `http://127.0.0.1:${port}`;
+    logSync(`server port: ${port}`);
+
     logSync('attempting to import @tauri-apps/plugin-http...');
     const plugin = await import('@tauri-apps/plugin-http');
     logSync(`import succeeded, plugin keys: ${Object.keys(plugin).join(',')}`);
@@ -78,6 +86,9 @@ export interface FileListItem {
   updated?: string;
   linkCount: number;
   backlinkCount: number;
+  wordCount: number;
+  readingMinutes: number;
+  outgoingLinkCount: number;
 }
 
 export interface OrgDocument {
@@ -91,6 +102,11 @@ export interface OrgDocument {
   backlinks: string[];
   created?: string;
   updated?: string;
+  wordCount: number;
+  readingMinutes: number;
+  outgoingLinkCount: number;
+  /** Checksum of the file as read; pass to updateFile to detect concurrent edits */
+  checksum?: string;
   resolvedBacklinks?: Array<{
     path: string;
     title: string;
@@ -108,6 +124,20 @@ export interface SearchResult {
   snippet: string;
 }
 
+export interface Backlink {
+  path: string;
+  title: string;
+  /** Sentence in the linking document around the link (empty if not found) */
+  snippet: string;
+}
+
+export interface UnlinkedMention {
+  path: string;
+  title: string;
+  snippet: string;
+  count: number;
+}
+
 export interface GraphData {
   nodes: Array<{
     id: string;
@@ -145,6 +175,23 @@ export interface ServerStatus {
   }>;
 }
 
+/** A failed request, with the server's `{ code, message }` body when it sent one */
+export class ApiError extends Error {
+  constructor(
+    public status: number,
+    public code: string | null,
+    detail: string | null
+  ) {
+    super(detail ? `API error: ${status} (${detail})` : `API error: ${status}`);
+    this.name = 'ApiError';
+  }
+}
+
+async function apiError(response: Response): Promise<ApiError> {
+  const body = await response.json().catch(() => null);
+  return new ApiError(response.status, body?.code ?? null, body?.message ?? null);
+}
+
 async function fetchJSON<T>(path: string): Promise<T> {
   logSync(`fetchJSON called for path: ${path}`);
 
@@ -153,7 +200,7 @@ async function fetchJSON<T>(path: string): Promise<T> {
     logSync(`getTauriFetch returned: ${tFetch ? 'function' : 'null'}`);
 
     if (tFetch) {
-      const url = `${SERVER_URL}/api${path}`;
+      const url = `${serverUrl}/api${path}`;
       logSync(`using tauriFetch for: ${url}`);
 
       try {
@@ -161,7 +208,7 @@ async function fetchJSON<T>(path: string): Promise<T> {
         logSync(`tauriFetch response status: ${response.status}`);
 
         if (!response.ok) {
-          throw new Error(`API error: ${response.status}`);
+          throw await apiError(response);
         }
 
         const data = await response.json();
@@ -181,7 +228,7 @@ async function fetchJSON<T>(path: string): Promise<T> {
     logSync(`browser fetch response status: ${response.status}`);
 
     if (!response.ok) {
-      throw new Error(`API error: ${response.status}`);
+      throw await apiError(response);
     }
 
     return response.json();
@@ -195,11 +242,11 @@ async function fetchWithMethod<T>(path: string, method: string): Promise<T> {
   const tFetch = await getTauriFetch();
 
   if (tFetch) {
-    const url = `${SERVER_URL}/api${path}`;
+    const url = `${serverUrl}/api${path}`;
     const response = await tFetch(url, { method });
 
     if (!response.ok) {
-      throw new Error(`API error: ${response.status}`);
+      throw await apiError(response);
     }
 
     return response.json();
@@ -208,7 +255,7 @@ async function fetchWithMethod<T>(path: string, method: string): Promise<T> {
   const response = await fetch(`/api${path}`, { method });
 
   if (!response.ok) {
-    throw new Error(`API error: ${response.status}`);
+    throw await apiError(response);
   }
 
   return response.json();
@@ -220,7 +267,7 @@ async function postJSON<T>(path: string, body: unknown): Promise<T> {
   const tFetch = await getTauriFetch();
 
   if (tFetch) {
-    const url = `${SERVER_URL}/api${path}`;
+    const url = `${serverUrl}/api${path}`;
     const response = await tFetch(url, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
@@ -228,7 +275,7 @@ async function postJSON<T>(path: string, body: unknown): Promise<T> {
     });
 
     if (!response.ok) {
-      throw new Error(`API error: ${response.status}`);
+      throw await apiError(response);
     }
 
     return response.json();
@@ -242,60 +289,105 @@ async function postJSON<T>(path: string, body: unknown): Promise<T> {
   });
 
   if (!response.ok) {
-    throw new Error(`API error: ${response.status}`);
+    throw await apiError(response);
   }
 
   return response.json();
 }
 
-async function putJSON<T>(path: string, body: unknown): Promise<T> {
+/** A conditional write was refused because the file changed since it was read */
+export class ConflictError extends Error {
+  constructor(public checksum: string | null) {
+    super('The file was changed elsewhere since it was opened');
+    this.name = 'ConflictError';
+  }
+}
+
+async function putJSON(path: string, body: unknown, ifMatch?: string): Promise<Response> {
   logSync(`putJSON called for path: ${path}`);
 
+  const headers: Record<string, string> = { 'Content-Type': 'application/json' };
+  if (ifMatch) headers['If-Match'] = `"${ifMatch}"`;
+
   const tFetch = await getTauriFetch();
 
+  let response: Response;
   if (tFetch) {
-    const url = `${SERVER_URL}/api${path}`;
+    const url = `${serverUrl}/api${path}`;
     logSync(`using tauriFetch PUT for: ${url}`);
 
-    const response = await tFetch(url, {
+    response = await tFetch(url, {
       method: 'PUT',
-      headers: { 'Content-Type': 'application/json' },
+      headers,
       body: JSON.stringify(body),
     });
 
     logSync(`tauriFetch PUT response status: ${response.status}`);
+  } else {
+    // Fallback to browser fetch
+    const url = `/api${path}`;
+    logSync(`using browser fetch PUT for: ${url}`);
 
-    if (!response.ok) {
-      throw new Error(`API error: ${response.status}`);
-    }
+    response = await fetch(url, {
+      method: 'PUT',
+      headers,
+      body: JSON.stringify(body),
+    });
+  }
 
-    // PUT returns 200 OK with no body
-    return {} as T;
+  if (response.status === 409) {
+    const conflict = await response.json().catch(() => null);
+    throw new ConflictError(conflict?.checksum ?? null);
+  }
+  if (!response.ok) {
+    throw await apiError(response);
   }
 
-  // Fallback to browser fetch
-  const url = `/api${path}`;
-  logSync(`using browser fetch PUT for: ${url}`);
+  return response;
+}
 
-  const response = await fetch(url, {
-    method: 'PUT',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(body),
-  });
+/** Checksum from an ETag header (`"sha256:..."`) */
+function etagChecksum(response: Response): string | undefined {
+  return response.headers.get('ETag')?.replace(/^W\//, '').replace(/"/g, '') || undefined;
+}
 
-  if (!response.ok) {
-    throw new Error(`API error: ${response.status}`);
-  }
+export interface SavedSearch {
+  name: string;
+  params: Record<string, string>;
+  updatedAt: string;
+}
 
-  return {} as T;
+export interface FolderTreeNode {
+  name: string;
+  path: string;
+  isDir: boolean;
+  /** Documents anywhere under a folder */
+  count?: number;
+  title?: string;
+  type?: string;
+  status?: string;
+  children?: FolderTreeNode[];
 }
 
 export const api = {
   // Files
-  async listFiles(filters?: { type?: string; tag?: string; folder?: string }): Promise<{ count: number; items: FileListItem[] }> {
+  async listFiles(filters?: {
+    type?: string;
+    status?: string;
+    tag?: string;
+    /** Match tags nested under `tag` too (`project` matches `project/alpha`) */
+    tagPrefix?: boolean;
+    folder?: string;
+    updatedAfter?: string;
+    updatedBefore?: string;
+  }): Promise<{ count: number; items: FileListItem[] }> {
     const params = new URLSearchParams();
     if (filters?.type) params.set('type', filters.type);
+    if (filters?.status) params.set('status', filters.status);
     if (filters?.tag) params.set('tag', filters.tag);
+    if (filters?.tagPrefix) params.set('prefix', 'true');
+    if (filters?.updatedAfter) params.set('updatedAfter', filters.updatedAfter);
+    if (filters?.updatedBefore) params.set('updatedBefore', filters.updatedBefore);
     if (filters?.folder) params.set('folder', filters.folder);
     const query = params.toString();
     return fetchJSON(`/files${query ? `?${query}` : ''}`);
@@ -305,23 +397,132 @@ export const api = {
     return fetchJSON(`/files/${path}`);
   },
 
+  // URL of a file under the org root as stored (images and other attachments): relative
+  // in a browser, on the embedded server's configured port inside Tauri (known once the
+  // first request has gone through getTauriFetch)
+  rawUrl(path: string): string {
+    return `${serverUrl}/api/raw/${path.split('/').map(encodeURIComponent).join('/')}`;
+  },
+
+  /**
+   * Save a document. With `expectedChecksum` (the `checksum` it was read with) the save
+   * throws ConflictError if the file changed since. Resolves to the new checksum.
+   */
   async updateFile(
     path: string,
     frontmatter: Record<string, unknown>,
-    content: string
-  ): Promise<void> {
-    await putJSON(`/files/${path}`, { frontmatter, content });
+    content: string,
+    expectedChecksum?: string
+  ): Promise<string | undefined> {
+    const response = await putJSON(`/files/${path}`, { frontmatter, content }, expectedChecksum);
+    return etagChecksum(response);
+  },
+
+  /**
+   * Create a note named after `title` under `folder`. Rejects with `API error: 409` if a
+   * note with that name exists; `template` names a file under `templates/`.
+   */
+  async createFile(note: {
+    folder?: string;
+    title: string;
+    type?: string;
+    tags?: string[];
+    template?: string;
+  }): Promise<{ success: boolean; path: string; checksum: string }> {
+    return postJSON('/files', note);
+  },
+
+  /** Move or rename a note; inbound wikilinks are rewritten to follow it */
+  async moveFile(
+    from: string,
+    to: string
+  ): Promise<{ success: boolean; path: string; rewritten: string[] }> {
+    return postJSON('/files/move', { from, to });
+  },
+
+  /** Recently touched notes; undated ones fall back to their file mtime */
+  async getRecent(filters?: { limit?: number; type?: string }): Promise<{
+    count: number;
+    items: {
+      path: string;
+      title: string;
+      type: string;
+      status?: string;
+      updated?: string;
+      created?: string;
+      mtime?: string;
+      touched: string;
+      touchedFrom: 'updated' | 'created' | 'mtime';
+    }[];
+  }> {
+    const params = new URLSearchParams();
+    if (filters?.limit) params.set('limit', String(filters.limit));
+    if (filters?.type) params.set('type', filters.type);
+    const query = params.toString();
+    return fetchJSON(`/recent${query ? `?${query}` : ''}`);
+  },
+
+  /** Documents created/updated per day (`YYYY-MM-DD` → count), for an activity heatmap */
+  async getCalendar(range?: { from?: string; to?: string }): Promise<Record<string, number>> {
+    const params = new URLSearchParams();
+    if (range?.from) params.set('from', range.from);
+    if (range?.to) params.set('to', range.to);
+    const query = params.toString();
+    return fetchJSON(`/calendar${query ? `?${query}` : ''}`);
+  },
+
+  /** Folder hierarchy under `root` (the whole vault if omitted) with document metadata */
+  async getTree(root?: string): Promise<{ root: string; count: number; children: FolderTreeNode[] }> {
+    const params = root ? `?root=${encodeURIComponent(root)}` : '';
+    return fetchJSON(`/tree${params}`);
   },
 
   // Search
-  async search(query: string, filters?: { type?: string; tag?: string; limit?: number }): Promise<{ query: string; count: number; total: number; items: SearchResult[] }> {
+  async search(
+    query: string,
+    filters?: {
+      type?: string;
+      tag?: string;
+      limit?: number;
+      /** `fuzzy` (default) or a literal, case-insensitive match */
+      mode?: 'fuzzy' | 'substring' | 'word' | 'regex';
+    }
+  ): Promise<{ query: string; count: number; total: number; items: SearchResult[] }> {
     const params = new URLSearchParams({ q: query });
+    if (filters?.mode) params.set('mode', filters.mode);
     if (filters?.type) params.set('type', filters.type);
     if (filters?.tag) params.set('tag', filters.tag);
     if (filters?.limit) params.set('limit', String(filters.limit));
     return fetchJSON(`/search?${params}`);
   },
 
+  // Saved searches: stored server-side, replayed by the client against search or listFiles
+  async listSavedSearches(): Promise<{ count: number; items: SavedSearch[] }> {
+    return fetchJSON('/saved-searches');
+  },
+
+  async saveSearch(name: string, params: Record<string, string>): Promise<SavedSearch> {
+    return postJSON('/saved-searches', { name, params });
+  },
+
+  async deleteSavedSearch(name: string): Promise<{ success: boolean; name: string }> {
+    return fetchWithMethod(`/saved-searches/${encodeURIComponent(name)}`, 'DELETE');
+  },
+
+  async getBacklinks(path: string): Promise<{ path: string; backlinks: Backlink[] }> {
+    return fetchJSON(`/backlinks/${path}`);
+  },
+
+  async getUnlinkedMentions(path: string): Promise<{
+    path: string;
+    terms: string[];
+    count: number;
+    truncated: boolean;
+    items: UnlinkedMention[];
+  }> {
+    return fetchJSON(`/unlinked-mentions/${path}`);
+  },
+
   // Graph
   async getGraph(folder?: string): Promise<GraphData> {
     const params = folder ? `?folder=${encodeURIComponent(folder)}` : '';
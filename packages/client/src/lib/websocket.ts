@@ -88,6 +88,14 @@ class LiveReloadClient {
           this.onRemoveCallbacks.forEach(cb => cb(message.path!));
         }
         break;
+      case 'bulk': {
+        // Folder-scale change coalesced server-side into one message
+        const removed = (message.removed as string[] | undefined) ?? [];
+        const updated = (message.updated as string[] | undefined) ?? [];
+        removed.forEach(path => this.onRemoveCallbacks.forEach(cb => cb(path)));
+        updated.forEach(path => this.onUpdateCallbacks.forEach(cb => cb(path)));
+        break;
+      }
     }
   }
 
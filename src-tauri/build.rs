@@ -1,3 +1,10 @@
 fn main() {
+    // Folder embedded as the web client (see server/static_files.rs). Override with
+    // VITRUM_CLIENT_DIST (absolute, or relative to src-tauri/) when building outside the repo layout.
+    println!("cargo:rerun-if-env-changed=VITRUM_CLIENT_DIST");
+    let client_dist =
+        std::env::var("VITRUM_CLIENT_DIST").unwrap_or_else(|_| "../packages/client/dist".to_string());
+    println!("cargo:rustc-env=VITRUM_CLIENT_DIST={}", client_dist);
+
     tauri_build::build()
 }
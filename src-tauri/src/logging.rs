@@ -0,0 +1,128 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+// Log filter used when RUST_LOG is unset
+const DEFAULT_LOG_FILTER: &str = "warn,vitrum=info,tower_http=info";
+
+const LOG_FILENAME: &str = "vitrum.log";
+
+/// Size at which `vitrum.log` is rotated, unless ORG_VIEWER_LOG_MAX_MB says otherwise
+pub const DEFAULT_LOG_MAX_MB: u64 = 10;
+
+// Rotated files kept next to the live one (vitrum.log.1, vitrum.log.2)
+const ROTATED_LOGS_KEPT: usize = 2;
+
+/// Send `tracing` output to `vitrum.log` in the temp dir, capped at `max_mb` (see
+/// [`RotatingLog`]). `RUST_LOG` overrides the default filter (e.g.
+/// `RUST_LOG=vitrum=debug,tower_http=debug`). Returns the log path.
+pub fn init(max_mb: u64) -> PathBuf {
+    let path = std::env::temp_dir().join(LOG_FILENAME);
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(false)
+        .with_writer(RotatingLog::new(path.clone(), max_mb.saturating_mul(1024 * 1024)))
+        .init();
+    path
+}
+
+/// Append-only log file that moves itself to `<name>.1` (shifting older ones up to
+/// `<name>.2`) once it passes `max_bytes`. The size is counted from our own writes, so
+/// there's one stat when the file is opened rather than one per line.
+pub struct RotatingLog {
+    state: Mutex<LogFile>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: Option<File>,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl RotatingLog {
+    pub fn new(path: PathBuf, max_bytes: u64) -> Self {
+        let (file, written) = LogFile::open(&path);
+        RotatingLog {
+            state: Mutex::new(LogFile {
+                path,
+                file,
+                written,
+                max_bytes,
+            }),
+        }
+    }
+}
+
+impl LogFile {
+    fn open(path: &Path) -> (Option<File>, u64) {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+                (Some(file), len)
+            }
+            Err(_) => (None, 0),
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) {
+        // Close first: Windows won't rename a file that's open
+        self.file = None;
+        for n in (1..ROTATED_LOGS_KEPT).rev() {
+            let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        let _ = std::fs::rename(&self.path, self.rotated(1));
+
+        let (file, written) = Self::open(&self.path);
+        self.file = file;
+        self.written = written;
+    }
+}
+
+/// Exclusive access to the log for one formatted event
+pub struct RotatingLogWriter<'a>(MutexGuard<'a, LogFile>);
+
+impl Write for RotatingLogWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let log = &mut *self.0;
+        if log.written >= log.max_bytes {
+            log.rotate();
+        }
+        match log.file.as_mut() {
+            Some(file) => {
+                let n = file.write(buf)?;
+                log.written += n as u64;
+                Ok(n)
+            }
+            // Nowhere to log to; drop the line rather than fail the caller
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingLog {
+    type Writer = RotatingLogWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingLogWriter(self.state.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
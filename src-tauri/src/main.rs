@@ -1,127 +1,324 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod logging;
 mod server;
 
-use std::collections::hash_map::DefaultHasher;
 use std::env;
-use std::fs::OpenOptions;
-use std::hash::{Hash, Hasher};
-use std::io::Write;
 use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::{error, info, warn};
+
+// Embedded server port, resolved once at startup (ORG_VIEWER_PORT or the default)
+static SERVER_PORT: OnceLock<u16> = OnceLock::new();
+
+// How long `api_request` keeps retrying refused connections while the server is still starting
+const API_RETRY_TOTAL_MS: u64 = 2000;
+const API_RETRY_FIRST_DELAY_MS: u64 = 50;
+
+// How long `switch_vault` waits for the new index before giving up on `vault-ready`
+const VAULT_READY_TIMEOUT_SECS: u64 = 600;
+
+// The running embedded server and the org root it serves (replaced by `switch_vault`)
+struct ServerTask {
+    org_root: PathBuf,
+    handle: tauri::async_runtime::JoinHandle<()>,
+}
+
+static SERVER_TASK: Mutex<Option<ServerTask>> = Mutex::new(None);
 
 // Tauri command for frontend logging (uses IPC, bypasses mixed content)
 #[tauri::command]
 fn frontend_log(msg: String) {
-    log_to_file(&format!("[frontend] {}", msg));
+    info!(target: "vitrum::frontend", "{}", msg);
+}
+
+// Response from the embedded server, as returned by `api_request`
+#[derive(serde::Serialize)]
+struct ApiResponse {
+    status: u16,
+    body: String,
 }
 
-// Tauri command to proxy API requests through Rust (bypasses browser restrictions)
+// Tauri command to proxy API requests through Rust (bypasses browser restrictions).
+// `method` is any HTTP method; `body` is sent as-is with `content_type` (from JS:
+// `invoke('api_request', { method, path, body, contentType })`). Every HTTP response,
+// error statuses included, comes back as `{ status, body }`. Refused connections are
+// retried briefly, since the server may still be binding its port; `Err` starts with
+// "Server not ready:" if it never answered, or "Request failed:" for anything else.
 #[tauri::command]
-async fn api_request(path: String) -> Result<String, String> {
-    log_to_file(&format!("[cmd] api_request called with path: {}", path));
-    let url = format!("http://127.0.0.1:3847{}", path);
-
-    match reqwest::get(&url).await {
-        Ok(response) => match response.text().await {
-            Ok(text) => {
-                log_to_file(&format!("[cmd] api_request success, {} bytes", text.len()));
-                Ok(text)
+async fn api_request(
+    method: String,
+    path: String,
+    body: Option<String>,
+    content_type: Option<String>,
+) -> Result<ApiResponse, String> {
+    info!("[cmd] api_request {} {}", method, path);
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let port = server_port();
+    let url = format!("http://127.0.0.1:{}{}", port, path);
+    let client = reqwest::Client::new();
+
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(API_RETRY_TOTAL_MS);
+    let mut delay = Duration::from_millis(API_RETRY_FIRST_DELAY_MS);
+    let response = loop {
+        let mut request = client.request(method.clone(), &url);
+        if let Some(content_type) = &content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        if let Some(body) = &body {
+            request = request.body(body.clone());
+        }
+
+        // A refused connection never reached the server, so retrying is safe for writes too
+        match request.send().await {
+            Ok(response) => break response,
+            Err(e) if e.is_connect() && tokio::time::Instant::now() + delay < deadline => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) if e.is_connect() => {
+                warn!("[cmd] api_request gave up, server not ready: {}", e);
+                return Err(format!("Server not ready: {}", e));
             }
             Err(e) => {
-                log_to_file(&format!("[cmd] api_request body error: {}", e));
-                Err(format!("Failed to read response: {}", e))
+                error!("[cmd] api_request failed: {}", e);
+                return Err(format!("Request failed: {}", e));
+            }
+        }
+    };
+
+    let status = response.status();
+    match response.text().await {
+        Ok(text) => {
+            if status.is_success() {
+                info!("[cmd] api_request success, {} bytes", text.len());
+            } else {
+                warn!("[cmd] api_request {} {} returned {}", method, path, status);
             }
-        },
+            Ok(ApiResponse {
+                status: status.as_u16(),
+                body: text,
+            })
+        }
         Err(e) => {
-            log_to_file(&format!("[cmd] api_request failed: {}", e));
+            error!("[cmd] api_request body error: {}", e);
             Err(format!("Request failed: {}", e))
         }
     }
 }
 
+// GET through `api_request`, returning just the body. Error statuses become
+// `Err("HTTP <status>: <body>")`.
+#[tauri::command]
+async fn api_get(path: String) -> Result<String, String> {
+    let response = api_request("GET".to_string(), path, None, None).await?;
+    if (200..300).contains(&response.status) {
+        Ok(response.body)
+    } else {
+        Err(format!("HTTP {}: {}", response.status, response.body))
+    }
+}
+
+// Tauri command for the embedded server's port, so the frontend can build URLs for
+// resources it loads directly (images via /api/raw) instead of assuming the default
+#[tauri::command]
+fn get_server_port() -> u16 {
+    server_port()
+}
+
 // Tauri command to get current org root for display
 #[tauri::command]
 fn get_org_root() -> String {
+    match SERVER_TASK.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(task) => task.org_root.to_string_lossy().to_string(),
+        None => launch_org_root().to_string_lossy().to_string(),
+    }
+}
+
+// Tauri command to serve a different org root without restarting the app: stops the
+// running server, starts one on the new root, reloads the WebView once it answers and
+// emits `vault-ready` when the new index has finished loading
+#[tauri::command]
+async fn switch_vault(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    let org_root = PathBuf::from(&path);
+    if !org_root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+    let org_root = org_root.canonicalize().unwrap_or(org_root);
+    info!(
+        "[cmd] switch_vault to {:?} (path hash {})",
+        org_root,
+        server::config::vault_hash(&org_root)
+    );
+
+    // Cancelling the server task drops its listeners and aborts its background tasks
+    let running = SERVER_TASK.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(task) = running {
+        task.handle.abort();
+        let _ = task.handle.await;
+        info!("Stopped server for {:?}", task.org_root);
+    }
+
+    spawn_server(&app, org_root.clone());
+    tauri::async_runtime::spawn(announce_vault(app, org_root.clone()));
+    Ok(org_root.to_string_lossy().to_string())
+}
+
+// Org root given at launch: 1) command line arg, 2) cwd
+fn launch_org_root() -> PathBuf {
     let args: Vec<String> = env::args().collect();
     if args.len() > 1 {
-        args[1].clone()
+        PathBuf::from(&args[1])
     } else {
-        env::current_dir()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| ".".to_string())
+        env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
     }
 }
 
-// Simple file logger
-fn log_to_file(msg: &str) {
-    let log_path = env::temp_dir().join("vitrum.log");
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-    {
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-        let _ = writeln!(file, "[{}] {}", timestamp, msg);
+// Port the embedded server listens on (resolved by `spawn_server`)
+fn server_port() -> u16 {
+    SERVER_PORT.get().copied().unwrap_or(server::config::DEFAULT_PORT)
+}
+
+// Start the embedded server for `org_root` in a background task
+fn spawn_server(app: &tauri::AppHandle, org_root: PathBuf) {
+    let port = server::config::resolve_port(server::config::DEFAULT_PORT);
+    let _ = SERVER_PORT.set(port.value);
+    info!("Starting server on port {}", port.value);
+
+    let org_root_clone = org_root.clone();
+    let app_handle = app.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        info!("Server task spawned");
+        match server::start_server(org_root_clone, port).await {
+            Ok(()) => info!("Server exited normally"),
+            Err(e) => {
+                error!("Server error: {}", e);
+                show_server_error(&app_handle, &e.to_string());
+            }
+        }
+    });
+
+    *SERVER_TASK.lock().unwrap_or_else(|e| e.into_inner()) = Some(ServerTask { org_root, handle });
+}
+
+// After a vault switch: reload the WebView as soon as the new server answers, then emit
+// `vault-ready` once `/api/health` reports the index loaded. Stops early if another
+// switch replaces this vault.
+async fn announce_vault(app: tauri::AppHandle, org_root: PathBuf) {
+    let port = server_port();
+    let url = format!("http://127.0.0.1:{}/api/health", port);
+    let deadline = std::time::Instant::now() + Duration::from_secs(VAULT_READY_TIMEOUT_SECS);
+    let mut reloaded = false;
+
+    while std::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        let current = SERVER_TASK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|t| t.org_root == org_root)
+            .unwrap_or(false);
+        if !current {
+            return;
+        }
+
+        let health = match reqwest::get(&url).await {
+            Ok(resp) => resp.json::<serde_json::Value>().await.ok(),
+            Err(_) => None,
+        };
+        let health = match health {
+            Some(h) => h,
+            None => continue,
+        };
+
+        if !reloaded {
+            if let Some(window) = app.get_webview_window("main") {
+                if let Err(e) = window.eval("window.location.reload()") {
+                    error!("Failed to reload window: {}", e);
+                }
+            }
+            reloaded = true;
+        }
+
+        if health["indexReady"].as_bool().unwrap_or(false) {
+            info!("Vault ready: {:?}", org_root);
+            let payload = serde_json::json!({ "orgRoot": org_root.to_string_lossy() });
+            if let Err(e) = app.emit("vault-ready", payload) {
+                error!("Failed to emit vault-ready: {}", e);
+            }
+            return;
+        }
     }
+    error!("Gave up waiting for vault {:?} to load", org_root);
 }
 
-/// Compute a short hash of the org root path for cache isolation
-fn hash_path(path: &PathBuf) -> String {
-    let mut hasher = DefaultHasher::new();
-    // Canonicalize to handle . and .. and get absolute path
-    let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
-    canonical.to_string_lossy().to_lowercase().hash(&mut hasher);
-    format!("{:016x}", hasher.finish())
+// Overlay a fatal server error in the main window (the UI can't load without the server)
+fn show_server_error(app: &tauri::AppHandle, msg: &str) {
+    let window = match app.get_webview_window("main") {
+        Some(w) => w,
+        None => return,
+    };
+    let msg_json = serde_json::to_string(&format!("Vitrum server failed to start\n\n{}", msg))
+        .unwrap_or_default();
+    let js = format!(
+        r#"(function(m) {{
+            function show() {{
+                var d = document.createElement('div');
+                d.style.cssText = 'position:fixed;inset:0;z-index:99999;padding:2rem;background:#111;color:#f87171;font:14px monospace;white-space:pre-wrap';
+                d.textContent = m;
+                document.body.appendChild(d);
+            }}
+            if (document.readyState === 'loading') {{
+                document.addEventListener('DOMContentLoaded', show);
+            }} else {{
+                show();
+            }}
+        }})({});"#,
+        msg_json
+    );
+    if let Err(e) = window.eval(&js) {
+        error!("Failed to show server error in window: {}", e);
+    }
 }
 
 /// Clear WebView cache for this instance
 fn clear_webview_cache(cache_dir: &PathBuf) {
     let ebwebview = cache_dir.join("EBWebView");
     if ebwebview.exists() {
-        log_to_file(&format!("Clearing WebView cache at {:?}", ebwebview));
+        info!("Clearing WebView cache at {:?}", ebwebview);
         if let Err(e) = std::fs::remove_dir_all(&ebwebview) {
-            log_to_file(&format!("Failed to clear WebView cache: {}", e));
+            error!("Failed to clear WebView cache: {}", e);
         } else {
-            log_to_file("WebView cache cleared successfully");
+            info!("WebView cache cleared successfully");
         }
     }
 }
 
 fn main() {
-    // Clear log file on start
-    let log_path = env::temp_dir().join("vitrum.log");
-    let _ = std::fs::write(&log_path, "");
+    let log_max_mb = server::config::resolve_log_max_mb(logging::DEFAULT_LOG_MAX_MB);
+    let log_path = logging::init(log_max_mb.value);
 
     // Set up panic hook to log panics
-    let log_path_clone = log_path.clone();
-    std::panic::set_hook(Box::new(move |panic_info| {
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path_clone)
-        {
-            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-            let _ = writeln!(file, "[{}] PANIC: {}", timestamp, panic_info);
-        }
+    std::panic::set_hook(Box::new(|panic_info| {
+        error!("PANIC: {}", panic_info);
     }));
 
-    log_to_file("=== Vitrum Starting ===");
-    log_to_file(&format!("Log file: {:?}", log_path));
-    log_to_file(&format!("Args: {:?}", env::args().collect::<Vec<_>>()));
-    log_to_file(&format!("CWD: {:?}", env::current_dir()));
+    info!("=== Vitrum Starting ===");
+    info!("Log file: {:?} (rotated at {} MB)", log_path, log_max_mb.value);
+    info!("Args: {:?}", env::args().collect::<Vec<_>>());
+    info!("CWD: {:?}", env::current_dir());
 
     // Get org root from: 1) command line arg, 2) cwd
-    let args: Vec<String> = env::args().collect();
-    let org_root = if args.len() > 1 {
-        PathBuf::from(&args[1])
-    } else {
-        env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
-    };
+    let org_root = launch_org_root();
 
     // Compute hash for cache isolation
-    let path_hash = hash_path(&org_root);
-    log_to_file(&format!("ORG_ROOT: {:?}", org_root));
-    log_to_file(&format!("Path hash: {}", path_hash));
+    let path_hash = server::config::vault_hash(&org_root);
+    info!("ORG_ROOT: {:?}", org_root);
+    info!("Path hash: {}", path_hash);
 
     // Set custom app data directory based on org root hash
     // This isolates WebView cache per org folder
@@ -130,11 +327,11 @@ fn main() {
         .join("vitrum")
         .join(&path_hash);
 
-    log_to_file(&format!("Data directory: {:?}", base_data_dir));
+    info!("Data directory: {:?}", base_data_dir);
 
     // Ensure directory exists
     if let Err(e) = std::fs::create_dir_all(&base_data_dir) {
-        log_to_file(&format!("Failed to create data dir: {}", e));
+        error!("Failed to create data dir: {}", e);
     }
 
     // Clear WebView cache on startup to ensure fresh embedded assets
@@ -154,25 +351,22 @@ fn main() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_http::init())
-        .invoke_handler(tauri::generate_handler![api_request, frontend_log, get_org_root])
-        .setup(move |_app| {
-            log_to_file("Tauri setup starting");
-            log_to_file(&format!("ORG_ROOT exists: {}", org_root_for_server.exists()));
+        .invoke_handler(tauri::generate_handler![
+            api_get,
+            api_request,
+            frontend_log,
+            get_org_root,
+            get_server_port,
+            switch_vault
+        ])
+        .setup(move |app| {
+            info!("Tauri setup starting");
+            info!("ORG_ROOT exists: {}", org_root_for_server.exists());
 
             // Start the embedded server in a background task
-            let port = 3847u16;
-            log_to_file(&format!("Starting server on port {}", port));
-
-            let org_root_clone = org_root_for_server.clone();
-            tauri::async_runtime::spawn(async move {
-                log_to_file("Server task spawned");
-                match server::start_server(org_root_clone, port).await {
-                    Ok(()) => log_to_file("Server exited normally"),
-                    Err(e) => log_to_file(&format!("Server error: {}", e)),
-                }
-            });
+            spawn_server(app.handle(), org_root_for_server.clone());
 
-            log_to_file("Tauri setup complete");
+            info!("Tauri setup complete");
             Ok(())
         })
         .run(tauri::generate_context!())
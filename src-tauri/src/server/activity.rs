@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::error;
+
+
+const ACTIVITY_FILE: &str = ".vitrum-activity.jsonl";
+
+/// A single document access/edit event, stored one per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub path: String,
+    /// "view" | "edit" | "delete" | "archive"
+    pub action: String,
+    /// RFC 3339 timestamp
+    pub timestamp: String,
+}
+
+/// Append-only per-document activity log (`.vitrum-activity.jsonl` at the org root)
+pub struct ActivityLog {
+    path: PathBuf,
+    enabled: bool,
+    write_lock: Mutex<()>,
+}
+
+impl ActivityLog {
+    pub fn new(org_root: &Path, enabled: bool) -> Self {
+        ActivityLog {
+            path: org_root.join(ACTIVITY_FILE),
+            enabled,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Append an event. No-op when activity logging is disabled; failures are logged only.
+    pub async fn record(&self, path: &str, action: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let event = ActivityEvent {
+            path: path.to_string(),
+            action: action.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut line = match serde_json::to_string(&event) {
+            Ok(l) => l,
+            Err(_) => return,
+        };
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().await;
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            file.write_all(line.as_bytes()).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            error!("Failed to write {}: {}", ACTIVITY_FILE, e);
+        }
+    }
+
+    /// Events at or after `since` (oldest first), keeping only the most recent `limit`.
+    pub async fn read_since(
+        &self,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        limit: usize,
+    ) -> Vec<ActivityEvent> {
+        let raw = match tokio::fs::read_to_string(&self.path).await {
+            Ok(r) => r,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut events: Vec<ActivityEvent> = raw
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ActivityEvent>(line).ok())
+            .filter(|e| match since {
+                Some(since) => chrono::DateTime::parse_from_rfc3339(&e.timestamp)
+                    .map(|t| t >= since)
+                    .unwrap_or(false),
+                None => true,
+            })
+            .collect();
+
+        if events.len() > limit {
+            events.drain(..events.len() - limit);
+        }
+        events
+    }
+}
@@ -0,0 +1,388 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+use crate::server::index::{
+    SearchWeights, DEFAULT_MAX_DOCUMENTS, DEFAULT_MAX_FILE_MB, EXCLUDED_DIRS,
+};
+use crate::server::sync::ChecksumAlgorithm;
+
+const CONFIG_FILE: &str = ".vitrum-config.json";
+
+pub const DEFAULT_PORT: u16 = 3847;
+const DEFAULT_BIND: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+const DEFAULT_ARCHIVE_DIR: &str = "archive";
+const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+const DEFAULT_INDEX_TIMEOUT_SECS: u64 = 30;
+
+// --- Types ---
+
+/// Where a resolved setting came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+    Arg,
+}
+
+/// A resolved setting and the source it was taken from
+#[derive(Debug, Clone, Serialize)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, source: ConfigSource) -> Self {
+        Setting { value, source }
+    }
+}
+
+/// What to do when the initial index build outlasts `indexTimeoutSecs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexTimeoutMode {
+    /// Start serving with an empty index and swap in the full one when the build finishes
+    Background,
+    /// Fail startup
+    Abort,
+}
+
+impl IndexTimeoutMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "background" => Some(IndexTimeoutMode::Background),
+            "abort" => Some(IndexTimeoutMode::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Optional overrides read from `.vitrum-config.json` at the org root
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileConfig {
+    archive_dir: Option<String>,
+    checksum: Option<String>,
+    batch_concurrency: Option<usize>,
+    activity_log: Option<bool>,
+    max_documents: Option<usize>,
+    max_file_mb: Option<u64>,
+    index_timeout_secs: Option<u64>,
+    index_timeout_mode: Option<IndexTimeoutMode>,
+    ws_update_metadata: Option<bool>,
+    mdns: Option<bool>,
+    exclude_files: Option<Vec<String>>,
+    search_weights: Option<SearchWeights>,
+    follow_symlinks: Option<bool>,
+    tls_self_signed: Option<bool>,
+}
+
+/// Fully-resolved runtime configuration (defaults < config file < env vars).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerConfig {
+    pub org_root: Setting<String>,
+    /// Where the index cache and peer files live: the org root, or a per-vault folder
+    /// under `ORG_VIEWER_STATE_DIR`
+    pub state_dir: Setting<PathBuf>,
+    pub port: Setting<u16>,
+    pub bind: Setting<IpAddr>,
+    pub tls_cert: Setting<Option<String>>,
+    pub tls_key: Setting<Option<String>>,
+    /// Serve HTTPS with a generated self-signed certificate when no cert/key paths are set
+    pub tls_self_signed: Setting<bool>,
+    pub archive_dir: Setting<String>,
+    #[serde(serialize_with = "serialize_checksum")]
+    pub checksum: Setting<ChecksumAlgorithm>,
+    pub batch_concurrency: Setting<usize>,
+    pub activity_log: Setting<bool>,
+    pub max_documents: Setting<usize>,
+    /// Markdown files larger than this many megabytes are skipped (see /api/index-warnings)
+    pub max_file_mb: Setting<u64>,
+    /// Seconds to wait for the initial index build before `index_timeout_mode` applies; 0 waits forever
+    pub index_timeout_secs: Setting<u64>,
+    pub index_timeout_mode: Setting<IndexTimeoutMode>,
+    /// Include title/type/tags/checksum in WebSocket `update` messages
+    pub ws_update_metadata: Setting<bool>,
+    /// Advertise and discover peers on the LAN over mDNS
+    pub mdns: Setting<bool>,
+    pub excluded_dirs: Setting<Vec<String>>,
+    /// Individual files never indexed: relative paths or file-name patterns (`*` wildcard)
+    pub exclude_files: Setting<Vec<String>>,
+    /// Fuzzy search multipliers for title / path / tag matches
+    pub search_weights: Setting<SearchWeights>,
+    /// Index (and watch) symlinked folders under the org root
+    pub follow_symlinks: Setting<bool>,
+}
+
+fn serialize_checksum<S: serde::Serializer>(
+    setting: &Setting<ChecksumAlgorithm>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    Setting::new(setting.value.prefix(), setting.source).serialize(serializer)
+}
+
+impl ServerConfig {
+    /// Resolve configuration for a vault. `port` is resolved by the caller (see [`resolve_port`])
+    /// since the app shell needs it before the server starts.
+    pub fn load(org_root: &Path, port: Setting<u16>) -> Self {
+        let file = Self::load_file(org_root);
+
+        let bind = resolve("ORG_VIEWER_BIND", None, DEFAULT_BIND, |raw| {
+            raw.trim().parse::<IpAddr>().ok()
+        });
+
+        let mut state_dir = resolve("ORG_VIEWER_STATE_DIR", None, org_root.to_path_buf(), |raw| {
+            let raw = raw.trim();
+            (!raw.is_empty()).then(|| Path::new(raw).join(vault_hash(org_root)))
+        });
+        if state_dir.source == ConfigSource::Env {
+            if let Err(e) = std::fs::create_dir_all(&state_dir.value) {
+                error!(
+                    "Failed to create state dir {:?}: {}; keeping state in the org root",
+                    state_dir.value, e
+                );
+                state_dir = Setting::new(org_root.to_path_buf(), ConfigSource::Default);
+            }
+        }
+
+        let archive_dir = resolve(
+            "ORG_VIEWER_ARCHIVE_DIR",
+            file.archive_dir,
+            DEFAULT_ARCHIVE_DIR.to_string(),
+            |raw| {
+                let trimmed = raw.trim().trim_end_matches('/');
+                (!trimmed.is_empty()).then(|| trimmed.to_string())
+            },
+        );
+
+        let checksum = resolve(
+            "ORG_VIEWER_CHECKSUM",
+            file.checksum.as_deref().and_then(ChecksumAlgorithm::from_name),
+            ChecksumAlgorithm::Sha256,
+            ChecksumAlgorithm::from_name,
+        );
+
+        let batch_concurrency = resolve(
+            "ORG_VIEWER_BATCH_CONCURRENCY",
+            file.batch_concurrency.filter(|n| *n > 0),
+            DEFAULT_BATCH_CONCURRENCY,
+            |raw| raw.trim().parse::<usize>().ok().filter(|n| *n > 0),
+        );
+
+        let activity_log = resolve("ORG_VIEWER_ACTIVITY_LOG", file.activity_log, true, parse_bool);
+
+        let max_documents = resolve(
+            "ORG_VIEWER_MAX_DOCUMENTS",
+            file.max_documents.filter(|n| *n > 0),
+            DEFAULT_MAX_DOCUMENTS,
+            |raw| raw.trim().parse::<usize>().ok().filter(|n| *n > 0),
+        );
+
+        let max_file_mb = resolve(
+            "ORG_VIEWER_MAX_FILE_MB",
+            file.max_file_mb.filter(|n| *n > 0),
+            DEFAULT_MAX_FILE_MB,
+            |raw| raw.trim().parse::<u64>().ok().filter(|n| *n > 0),
+        );
+
+        let index_timeout_secs = resolve(
+            "ORG_VIEWER_INDEX_TIMEOUT_SECS",
+            file.index_timeout_secs,
+            DEFAULT_INDEX_TIMEOUT_SECS,
+            |raw| raw.trim().parse::<u64>().ok(),
+        );
+
+        let index_timeout_mode = resolve(
+            "ORG_VIEWER_INDEX_TIMEOUT_MODE",
+            file.index_timeout_mode,
+            IndexTimeoutMode::Background,
+            IndexTimeoutMode::from_name,
+        );
+
+        let ws_update_metadata = resolve(
+            "ORG_VIEWER_WS_UPDATE_METADATA",
+            file.ws_update_metadata,
+            false,
+            parse_bool,
+        );
+
+        let mdns = resolve("ORG_VIEWER_MDNS", file.mdns, false, parse_bool);
+
+        let exclude_files = resolve(
+            "ORG_VIEWER_EXCLUDE_FILES",
+            file.exclude_files,
+            Vec::new(),
+            |raw| {
+                Some(
+                    raw.split(',')
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .map(String::from)
+                        .collect(),
+                )
+            },
+        );
+
+        let search_weights = resolve(
+            "ORG_VIEWER_SEARCH_WEIGHTS",
+            file.search_weights,
+            SearchWeights::default(),
+            SearchWeights::parse,
+        );
+
+        let follow_symlinks = resolve(
+            "ORG_VIEWER_FOLLOW_SYMLINKS",
+            file.follow_symlinks,
+            false,
+            parse_bool,
+        );
+
+        let tls_self_signed = resolve(
+            "ORG_VIEWER_TLS_SELFSIGNED",
+            file.tls_self_signed,
+            false,
+            parse_bool,
+        );
+
+        ServerConfig {
+            org_root: Setting::new(org_root.to_string_lossy().to_string(), ConfigSource::Arg),
+            state_dir,
+            port,
+            bind,
+            tls_cert: env_optional("ORG_VIEWER_TLS_CERT"),
+            tls_key: env_optional("ORG_VIEWER_TLS_KEY"),
+            tls_self_signed,
+            archive_dir,
+            checksum,
+            batch_concurrency,
+            activity_log,
+            max_documents,
+            max_file_mb,
+            index_timeout_secs,
+            index_timeout_mode,
+            ws_update_metadata,
+            mdns,
+            excluded_dirs: Setting::new(
+                EXCLUDED_DIRS.iter().map(|d| d.to_string()).collect(),
+                ConfigSource::Default,
+            ),
+            exclude_files,
+            search_weights,
+            follow_symlinks,
+        }
+    }
+
+    /// Whether an HTTPS listener is configured (cert files, or a self-signed certificate)
+    pub fn tls_enabled(&self) -> bool {
+        (self.tls_cert.value.is_some() && self.tls_key.value.is_some())
+            || self.tls_self_signed.value
+    }
+
+    fn load_file(org_root: &Path) -> FileConfig {
+        let path = org_root.join(CONFIG_FILE);
+        if !path.exists() {
+            return FileConfig::default();
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(raw) => match serde_json::from_str(&raw) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Failed to parse {}: {}", CONFIG_FILE, e);
+                    FileConfig::default()
+                }
+            },
+            Err(e) => {
+                error!("Failed to read {}: {}", CONFIG_FILE, e);
+                FileConfig::default()
+            }
+        }
+    }
+}
+
+/// Resolve a setting: env var (if set and valid) > config file value > default.
+/// Invalid env values are logged and ignored.
+fn resolve<T>(
+    env_key: &str,
+    file_value: Option<T>,
+    default: T,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Setting<T> {
+    if let Ok(raw) = std::env::var(env_key) {
+        match parse(&raw) {
+            Some(value) => return Setting::new(value, ConfigSource::Env),
+            None => warn!("Invalid {} '{}', ignoring", env_key, raw),
+        }
+    }
+
+    match file_value {
+        Some(value) => Setting::new(value, ConfigSource::File),
+        None => Setting::new(default, ConfigSource::Default),
+    }
+}
+
+/// Short hash of the canonical org root path, naming per-vault folders (app data, state)
+pub fn vault_hash(org_root: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    // Canonicalize to handle . and .. and get absolute path
+    let canonical = org_root.canonicalize().unwrap_or_else(|_| org_root.to_path_buf());
+    canonical.to_string_lossy().to_lowercase().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Path of the state file `name` in `state_dir`. If the state dir is outside the vault and
+/// the file so far only exists at the org root, it is moved over first.
+pub fn state_file(org_root: &Path, state_dir: &Path, name: &str) -> PathBuf {
+    let path = state_dir.join(name);
+    let legacy = org_root.join(name);
+    if state_dir != org_root && !path.exists() && legacy.exists() {
+        // rename fails across filesystems; copy and remove instead
+        let moved = std::fs::rename(&legacy, &path).or_else(|_| {
+            std::fs::copy(&legacy, &path)?;
+            std::fs::remove_file(&legacy)
+        });
+        match moved {
+            Ok(()) => info!("Moved {} to {:?}", name, state_dir),
+            Err(e) => error!("Failed to move {} to {:?}: {}", name, state_dir, e),
+        }
+    }
+    path
+}
+
+/// Base HTTP port: `ORG_VIEWER_PORT` if valid, else `default`. HTTPS listens on `port + 1`,
+/// so 65535 is rejected.
+pub fn resolve_port(default: u16) -> Setting<u16> {
+    resolve("ORG_VIEWER_PORT", None, default, |raw| {
+        raw.trim().parse::<u16>().ok().filter(|p| *p > 0 && *p < u16::MAX)
+    })
+}
+
+/// Size in megabytes at which the app log rotates: `ORG_VIEWER_LOG_MAX_MB` if valid, else
+/// `default`. Env-only, since logging starts before any vault config is read.
+pub fn resolve_log_max_mb(default: u64) -> Setting<u64> {
+    resolve("ORG_VIEWER_LOG_MAX_MB", None, default, |raw| {
+        raw.trim().parse::<u64>().ok().filter(|n| *n > 0)
+    })
+}
+
+fn parse_bool(raw: &str) -> Option<bool> {
+    match raw.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn env_optional(env_key: &str) -> Setting<Option<String>> {
+    match std::env::var(env_key) {
+        Ok(value) => Setting::new(Some(value), ConfigSource::Env),
+        Err(_) => Setting::new(None, ConfigSource::Default),
+    }
+}
@@ -1,9 +1,14 @@
 use gray_matter::{engine::YAML, Matter};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
 
+/// Reading speed behind `OrgDocument::reading_minutes`
+const READING_WORDS_PER_MINUTE: usize = 200;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrgDocument {
     pub path: String,
@@ -11,43 +16,104 @@ pub struct OrgDocument {
     #[serde(rename = "type")]
     pub doc_type: String,
     pub status: Option<String>,
+    #[serde(default)]
     pub tags: Vec<String>,
+    /// Alternative names that `[[wikilinks]]` can resolve to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     pub created: Option<String>,
     pub updated: Option<String>,
+    #[serde(default)]
     pub links: Vec<String>,
+    #[serde(default)]
     pub backlinks: Vec<String>,
+    /// External (http/https) URLs; queryable but not part of the graph
+    #[serde(rename = "externalLinks", default, skip_serializing_if = "Vec::is_empty")]
+    pub external_links: Vec<String>,
+    /// Open / completed checkbox counts
+    #[serde(rename = "tasksOpen", default, skip_serializing_if = "is_zero")]
+    pub tasks_open: usize,
+    #[serde(rename = "tasksDone", default, skip_serializing_if = "is_zero")]
+    pub tasks_done: usize,
+    /// Words in the body, excluding frontmatter and fenced code
+    #[serde(rename = "wordCount", default)]
+    pub word_count: usize,
+    /// Estimated at `READING_WORDS_PER_MINUTE`, rounded up
+    #[serde(rename = "readingMinutes", default)]
+    pub reading_minutes: usize,
+    /// Internal plus external links
+    #[serde(rename = "outgoingLinkCount", default)]
+    pub outgoing_link_count: usize,
+    /// Outline of the body, for a table of contents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headings: Vec<Heading>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Non-fatal problems found while parsing (e.g. tab-indented frontmatter)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Another document has the same title (case-insensitive); show its path to tell them apart
+    #[serde(rename = "titleAmbiguous", default)]
+    pub title_ambiguous: bool,
+}
+
+/// A `- [ ]` / `- [x]` checkbox line
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkbox {
+    /// 1-based line number in the file
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+}
+
+/// A markdown heading with its GitHub-style anchor id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    pub id: String,
 }
 
 #[derive(Debug, Deserialize, Default)]
 struct Frontmatter {
     #[serde(rename = "type")]
     doc_type: Option<String>,
+    title: Option<String>,
     status: Option<String>,
     tags: Option<Vec<String>>,
+    aliases: Option<Vec<String>>,
     created: Option<String>,
     updated: Option<String>,
 }
 
 pub fn parse_document(path: &Path, org_root: &Path, content: &str) -> OrgDocument {
+    let content = &*normalize_text(content);
     let matter = Matter::<YAML>::new();
     let result = matter.parse(content);
 
-    // Parse frontmatter
-    let frontmatter: Frontmatter = result
-        .data
-        .and_then(|d| d.deserialize().ok())
-        .unwrap_or_default();
-
-    // Extract title from first heading or filename
-    let title = extract_title(content, path);
-
-    // Extract wikilinks
-    let links = extract_wikilinks(content);
+    // Parse frontmatter; problems become warnings rather than failing the document
+    let has_frontmatter = frontmatter_bounds(content)
+        .map(|(inner, _)| !content[inner].trim().is_empty())
+        .unwrap_or(false);
+    let (frontmatter, frontmatter_error): (Frontmatter, Option<String>) = match result.data {
+        Some(gray_matter::Pod::Null) | None if has_frontmatter => {
+            (Frontmatter::default(), Some("Frontmatter is not valid YAML".to_string()))
+        }
+        Some(gray_matter::Pod::Null) | None => (Frontmatter::default(), None),
+        Some(data) => match data.deserialize() {
+            Ok(frontmatter) => (frontmatter, None),
+            Err(e) => (
+                Frontmatter::default(),
+                Some(format!("Frontmatter fields could not be read: {}", e)),
+            ),
+        },
+    };
 
-    // Infer document type
-    let doc_type = infer_type(&frontmatter.doc_type, path, org_root);
+    // Title from frontmatter, else first heading or filename
+    let title = match frontmatter.title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => extract_title(content, path),
+    };
 
     // Get relative path
     let relative_path = path
@@ -56,18 +122,118 @@ pub fn parse_document(path: &Path, org_root: &Path, content: &str) -> OrgDocumen
         .to_string_lossy()
         .replace('\\', "/");
 
+    // Extract wikilinks, plus relative markdown links resolved to vault paths
+    let mut links = extract_wikilinks(content);
+    for link in extract_markdown_links(content, &relative_path) {
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    let external_links = extract_external_links(content);
+
+    let checkboxes = extract_checkboxes(content);
+    let tasks_done = checkboxes.iter().filter(|c| c.done).count();
+    let tasks_open = checkboxes.len() - tasks_done;
+
+    // Outline and size from the body (frontmatter excluded)
+    let headings = extract_headings(&result.content);
+    let word_count = count_words(&result.content);
+    let outgoing_link_count = links.len() + external_links.len();
+
+    // Infer document type
+    let doc_type = infer_type(&frontmatter.doc_type, path, org_root);
+
+    // Tab-indented frontmatter parses as empty, so flag it explicitly (that also explains
+    // the parse failure, so don't report both)
+    let mut warnings = check_frontmatter_indentation(content);
+    if let (Some(error), true) = (frontmatter_error, warnings.is_empty()) {
+        warnings.push(error);
+    }
+
     OrgDocument {
         path: relative_path,
         title,
         doc_type,
         status: frontmatter.status,
         tags: frontmatter.tags.unwrap_or_default(),
+        aliases: frontmatter.aliases.unwrap_or_default(),
         created: frontmatter.created,
         updated: frontmatter.updated,
         links,
         backlinks: Vec::new(), // Populated later
+        external_links,
+        tasks_open,
+        tasks_done,
+        word_count,
+        reading_minutes: word_count.div_ceil(READING_WORDS_PER_MINUTE),
+        outgoing_link_count,
+        headings,
         content: None,
+        warnings,
+        title_ambiguous: false, // Populated later
+    }
+}
+
+/// Strip a leading UTF-8 BOM and turn CRLF line endings into LF, so frontmatter fences
+/// are found in files saved by Windows editors
+pub fn normalize_text(content: &str) -> Cow<'_, str> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    if content.contains("\r\n") {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Locate the frontmatter block: `content` must open with a `---` line (after an optional
+/// BOM) and the block ends at the next line that is exactly `---`, so a `---` inside a
+/// value or later in the body never closes it. Returns the byte range between the fences
+/// and the offset just past the closing `---`.
+pub fn frontmatter_bounds(content: &str) -> Option<(Range<usize>, usize)> {
+    let start = if content.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+    let first_line = content[start..].split('\n').next().unwrap_or("");
+    if first_line.trim_end() != "---" {
+        return None;
+    }
+
+    let inner_start = start + 3;
+    let mut from = inner_start;
+    while let Some(idx) = content[from..].find("\n---") {
+        let fence = from + idx + 1;
+        let after = &content[fence + 3..];
+        if after.is_empty() || after.starts_with('\n') || after.starts_with("\r\n") {
+            return Some((inner_start..fence, fence + 3));
+        }
+        from = fence;
     }
+    None
+}
+
+/// Scan the frontmatter block for lines indented with tabs, which YAML rejects.
+/// Returns one warning per offending line (1-based line numbers).
+fn check_frontmatter_indentation(content: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut lines = content.lines().enumerate();
+
+    match lines.next() {
+        Some((_, first)) if first.trim_end() == "---" => {}
+        _ => return warnings,
+    }
+
+    for (idx, line) in lines {
+        if line.trim_end() == "---" {
+            break;
+        }
+        let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        if indent.contains('\t') {
+            warnings.push(format!(
+                "Frontmatter line {} is indented with a tab; YAML requires spaces",
+                idx + 1
+            ));
+        }
+    }
+
+    warnings
 }
 
 fn extract_title(content: &str, path: &Path) -> String {
@@ -85,14 +251,473 @@ fn extract_title(content: &str, path: &Path) -> String {
         .unwrap_or_else(|| "Untitled".to_string())
 }
 
+/// `[[target]]`, `[[target#Heading]]` and `[[target|alias]]` link targets; a `#heading`
+/// suffix is dropped so the link still resolves to its document
 fn extract_wikilinks(content: &str) -> Vec<String> {
-    let link_re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
+    let link_re = Regex::new(r"\[\[([^\]|#]+)(?:#[^\]|]*)?(?:\|[^\]]+)?\]\]").unwrap();
     link_re
         .captures_iter(content)
         .map(|cap| cap[1].to_string())
         .collect()
 }
 
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Find GitHub-style task list items (`- [ ]`, `* [x]`, `1. [ ]`), skipping fenced code.
+pub fn extract_checkboxes(content: &str) -> Vec<Checkbox> {
+    let checkbox_re = Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+\[([ xX])\]\s+(.+)$").unwrap();
+    let mut checkboxes = Vec::new();
+    let mut in_fence = false;
+
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(caps) = checkbox_re.captures(line) {
+            checkboxes.push(Checkbox {
+                line: idx + 1,
+                text: caps[2].trim().to_string(),
+                done: &caps[1] != " ",
+            });
+        }
+    }
+
+    checkboxes
+}
+
+/// Collect ATX headings (`#` to `######`), skipping fenced code blocks. Anchor ids
+/// follow GitHub's slugging; repeats get `-1`, `-2`, ... appended.
+/// Words outside fenced code blocks; tokens without a letter or digit (list bullets,
+/// `#`, table pipes) don't count.
+fn count_words(body: &str) -> usize {
+    let mut count = 0;
+    let mut fence: Option<char> = None;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = trimmed.chars().next().unwrap_or('`');
+            match fence {
+                None => fence = Some(marker),
+                Some(open) if open == marker => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+        count += trimmed
+            .split_whitespace()
+            .filter(|w| w.chars().any(|c| c.is_alphanumeric()))
+            .count();
+    }
+    count
+}
+
+fn extract_headings(body: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut fence: Option<char> = None;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        // Track ``` / ~~~ fences
+        if indent < 4 && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            let marker = trimmed.chars().next().unwrap_or('`');
+            match fence {
+                None => fence = Some(marker),
+                Some(open) if open == marker => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_some() || indent >= 4 {
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if level == 0 || level > 6 {
+            continue;
+        }
+        let rest = &trimmed[level..];
+        if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+            continue; // "#tag", not a heading
+        }
+
+        // Drop an optional closing sequence ("## Title ##")
+        let text = rest.trim();
+        let text = match text.trim_end_matches('#') {
+            stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+            _ => text,
+        };
+        if text.is_empty() {
+            continue;
+        }
+
+        let base = slugify(text);
+        let id = match seen.get_mut(&base) {
+            Some(count) => {
+                *count += 1;
+                format!("{}-{}", base, count)
+            }
+            None => {
+                seen.insert(base.clone(), 0);
+                base
+            }
+        };
+
+        headings.push(Heading {
+            level: level as u8,
+            text: text.to_string(),
+            id,
+        });
+    }
+
+    headings
+}
+
+/// GitHub-style anchor: lowercase, punctuation dropped, spaces to hyphens
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Extract `[text](target.md)` links to other notes, resolved against the linking
+/// document's folder into vault-relative paths. Images, anchors and URLs are skipped.
+fn extract_markdown_links(content: &str, doc_path: &str) -> Vec<String> {
+    let link_re = Regex::new(r"(!?)\[[^\]]*\]\(\s*<?([^)\s>]+)>?(?:\s+[^)]*)?\)").unwrap();
+    let base_dir = doc_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+    let mut links = Vec::new();
+    for cap in link_re.captures_iter(content) {
+        if &cap[1] == "!" {
+            continue;
+        }
+        if let Some(resolved) = resolve_markdown_target(&cap[2], base_dir) {
+            if !links.contains(&resolved) {
+                links.push(resolved);
+            }
+        }
+    }
+    links
+}
+
+/// Vault path a markdown link target (`../notes/a.md#intro`) points at, from a document in
+/// `base_dir`; None for URLs, anchors and anything that isn't a `.md` file
+fn resolve_markdown_target(raw: &str, base_dir: &str) -> Option<String> {
+    let target = raw.split('#').next().unwrap_or("");
+    if target.is_empty() || target.contains("://") || target.starts_with("mailto:") {
+        return None;
+    }
+    let target = percent_decode(target);
+    if !target.to_lowercase().ends_with(".md") {
+        return None;
+    }
+
+    let joined = match target.strip_prefix('/') {
+        Some(rooted) => rooted.to_string(),
+        None if base_dir.is_empty() => target,
+        None => format!("{}/{}", base_dir, target),
+    };
+    normalize_vault_path(&joined)
+}
+
+/// Link from a document in `from_dir` to the vault path `path`, e.g. `../archive/a.md`
+fn relative_link(from_dir: &str, path: &str) -> String {
+    let from: Vec<&str> = from_dir.split('/').filter(|s| !s.is_empty()).collect();
+    let to: Vec<&str> = path.split('/').collect();
+    let common = from
+        .iter()
+        .zip(&to[..to.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+/// Collect http(s) URLs, from markdown links and bare in the text, without duplicates.
+fn extract_external_links(content: &str) -> Vec<String> {
+    let url_re = Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).unwrap();
+
+    let mut urls: Vec<String> = Vec::new();
+    for m in url_re.find_iter(content) {
+        let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_']);
+        if !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Collapse `.` and `..` segments; `None` if the path climbs above the vault root.
+fn normalize_vault_path(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            p => parts.push(p),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Decode `%XX` escapes (e.g. `%20`) in a link target
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Rename tag `from` to `to` in the frontmatter `tags:` entry, keeping its layout
+/// (flow `[a, b]`, block `- a` list, or a single scalar) and quoting. If the document
+/// already has `to`, the `from` entry is dropped instead. Returns `None` if unchanged.
+/// A rewritten file comes back with LF line endings and no BOM.
+pub fn rename_tag(content: &str, from: &str, to: &str) -> Option<String> {
+    let content = &*normalize_text(content);
+    let fm_end = frontmatter_bounds(content)?.0.end;
+    let mut lines: Vec<String> = content[..fm_end].split('\n').map(String::from).collect();
+
+    let tags_idx = lines.iter().position(|l| l.starts_with("tags:"))?;
+    let inline = lines[tags_idx]["tags:".len()..].trim().to_string();
+
+    if inline.starts_with('[') && inline.ends_with(']') {
+        // Flow list: tags: [a, "b", c]
+        let items: Vec<&str> = inline[1..inline.len() - 1]
+            .split(',')
+            .map(|i| i.trim())
+            .filter(|i| !i.is_empty())
+            .collect();
+        let renamed = rename_in_list(&items, from, to)?;
+        lines[tags_idx] = format!("tags: [{}]", renamed.join(", "));
+    } else if inline.is_empty() {
+        // Block list: "  - a" lines following "tags:"
+        let mut end = tags_idx + 1;
+        while end < lines.len() && lines[end].trim_start().starts_with('-') {
+            end += 1;
+        }
+        let items: Vec<&str> = lines[tags_idx + 1..end]
+            .iter()
+            .map(|l| l.trim_start()[1..].trim())
+            .collect();
+        let renamed = rename_in_list(&items, from, to)?;
+        let prefix = lines[tags_idx + 1]
+            .split('-')
+            .next()
+            .unwrap_or("")
+            .to_string();
+        let new_lines: Vec<String> = renamed.iter().map(|i| format!("{}- {}", prefix, i)).collect();
+        lines.splice(tags_idx + 1..end, new_lines);
+    } else {
+        // Single scalar: tags: a
+        let renamed = rename_in_list(&[inline.as_str()], from, to)?;
+        lines[tags_idx] = format!("tags: {}", renamed.join(", "));
+    }
+
+    Some(format!("{}{}", lines.join("\n"), &content[fm_end..]))
+}
+
+/// Rename within raw list items (which may be quoted). `None` if `from` is absent.
+fn rename_in_list(items: &[&str], from: &str, to: &str) -> Option<Vec<String>> {
+    let unquote = |i: &str| i.trim_matches(|c| c == '"' || c == '\'').to_string();
+    if !items.iter().any(|i| unquote(i) == from) {
+        return None;
+    }
+    let has_to = items.iter().any(|i| unquote(i) == to);
+
+    let mut out: Vec<String> = Vec::new();
+    for item in items {
+        if unquote(item) != from {
+            out.push(item.to_string());
+        } else if !has_to && !out.iter().any(|o| unquote(o) == to) {
+            // Keep the original quoting style
+            let quote = item.chars().next().filter(|c| *c == '"' || *c == '\'');
+            out.push(match quote {
+                Some(q) => format!("{}{}{}", q, to, q),
+                None => to.to_string(),
+            });
+        }
+    }
+    Some(out)
+}
+
+/// Apply `rewrite` to the text of `content` outside code: fenced blocks and inline code
+/// spans are copied through as they are
+fn map_outside_code(content: &str, mut rewrite: impl FnMut(&str) -> String) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut in_fence = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            result.push_str(line);
+            continue;
+        }
+        if in_fence {
+            result.push_str(line);
+            continue;
+        }
+
+        let bytes = line.as_bytes();
+        let mut text_start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'`' {
+                i += 1;
+                continue;
+            }
+            let run = backtick_run(bytes, i);
+            match closing_backticks(bytes, i + run, run) {
+                Some(end) => {
+                    result.push_str(&rewrite(&line[text_start..i]));
+                    result.push_str(&line[i..end]);
+                    text_start = end;
+                    i = end;
+                }
+                // An unmatched run is literal text
+                None => i += run,
+            }
+        }
+        result.push_str(&rewrite(&line[text_start..]));
+    }
+    result
+}
+
+fn backtick_run(bytes: &[u8], from: usize) -> usize {
+    bytes[from..].iter().take_while(|&&b| b == b'`').count()
+}
+
+/// Offset just past the next run of exactly `len` backticks at or after `from`
+fn closing_backticks(bytes: &[u8], from: usize, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() {
+        if bytes[i] == b'`' {
+            let run = backtick_run(bytes, i);
+            if run == len {
+                return Some(i + run);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Rewrite `[[target]]` / `[[target#Heading]]` / `[[target|alias]]` wikilinks whose target
+/// matches one of `old_targets` (case-insensitive) to point at `new_target`, keeping any
+/// heading and alias. Links in fenced code blocks and inline code are left untouched.
+/// Returns None if nothing was rewritten.
+pub fn rewrite_wikilinks(
+    content: &str,
+    old_targets: &[String],
+    new_target: &str,
+) -> Option<String> {
+    let link_re = Regex::new(r"\[\[([^\]|#]+)(#[^\]|]*)?(\|[^\]]+)?\]\]").unwrap();
+    let old_lower: Vec<String> = old_targets.iter().map(|t| t.to_lowercase()).collect();
+
+    let mut changed = false;
+    let result = map_outside_code(content, |text| {
+        link_re
+            .replace_all(text, |caps: &regex::Captures| {
+                if old_lower.contains(&caps[1].trim().to_lowercase()) {
+                    changed = true;
+                    let heading = caps.get(2).map(|m| m.as_str()).unwrap_or("");
+                    let alias = caps.get(3).map(|m| m.as_str()).unwrap_or("");
+                    format!("[[{}{}{}]]", new_target, heading, alias)
+                } else {
+                    caps[0].to_string()
+                }
+            })
+            .into_owned()
+    });
+
+    changed.then_some(result)
+}
+
+/// Rewrite `[text](target.md)` links in the document at `doc_path` that resolve to
+/// `old_path` (case-insensitive) to point at `new_path`, keeping any `#anchor`. Rooted
+/// targets (`/notes/a.md`) stay rooted; others are made relative to the document's folder.
+/// Links in code are left untouched. Returns None if nothing was rewritten.
+pub fn rewrite_markdown_links(
+    content: &str,
+    doc_path: &str,
+    old_path: &str,
+    new_path: &str,
+) -> Option<String> {
+    let link_re = Regex::new(r"(!?)\[[^\]]*\]\(\s*(<?)([^)\s>]+)>?(?:\s+[^)]*)?\)").unwrap();
+    let base_dir = doc_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    let old_lower = old_path.to_lowercase();
+
+    let mut changed = false;
+    let result = map_outside_code(content, |text| {
+        link_re
+            .replace_all(text, |caps: &regex::Captures| {
+                let raw = &caps[3];
+                let points_at_old = resolve_markdown_target(raw, base_dir)
+                    .is_some_and(|resolved| resolved.to_lowercase() == old_lower);
+                if &caps[1] == "!" || !points_at_old {
+                    return caps[0].to_string();
+                }
+                changed = true;
+
+                let mut target = if raw.starts_with('/') {
+                    format!("/{}", new_path)
+                } else {
+                    relative_link(base_dir, new_path)
+                };
+                // Inside `<...>` spaces are allowed as they are
+                if caps[2].is_empty() {
+                    target = target.replace(' ', "%20");
+                }
+                if let Some(anchor) = raw.find('#') {
+                    target.push_str(&raw[anchor..]);
+                }
+
+                let whole = caps.get(0).unwrap();
+                let span = caps.get(3).unwrap();
+                let (start, end) = (span.start() - whole.start(), span.end() - whole.start());
+                format!("{}{}{}", &caps[0][..start], target, &caps[0][end..])
+            })
+            .into_owned()
+    });
+
+    changed.then_some(result)
+}
+
 fn infer_type(frontmatter_type: &Option<String>, path: &Path, org_root: &Path) -> String {
     // Check frontmatter first
     if let Some(t) = frontmatter_type {
@@ -193,3 +818,151 @@ fn format_yaml_field(key: &str, value: &serde_json::Value) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> OrgDocument {
+        parse_document(Path::new("/vault/note.md"), Path::new("/vault"), content)
+    }
+
+    #[test]
+    fn normalize_text_strips_bom_and_crlf() {
+        assert_eq!(normalize_text("\u{feff}a\r\nb\r\n"), "a\nb\n");
+        assert!(matches!(normalize_text("a\nb\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn frontmatter_with_bom_is_parsed() {
+        let doc = parse("\u{feff}---\ntitle: With BOM\ntype: task\n---\n\nBody\n");
+        assert_eq!(doc.title, "With BOM");
+        assert_eq!(doc.doc_type, "task");
+        assert!(doc.warnings.is_empty(), "{:?}", doc.warnings);
+    }
+
+    #[test]
+    fn frontmatter_with_crlf_is_parsed() {
+        let doc = parse("---\r\ntitle: Windows\r\ntags: [a, b]\r\n---\r\n# Heading\r\nBody\r\n");
+        assert_eq!(doc.title, "Windows");
+        assert_eq!(doc.tags, vec!["a", "b"]);
+        assert!(doc.warnings.is_empty(), "{:?}", doc.warnings);
+    }
+
+    #[test]
+    fn frontmatter_with_bom_and_crlf_is_parsed() {
+        let doc = parse("\u{feff}---\r\ntitle: Both\r\nstatus: open\r\n---\r\nBody\r\n");
+        assert_eq!(doc.title, "Both");
+        assert_eq!(doc.status.as_deref(), Some("open"));
+    }
+
+    #[test]
+    fn frontmatter_bounds_accept_bom_and_crlf() {
+        let content = "\u{feff}---\r\ntitle: x\r\n---\r\nBody";
+        let (inner, end) = frontmatter_bounds(content).unwrap();
+        assert_eq!(content[inner].trim(), "title: x");
+        assert_eq!(&content[end..], "\r\nBody");
+    }
+
+    fn old(targets: &[&str]) -> Vec<String> {
+        targets.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn wikilink_rewrite_keeps_aliases_and_headings() {
+        let content = "See [[old]], [[old|the old note]] and [[old#Setup|setup]].\n";
+        assert_eq!(
+            rewrite_wikilinks(content, &old(&["old"]), "new").unwrap(),
+            "See [[new]], [[new|the old note]] and [[new#Setup|setup]].\n"
+        );
+        assert_eq!(
+            rewrite_wikilinks("![[old#Intro]]\n", &old(&["old"]), "notes/new").unwrap(),
+            "![[notes/new#Intro]]\n"
+        );
+    }
+
+    #[test]
+    fn wikilink_rewrite_ignores_case() {
+        let content = "[[Old Note]] and [[notes/OLD NOTE|alias]]\n";
+        assert_eq!(
+            rewrite_wikilinks(content, &old(&["old note", "notes/old note"]), "new").unwrap(),
+            "[[new]] and [[new|alias]]\n"
+        );
+    }
+
+    #[test]
+    fn wikilink_rewrite_skips_code() {
+        let content = "```\n[[old]]\n```\nUse `[[old]]` or ``a ` [[old]]`` for [[old]].\n";
+        assert_eq!(
+            rewrite_wikilinks(content, &old(&["old"]), "new").unwrap(),
+            "```\n[[old]]\n```\nUse `[[old]]` or ``a ` [[old]]`` for [[new]].\n"
+        );
+        assert!(rewrite_wikilinks("~~~\n[[old]]\n~~~\n", &old(&["old"]), "new").is_none());
+        // An unclosed backtick doesn't start a code span
+        assert_eq!(
+            rewrite_wikilinks("a ` [[old]]\n", &old(&["old"]), "new").unwrap(),
+            "a ` [[new]]\n"
+        );
+    }
+
+    #[test]
+    fn wikilink_rewrite_leaves_other_links() {
+        assert!(rewrite_wikilinks("[[older]] [[x|old]]\n", &old(&["old"]), "new").is_none());
+    }
+
+    #[test]
+    fn heading_wikilinks_resolve_to_their_document() {
+        let doc = parse("[[old#Setup]] and [[other#Usage|usage]]\n");
+        assert_eq!(doc.links, vec!["old", "other"]);
+    }
+
+    #[test]
+    fn markdown_link_rewrite_is_relative_to_the_linking_note() {
+        let content = "See [the note](old.md#intro) and [again](./old.md \"title\").\n";
+        assert_eq!(
+            rewrite_markdown_links(content, "notes/a.md", "notes/old.md", "archive/old.md")
+                .unwrap(),
+            "See [the note](../archive/old.md#intro) and [again](../archive/old.md \"title\").\n"
+        );
+        assert_eq!(
+            rewrite_markdown_links(
+                "[x](../notes/Old.md)\n",
+                "daily/a.md",
+                "notes/old.md",
+                "notes/new.md"
+            )
+            .unwrap(),
+            "[x](../notes/new.md)\n"
+        );
+    }
+
+    #[test]
+    fn markdown_link_rewrite_keeps_rooted_and_encoded_forms() {
+        assert_eq!(
+            rewrite_markdown_links(
+                "[x](/notes/old.md)\n",
+                "a/b.md",
+                "notes/old.md",
+                "my notes/new.md"
+            )
+            .unwrap(),
+            "[x](/my%20notes/new.md)\n"
+        );
+        assert_eq!(
+            rewrite_markdown_links("[x](<old.md>)\n", "a.md", "old.md", "my notes/new.md").unwrap(),
+            "[x](<my notes/new.md>)\n"
+        );
+    }
+
+    #[test]
+    fn markdown_link_rewrite_skips_code_images_and_other_targets() {
+        let content = "`[x](old.md)` ![img](old.md) [y](other.md) [z](https://x.org/old.md)\n";
+        assert!(rewrite_markdown_links(content, "a.md", "old.md", "new.md").is_none());
+    }
+
+    #[test]
+    fn tab_indented_frontmatter_is_reported_after_crlf() {
+        let warnings = check_frontmatter_indentation("---\r\ntags:\r\n\t- a\r\n---\r\n");
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    }
+}
@@ -0,0 +1,63 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+
+/// Error returned by API handlers: the status plus a `{ "code", "message" }` body, so a
+/// client can tell a missing parameter from an invalid one without guessing from "400".
+/// A bare `StatusCode` converts into one (with `?` too), taking its code and message from
+/// the status's reason phrase.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    /// Machine-readable, e.g. `missing_param` or `invalid_action`
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    /// 400 for a required query or body field that wasn't given
+    pub fn missing(field: &str) -> Self {
+        Self::bad_request("missing_param", format!("`{}` is required", field))
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "bad_request",
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+            StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+            StatusCode::BAD_GATEWAY => "bad_gateway",
+            StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+            StatusCode::GATEWAY_TIMEOUT => "peer_timeout",
+            s if s.is_server_error() => "internal_error",
+            _ => "error",
+        };
+        let message = status.canonical_reason().unwrap_or("Error");
+        Self::new(status, code, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "code": self.code, "message": self.message });
+        (self.status, Json(body)).into_response()
+    }
+}
@@ -1,18 +1,95 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::{get, post},
-    Router,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post},
+    Extension, Router,
 };
+use std::net::SocketAddr;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use tracing::{error, info, warn};
 
-use crate::server::log_to_file;
-use crate::server::sync::compute_checksum;
+use crate::server::error::ApiError;
+use crate::server::peers::{
+    constant_time_eq, in_folder, split_host_port, with_peer_auth, PeerEntry, PeerHistory,
+    PeerSelf, INSTANCE_HEADER,
+};
+use crate::server::sync::{
+    compute_checksum, compute_checksum_like, extract_body, extract_frontmatter, replace_body,
+    SharedDocument,
+};
+use crate::server::resolve_safe_path;
 use crate::server::FederationState;
 
+/// Max cached search snippets before the cache is flushed
+const SNIPPET_CACHE_MAX: usize = 1024;
+/// Max concurrent file reads when building search snippets
+const SNIPPET_READ_CONCURRENCY: usize = 8;
+/// Cross-search returns whatever peers answered within this long; slower peers are dropped
+const CROSS_SEARCH_DEADLINE_MS: u64 = 3000;
+
+/// Search snippets keyed by (path, mtime, query)
+pub type SnippetCache = HashMap<(String, u64, String), String>;
+
+/// `/hello` gets this many times the configured per-minute limit, so polling isn't throttled
+const HELLO_RATE_MULTIPLIER: f64 = 4.0;
+/// Buckets idle this long are dropped once the table grows past `RATE_BUCKETS_MAX`
+const RATE_BUCKET_IDLE_SECS: u64 = 600;
+const RATE_BUCKETS_MAX: usize = 4096;
+
+/// Token buckets for inbound federation requests, keyed by (remote IP, is `/hello`).
+/// Each bucket holds up to a minute's allowance and refills continuously.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: std::sync::Mutex<HashMap<(std::net::IpAddr, bool), TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: std::time::Instant,
+}
+
+impl RateLimiter {
+    /// Take one token. On refusal, returns the seconds until a token is available.
+    fn check(&self, ip: std::net::IpAddr, hello: bool, per_minute: u32) -> Result<(), u64> {
+        let capacity = if hello {
+            per_minute as f64 * HELLO_RATE_MULTIPLIER
+        } else {
+            per_minute as f64
+        };
+        let per_sec = capacity / 60.0;
+        let now = std::time::Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > RATE_BUCKETS_MAX {
+            let idle = std::time::Duration::from_secs(RATE_BUCKET_IDLE_SECS);
+            buckets.retain(|_, b| now.duration_since(b.updated) < idle);
+        }
+
+        let bucket = buckets.entry((ip, hello)).or_insert(TokenBucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / per_sec).ceil().max(1.0) as u64)
+        }
+    }
+}
+
 // --- Request/Response types ---
 
 #[derive(Serialize)]
@@ -121,6 +198,22 @@ struct FileListItem {
 struct SingleFileQuery {
     #[serde(rename = "checksumOnly")]
     checksum_only: Option<String>,
+    /// `true` when the requester is adopting the document (recorded so it may push back)
+    adopt: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReceiveQuery {
+    /// `true` writes the document even if an identical copy is already in the inbox
+    force: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PushRequest {
+    content: String,
+    /// Origin checksum the pushed edit was based on
+    #[serde(rename = "baseChecksum")]
+    base_checksum: String,
 }
 
 #[derive(Serialize)]
@@ -152,12 +245,17 @@ struct CrossSearchQuery {
     doc_type: Option<String>,
     tag: Option<String>,
     limit: Option<usize>,
+    /// Skip this many aggregated results (each peer is asked for `offset + limit`)
+    offset: Option<usize>,
 }
 
 #[derive(Serialize)]
 struct CrossSearchResponse {
     query: String,
     results: Vec<CrossSearchResult>,
+    offset: usize,
+    /// Aggregated results available before paging
+    total: usize,
     #[serde(rename = "totalPeersQueried")]
     total_peers_queried: usize,
     #[serde(rename = "totalPeersResponded")]
@@ -186,6 +284,8 @@ struct CrossSearchResult {
 struct PeerSearchStats {
     count: usize,
     took: u64,
+    /// False if the peer errored or missed the cross-search deadline
+    responded: bool,
 }
 
 #[derive(Deserialize)]
@@ -214,6 +314,20 @@ struct AdoptRequest {
     target_path: Option<String>,
 }
 
+/// Outcome of one item in `POST /adopt-batch`
+#[derive(Serialize)]
+struct AdoptBatchItem {
+    #[serde(rename = "sourcePath")]
+    source_path: String,
+    success: bool,
+    #[serde(rename = "localPath", skip_serializing_if = "Option::is_none")]
+    local_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct SendRequest {
     #[serde(rename = "peerHost")]
@@ -221,6 +335,11 @@ struct SendRequest {
     #[serde(rename = "sourcePath")]
     source_path: String,
     message: Option<String>,
+    /// Also send the document's frontmatter (default: body, title and tags only)
+    #[serde(rename = "includeFrontmatter", default)]
+    include_frontmatter: bool,
+    /// Send only these frontmatter fields (implies `includeFrontmatter`)
+    fields: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -246,6 +365,8 @@ struct ReceiveDocument {
     tags: Option<Vec<String>>,
     #[serde(rename = "sourcePath")]
     source_path: String,
+    /// Sender's frontmatter, when it opted in
+    frontmatter: Option<serde_json::Map<String, serde_json::Value>>,
 }
 
 #[derive(Deserialize)]
@@ -266,34 +387,189 @@ struct RespondRequest {
     comment: Option<String>,
 }
 
+/// Adopted documents from one origin peer
+#[derive(Serialize)]
+struct OriginGroup {
+    #[serde(rename = "originHost")]
+    origin_host: String,
+    #[serde(rename = "originName")]
+    origin_name: String,
+    #[serde(rename = "originPeer")]
+    origin_peer: String,
+    /// Registry status of the origin ("online" | "offline" | "unknown" if not a known peer)
+    status: String,
+    count: usize,
+    /// Number of documents per sync-status
+    #[serde(rename = "syncStatus")]
+    sync_status: BTreeMap<String, usize>,
+    items: Vec<SharedDocument>,
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    #[serde(rename = "peerA")]
+    peer_a: Option<String>,
+    #[serde(rename = "peerB")]
+    peer_b: Option<String>,
+    path: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct DiffQuery {
     path: Option<String>,
 }
 
+/// Body of `POST /shared/push` and `POST /shared/unadopt`
+#[derive(Deserialize)]
+struct SharedPathRequest {
+    path: Option<String>,
+}
+
 // --- Build federation router ---
 
-pub fn create_federation_routes() -> Router<Arc<FederationState>> {
+pub fn create_federation_routes(state: Arc<FederationState>) -> Router<Arc<FederationState>> {
     Router::new()
         .route("/hello", get(hello))
-        .route("/peers", get(peers))
+        .route("/peers", get(peers).post(add_peer))
+        .route("/peers/{host}/{port}", delete(remove_peer))
+        .route("/peers/{host}/{port}/history", get(peer_history))
         .route("/search", get(search))
         .route("/files", get(list_files))
-        .route("/files/{*path}", get(get_file))
+        .route("/files/{*path}", get(get_file).put(put_file))
         .route("/cross-search", get(cross_search))
+        .route("/cross-search/ws", get(cross_search_ws))
         .route("/cross-files", get(cross_files))
         .route("/cross-file/{*path}", get(cross_file))
+        .route("/compare", get(compare))
         .route("/adopt", post(adopt))
+        .route("/adopt-batch", post(adopt_batch))
         .route("/send", post(send))
         .route("/receive", post(receive))
         .route("/shared", get(shared))
+        .route("/by-origin", get(by_origin))
+        .route("/shared/status", get(shared_status))
         .route("/shared/diff", get(shared_diff))
         .route("/shared/resolve", post(shared_resolve))
+        .route("/shared/push", post(shared_push))
+        .route("/shared/unadopt", post(shared_unadopt))
         .route("/shared/respond", post(shared_respond))
+        .route_layer(middleware::from_fn_with_state(Arc::clone(&state), require_auth))
+        .route_layer(middleware::from_fn_with_state(state, rate_limit))
+}
+
+/// Throttle each remote IP to `self.rateLimitPerMinute` requests (4x that for `/hello`),
+/// answering 429 with `Retry-After` once the bucket is empty. Loopback is exempt.
+async fn rate_limit(
+    State(state): State<Arc<FederationState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) if !addr.ip().is_loopback() => addr.ip(),
+        _ => return next.run(request).await,
+    };
+    let per_minute = state.peer_registry.get_self().await.rate_limit_per_minute();
+    if per_minute == 0 {
+        return next.run(request).await;
+    }
+
+    let hello = request.uri().path().ends_with("/hello");
+    match state.rate_limiter.check(ip, hello, per_minute) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            warn!("Rate limited {} on {} (retry in {}s)", ip, request.uri().path(), retry_after);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+            )
+                .into_response()
+        }
+    }
+}
+
+/// When `self.authToken` is configured, require `Authorization: Bearer <token>` on every
+/// federation route, where the token is either that one or a configured peer's
+/// `inboundToken`. Loopback connections (the local WebView) are trusted.
+async fn require_auth(
+    State(state): State<Arc<FederationState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let expected = match state.peer_registry.get_self().await.auth_token {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(next.run(request).await),
+    };
+
+    let is_loopback = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().is_loopback())
+        .unwrap_or(false);
+
+    let authorized = match bearer_token(request.headers()) {
+        Some(token) => {
+            constant_time_eq(token.as_bytes(), expected.as_bytes())
+                || !state.peer_registry.identities_for_token(Some(token)).await.is_empty()
+        }
+        None => false,
+    };
+
+    if authorized || is_loopback {
+        Ok(next.run(request).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED.into())
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
 }
 
 // --- Handlers ---
 
+/// The peer making a federation request, identified by the `inboundToken` it presents.
+/// The `X-Vitrum-Instance` header is not trusted for this: anyone can send it.
+struct Requester {
+    identities: Vec<String>,
+    /// Per-peer folder ACL (`allowedFolders`), applied on top of the shared folders; empty
+    /// for an unidentified requester while any peer has one
+    allowed_folders: Option<Vec<String>>,
+}
+
+impl Requester {
+    fn in_acl(&self, path: &str) -> bool {
+        match &self.allowed_folders {
+            Some(folders) => folders.iter().any(|f| in_folder(path, f)),
+            None => true,
+        }
+    }
+
+    fn can_read(&self, self_info: &PeerSelf, path: &str) -> bool {
+        self.in_acl(path) && self_info.can_read(path, &self.identities)
+    }
+
+    /// Write access needs an identified peer: an open write folder (no `allowedPeers`)
+    /// still doesn't accept pushes from a request without a recognised token
+    fn can_write(&self, self_info: &PeerSelf, path: &str) -> bool {
+        !self.identities.is_empty()
+            && self.in_acl(path)
+            && self_info.can_write(path, &self.identities)
+    }
+}
+
+async fn requester(state: &FederationState, headers: &HeaderMap) -> Requester {
+    let identities = state.peer_registry.identities_for_token(bearer_token(headers)).await;
+    let allowed_folders = state.peer_registry.allowed_folders_for(&identities).await;
+    Requester {
+        identities,
+        allowed_folders,
+    }
+}
+
 async fn hello(State(state): State<Arc<FederationState>>) -> Json<HelloResponse> {
     let self_info = state.peer_registry.get_self().await;
     let index = state.app_state.index.read().await;
@@ -302,12 +578,13 @@ async fn hello(State(state): State<Arc<FederationState>>) -> Json<HelloResponse>
     let doc_count = docs.len();
     let knowledge_count = docs.iter().filter(|d| d.doc_type == "knowledge").count();
     let task_count = docs.iter().filter(|d| d.doc_type == "task").count();
+    let shared_folders = self_info.shared_folder_paths();
 
     Json(HelloResponse {
         instance_id: self_info.instance_id,
         display_name: self_info.display_name,
         api_version: "1".to_string(),
-        shared_folders: self_info.shared_folders,
+        shared_folders,
         shared_tags: self_info.shared_tags,
         stats: HelloStats {
             document_count: doc_count,
@@ -320,14 +597,14 @@ async fn hello(State(state): State<Arc<FederationState>>) -> Json<HelloResponse>
 }
 
 async fn peers(State(state): State<Arc<FederationState>>) -> Json<PeersResponse> {
+    Json(peers_response(&state).await)
+}
+
+async fn peers_response(state: &FederationState) -> PeersResponse {
     let self_info = state.peer_registry.get_self().await;
-    let local = state.local_host.read().await;
-    let (host, port) = local
-        .as_ref()
-        .map(|(h, p)| (h.clone(), *p))
-        .unwrap_or(("localhost".to_string(), 3847));
+    let (host, port) = state.local_host.clone();
 
-    Json(PeersResponse {
+    PeersResponse {
         self_info: PeersSelfInfo {
             instance_id: self_info.instance_id,
             display_name: self_info.display_name,
@@ -335,64 +612,172 @@ async fn peers(State(state): State<Arc<FederationState>>) -> Json<PeersResponse>
             port,
         },
         peers: state.peer_registry.get_peer_status().await,
-    })
+    }
+}
+
+/// Connection info for handlers that need the caller's address; absent in tests and when
+/// the server isn't built with `into_make_service_with_connect_info`
+type Caller = Option<Extension<ConnectInfo<SocketAddr>>>;
+
+/// Managing the peer list is for the local app (loopback) or a holder of this instance's
+/// own `authToken`. A peer's `inboundToken` passes `require_auth` but is rejected here, so a
+/// peer can't register itself a fresh entry outside its ACL or remove others.
+fn authorize_peer_admin(
+    self_info: &PeerSelf,
+    caller: &Caller,
+    headers: &HeaderMap,
+) -> Result<(), ApiError> {
+    if caller
+        .as_ref()
+        .is_some_and(|Extension(ConnectInfo(addr))| addr.ip().is_loopback())
+    {
+        return Ok(());
+    }
+    let has_own_token = match (self_info.auth_token.as_deref(), bearer_token(headers)) {
+        (Some(expected), Some(token)) if !expected.is_empty() => {
+            constant_time_eq(token.as_bytes(), expected.as_bytes())
+        }
+        _ => false,
+    };
+    if has_own_token {
+        Ok(())
+    } else {
+        warn!("[federation] Rejected peer management from a non-local caller");
+        Err(StatusCode::FORBIDDEN.into())
+    }
+}
+
+/// POST /peers - Register a peer, persist it to `.vitrum-peers.json`, and poll it once
+async fn add_peer(
+    State(state): State<Arc<FederationState>>,
+    caller: Caller,
+    headers: HeaderMap,
+    Json(mut peer): Json<PeerEntry>,
+) -> Result<Json<PeersResponse>, ApiError> {
+    authorize_peer_admin(&state.peer_registry.get_self().await, &caller, &headers)?;
+    peer.normalize();
+    if let Err(e) = peer.validate() {
+        warn!("Rejected peer: {}", e);
+        return Err(ApiError::bad_request("invalid_peer", e));
+    }
+
+    match state.peer_registry.add_peer(peer).await {
+        Ok(true) => Ok(Json(peers_response(&state).await)),
+        Ok(false) => Err(StatusCode::CONFLICT.into()),
+        Err(e) => {
+            error!("Failed to add peer: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+/// DELETE /peers/{host}/{port} - Remove a configured peer and persist the change
+async fn remove_peer(
+    State(state): State<Arc<FederationState>>,
+    caller: Caller,
+    headers: HeaderMap,
+    Path((host, port)): Path<(String, u16)>,
+) -> Result<Json<PeersResponse>, ApiError> {
+    authorize_peer_admin(&state.peer_registry.get_self().await, &caller, &headers)?;
+    match state.peer_registry.remove_peer(&host, port).await {
+        Ok(true) => Ok(Json(peers_response(&state).await)),
+        Ok(false) => Err(StatusCode::NOT_FOUND.into()),
+        Err(e) => {
+            error!("Failed to remove peer: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+/// GET /peers/{host}/{port}/history - Recent poll latencies and uptime over the window
+async fn peer_history(
+    State(state): State<Arc<FederationState>>,
+    Path((host, port)): Path<(String, u16)>,
+) -> Result<Json<PeerHistory>, ApiError> {
+    match state.peer_registry.get_peer_history(&host, port).await {
+        Some(history) => Ok(Json(history)),
+        None => Err(StatusCode::NOT_FOUND.into()),
+    }
 }
 
 async fn search(
     State(state): State<Arc<FederationState>>,
+    headers: HeaderMap,
     Query(query): Query<SearchQuery>,
-) -> Result<Json<SearchResponse>, StatusCode> {
+) -> Result<Json<SearchResponse>, ApiError> {
     let q = query.q.as_deref().unwrap_or("");
     if q.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::missing("q"));
     }
 
     let self_info = state.peer_registry.get_self().await;
-    let index = state.app_state.index.read().await;
-
-    let results = index.search(q);
+    let requester = requester(&state, &headers).await;
     let limit = query.limit.unwrap_or(20);
 
-    // Filter to shared folders only
-    let items: Vec<SearchItem> = results
-        .into_iter()
-        .filter(|doc| {
-            self_info
-                .shared_folders
-                .iter()
-                .any(|f| doc.path.starts_with(f))
-        })
-        .filter(|doc| {
-            query
-                .doc_type
-                .as_ref()
-                .map(|t| doc.doc_type == *t)
-                .unwrap_or(true)
-        })
-        .filter(|doc| {
-            query
-                .tag
-                .as_ref()
-                .map(|t| doc.tags.contains(t))
-                .unwrap_or(true)
-        })
-        .take(limit)
-        .map(|doc| {
-            // Read content for snippet
-            let full_path = state.app_state.org_root.join(&doc.path);
-            let content = std::fs::read_to_string(&full_path).unwrap_or_default();
-            let snippet = extract_snippet(&content, q, 100);
-
-            SearchItem {
-                path: doc.path.clone(),
-                title: doc.title.clone(),
-                doc_type: doc.doc_type.clone(),
-                tags: doc.tags.clone(),
-                score: 0,
-                snippet,
+    // Collect matching docs (with mtimes for the snippet cache), then release the index lock
+    let matches: Vec<(SearchItem, u64)> = {
+        let index = state.app_state.index.read().await;
+        index
+            .search_scored(q)
+            .into_iter()
+            // Filter to shared folders this peer may read
+            .filter(|(doc, _)| requester.can_read(&self_info, &doc.path))
+            .filter(|(doc, _)| {
+                query
+                    .doc_type
+                    .as_ref()
+                    .map(|t| doc.doc_type == *t)
+                    .unwrap_or(true)
+            })
+            .filter(|(doc, _)| {
+                query
+                    .tag
+                    .as_ref()
+                    .map(|t| doc.tags.contains(t))
+                    .unwrap_or(true)
+            })
+            .take(limit)
+            .map(|(doc, score)| {
+                let mtime = index.get_document_mtime(&doc.path).unwrap_or(0);
+                let item = SearchItem {
+                    path: doc.path.clone(),
+                    title: doc.title.clone(),
+                    doc_type: doc.doc_type.clone(),
+                    tags: doc.tags.clone(),
+                    score,
+                    snippet: String::new(),
+                };
+                (item, mtime)
+            })
+            .collect()
+    };
+
+    // Fill snippets from cache, or read content asynchronously with bounded concurrency
+    let items: Vec<SearchItem> = futures::stream::iter(matches)
+        .map(|(mut item, mtime)| {
+            let state = Arc::clone(&state);
+            async move {
+                let key = (item.path.clone(), mtime, q.to_string());
+                if let Some(snippet) = state.snippet_cache.read().await.get(&key) {
+                    item.snippet = snippet.clone();
+                    return item;
+                }
+
+                let full_path = state.app_state.org_root.join(&item.path);
+                let content = tokio::fs::read_to_string(&full_path).await.unwrap_or_default();
+                item.snippet = extract_snippet(&content, q, 100);
+
+                let mut cache = state.snippet_cache.write().await;
+                if cache.len() >= SNIPPET_CACHE_MAX {
+                    cache.clear();
+                }
+                cache.insert(key, item.snippet.clone());
+                item
             }
         })
-        .collect();
+        .buffered(SNIPPET_READ_CONCURRENCY)
+        .collect()
+        .await;
 
     Ok(Json(SearchResponse {
         instance_id: self_info.instance_id,
@@ -405,25 +790,27 @@ async fn search(
 
 async fn list_files(
     State(state): State<Arc<FederationState>>,
+    headers: HeaderMap,
     Query(query): Query<FilesQuery>,
-) -> Json<FilesResponse> {
+) -> Result<Json<FilesResponse>, ApiError> {
     let self_info = state.peer_registry.get_self().await;
+    let requester = requester(&state, &headers).await;
+    if let Some(folder) = &query.folder {
+        if !requester.in_acl(folder) {
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    }
     let index = state.app_state.index.read().await;
     let docs = index.get_documents();
 
     let items: Vec<FileListItem> = docs
         .into_iter()
-        .filter(|d| {
-            self_info
-                .shared_folders
-                .iter()
-                .any(|f| d.path.starts_with(f))
-        })
+        .filter(|d| requester.can_read(&self_info, &d.path))
         .filter(|d| {
             query
                 .folder
                 .as_ref()
-                .map(|f| d.path.starts_with(f))
+                .map(|f| in_folder(&d.path, f))
                 .unwrap_or(true)
         })
         .filter(|d| {
@@ -443,53 +830,67 @@ async fn list_files(
         })
         .collect();
 
-    Json(FilesResponse {
+    Ok(Json(FilesResponse {
         instance_id: self_info.instance_id,
         display_name: self_info.display_name,
         count: items.len(),
         items,
-    })
+    }))
 }
 
 async fn get_file(
     State(state): State<Arc<FederationState>>,
+    headers: HeaderMap,
     Path(path): Path<String>,
     Query(query): Query<SingleFileQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let self_info = state.peer_registry.get_self().await;
 
-    // Check if path is within shared folders
-    let is_shared = self_info
-        .shared_folders
-        .iter()
-        .any(|f| path.starts_with(f));
-    if !is_shared {
-        return Err(StatusCode::FORBIDDEN);
+    // Check if path is within a shared folder this peer may read
+    let requester = requester(&state, &headers).await;
+    if !requester.can_read(&self_info, &path) {
+        return Err(StatusCode::FORBIDDEN.into());
     }
 
+    // Reject traversal out of the shared folder (e.g. "knowledge/../../etc/passwd")
+    let full_path = resolve_safe_path(&state.app_state.org_root, &path).ok_or_else(|| {
+        warn!("[federation] GET rejected - path traversal attempt: {}", path);
+        StatusCode::FORBIDDEN
+    })?;
+
     let index = state.app_state.index.read().await;
     let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
 
     // Read file content
-    let full_path = state.app_state.org_root.join(&path);
     let content = tokio::fs::read_to_string(&full_path)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
     // Parse body (after frontmatter)
-    let body = extract_body_from_content(&content);
+    let body = extract_body(&content);
+
+    let rev = state.sync_service.document_rev(&path, &body);
 
     // Support checksumOnly
     if query.checksum_only.as_deref() == Some("true") {
         let checksum = compute_checksum(&body);
         return Ok(Json(serde_json::json!({
             "checksum": checksum,
+            "rev": rev,
             "updated": doc.updated,
         })));
     }
 
     let checksum = compute_checksum(&body);
 
+    // Adoption is what lets a peer push edits back, so only record it for a peer that
+    // authenticated with its `inboundToken` and may write the document
+    if query.adopt.as_deref() == Some("true") && requester.can_write(&self_info, &path) {
+        if let Some(name) = requester.identities.first() {
+            state.sync_service.record_adoption(&path, name);
+        }
+    }
+
     // Parse frontmatter as generic value
     let frontmatter = parse_frontmatter_as_value(&content);
 
@@ -505,34 +906,77 @@ async fn get_file(
         "links": doc.links,
         "backlinks": doc.backlinks,
         "checksum": checksum,
+        "rev": rev,
     })))
 }
 
-async fn cross_search(
+/// PUT /files/{path} - Accept an edited body from a peer that adopted this document.
+/// Rejected with 409 if our copy changed since the peer last synced (`baseChecksum`).
+async fn put_file(
     State(state): State<Arc<FederationState>>,
-    Query(query): Query<CrossSearchQuery>,
-) -> Result<Json<CrossSearchResponse>, StatusCode> {
-    let q = query.q.as_deref().unwrap_or("");
-    if q.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Json(body): Json<PushRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let self_info = state.peer_registry.get_self().await;
+
+    let requester = requester(&state, &headers).await;
+    if !requester.can_write(&self_info, &path)
+        || !state.sync_service.is_adopted_by(&path, &requester.identities)
+    {
+        return Err(StatusCode::FORBIDDEN.into());
     }
 
-    let limit = query.limit.unwrap_or(20);
-    let online_peers = state.peer_registry.get_online_peers().await;
+    let full_path = resolve_safe_path(&state.app_state.org_root, &path).ok_or_else(|| {
+        warn!("[federation] PUT rejected - path traversal attempt: {}", path);
+        StatusCode::FORBIDDEN
+    })?;
 
-    let mut all_results: Vec<CrossSearchResult> = Vec::new();
-    let mut peer_results: HashMap<String, PeerSearchStats> = HashMap::new();
+    let content = tokio::fs::read_to_string(&full_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let current = extract_body(&content);
+    if compute_checksum_like(&body.base_checksum, &current) != body.base_checksum {
+        return Err(StatusCode::CONFLICT.into());
+    }
+
+    tokio::fs::write(&full_path, replace_body(&content, &body.content))
+        .await
+        .map_err(|e| {
+            error!("[federation] PUT failed to write {}: {}", path, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .danger_accept_invalid_certs(true)
-        .build()
-        .unwrap_or_default();
+    info!("[federation] Accepted push to {} from {}", path, requester.identities.join("/"));
 
-    let mut handles = Vec::new();
+    Ok(Json(serde_json::json!({
+        "path": path,
+        "checksum": compute_checksum(&body.content),
+        "rev": state.sync_service.document_rev(&path, &body.content),
+    })))
+}
+
+/// One peer's answer to a fanned-out search
+type PeerSearch = (String, PeerSearchStats, Vec<CrossSearchResult>);
+
+/// Send a search to every online peer concurrently, asking each for its top `peer_limit`.
+/// Returns the running tasks and the names of the peers queried.
+async fn spawn_peer_searches(
+    state: &FederationState,
+    query: &CrossSearchQuery,
+    q: &str,
+    peer_limit: usize,
+) -> (tokio::task::JoinSet<PeerSearch>, Vec<String>) {
+    let online_peers = state.peer_registry.get_online_peers().await;
+
+    let client = state.peer_registry.client();
+
+    let self_id = state.peer_registry.get_self().await.instance_id;
+    let mut tasks = tokio::task::JoinSet::new();
 
     for peer in &online_peers {
-        let mut params = vec![("q", q.to_string()), ("limit", limit.to_string())];
+        let mut params = vec![("q", q.to_string()), ("limit", peer_limit.to_string())];
         if let Some(ref t) = query.doc_type {
             params.push(("type", t.clone()));
         }
@@ -546,12 +990,18 @@ async fn cross_search(
         );
 
         let client = client.clone();
+        let self_id = self_id.clone();
+        let token = state.peer_registry.auth_token_for(&peer.host, peer.port).await;
         let peer_name = peer.name.clone();
         let peer_host = format!("{}:{}", peer.host, peer.port);
 
-        handles.push(tokio::spawn(async move {
+        tasks.spawn(async move {
             let start = std::time::Instant::now();
-            let resp = client.get(&url).query(&params).send().await;
+            let resp = with_peer_auth(client.get(&url), token.as_deref())
+                .header(INSTANCE_HEADER, &self_id)
+                .query(&params)
+                .send()
+                .await;
             let took = start.elapsed().as_millis() as u64;
 
             match resp {
@@ -589,47 +1039,160 @@ async fn cross_search(
                             })
                             .collect();
 
-                        (peer_name, count, took, results)
+                        (peer_name, PeerSearchStats { count, took, responded: true }, results)
                     } else {
-                        (peer_name, 0, took, vec![])
+                        (peer_name, PeerSearchStats { count: 0, took, responded: false }, vec![])
                     }
                 }
-                _ => (peer_name, 0, 0, vec![]),
+                _ => {
+                    let stats = PeerSearchStats { count: 0, took: 0, responded: false };
+                    (peer_name, stats, vec![])
+                }
             }
-        }));
+        });
+    }
+
+    let names = online_peers.into_iter().map(|p| p.name).collect();
+    (tasks, names)
+}
+
+/// Stats for a peer that didn't answer before the cross-search deadline
+fn missed_deadline() -> PeerSearchStats {
+    PeerSearchStats {
+        count: 0,
+        took: CROSS_SEARCH_DEADLINE_MS,
+        responded: false,
     }
+}
+
+fn sort_by_score(results: &mut [CrossSearchResult]) {
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
 
-    for handle in handles {
-        if let Ok((name, count, took, results)) = handle.await {
-            peer_results.insert(name, PeerSearchStats { count, took });
+async fn cross_search(
+    State(state): State<Arc<FederationState>>,
+    Query(query): Query<CrossSearchQuery>,
+) -> Result<Json<CrossSearchResponse>, ApiError> {
+    let q = query.q.as_deref().unwrap_or("");
+    if q.is_empty() {
+        return Err(ApiError::missing("q"));
+    }
+
+    let limit = query.limit.unwrap_or(20);
+    let offset = query.offset.unwrap_or(0);
+    // Each peer ranks independently, so page `offset` needs its top `offset + limit`
+    let (mut tasks, peer_names) = spawn_peer_searches(&state, &query, q, offset + limit).await;
+
+    let mut all_results: Vec<CrossSearchResult> = Vec::new();
+    let mut peer_results: HashMap<String, PeerSearchStats> = HashMap::new();
+
+    // Collect until every peer answered or the deadline passes; stragglers are aborted
+    let deadline = tokio::time::Instant::now()
+        + std::time::Duration::from_millis(CROSS_SEARCH_DEADLINE_MS);
+    while let Ok(Some(joined)) = tokio::time::timeout_at(deadline, tasks.join_next()).await {
+        if let Ok((name, stats, results)) = joined {
+            peer_results.insert(name, stats);
             all_results.extend(results);
         }
     }
+    tasks.abort_all();
+
+    for name in &peer_names {
+        peer_results.entry(name.clone()).or_insert_with(missed_deadline);
+    }
 
-    // Sort by score descending
-    all_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    all_results.truncate(limit);
+    sort_by_score(&mut all_results);
+    let total = all_results.len();
+    let results: Vec<CrossSearchResult> = all_results.into_iter().skip(offset).take(limit).collect();
 
-    let responded = peer_results.len();
+    let responded = peer_results.values().filter(|s| s.responded).count();
 
     Ok(Json(CrossSearchResponse {
         query: q.to_string(),
-        results: all_results,
-        total_peers_queried: online_peers.len(),
+        results,
+        offset,
+        total,
+        total_peers_queried: peer_names.len(),
         total_peers_responded: responded,
         peer_results,
     }))
 }
 
+/// GET /cross-search/ws - Cross-search over a WebSocket: one `peer-results` message per
+/// peer as it answers, then a `done` message with per-peer stats.
+async fn cross_search_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<FederationState>>,
+    Query(query): Query<CrossSearchQuery>,
+) -> Result<Response, ApiError> {
+    let q = query.q.clone().unwrap_or_default();
+    if q.is_empty() {
+        return Err(ApiError::missing("q"));
+    }
+    Ok(ws.on_upgrade(move |socket| stream_cross_search(socket, state, query, q)))
+}
+
+async fn stream_cross_search(
+    mut socket: WebSocket,
+    state: Arc<FederationState>,
+    query: CrossSearchQuery,
+    q: String,
+) {
+    let limit = query.limit.unwrap_or(20);
+    let (mut tasks, peer_names) = spawn_peer_searches(&state, &query, &q, limit).await;
+    let mut peer_results: HashMap<String, PeerSearchStats> = HashMap::new();
+
+    let deadline = tokio::time::Instant::now()
+        + std::time::Duration::from_millis(CROSS_SEARCH_DEADLINE_MS);
+    while let Ok(Some(joined)) = tokio::time::timeout_at(deadline, tasks.join_next()).await {
+        let (name, stats, mut results) = match joined {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        sort_by_score(&mut results);
+        results.truncate(limit);
+
+        let msg = serde_json::json!({
+            "type": "peer-results",
+            "peer": name,
+            "peerId": results.first().map(|r| r.peer_id.as_str()).unwrap_or(""),
+            "stats": stats,
+            "results": results,
+        });
+        peer_results.insert(name, stats);
+        if socket.send(Message::Text(msg.to_string().into())).await.is_err() {
+            // Client went away; stop waiting on the remaining peers
+            tasks.abort_all();
+            return;
+        }
+    }
+    tasks.abort_all();
+
+    for name in &peer_names {
+        peer_results.entry(name.clone()).or_insert_with(missed_deadline);
+    }
+
+    let responded = peer_results.values().filter(|s| s.responded).count();
+    let done = serde_json::json!({
+        "type": "done",
+        "query": q,
+        "totalPeersQueried": peer_names.len(),
+        "totalPeersResponded": responded,
+        "peerResults": peer_results,
+    });
+    let _ = socket.send(Message::Text(done.to_string().into())).await;
+    let _ = socket.send(Message::Close(None)).await;
+}
+
 async fn cross_files(
     State(state): State<Arc<FederationState>>,
     Query(query): Query<CrossFilesQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let peer_host = query.peer.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let peer_host = query.peer.as_deref().ok_or_else(|| ApiError::missing("peer"))?;
 
-    let parts: Vec<&str> = peer_host.split(':').collect();
-    let host = parts[0];
-    let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+    let (host, port) = split_host_port(peer_host).ok_or_else(|| {
+        ApiError::bad_request("invalid_peer", "`peer` must be host:port")
+    })?;
 
     let peers = state.peer_registry.get_peer_status().await;
     let peer = peers
@@ -650,21 +1213,21 @@ async fn cross_files(
         peer.protocol, peer.host, peer.port
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .danger_accept_invalid_certs(true)
-        .build()
-        .unwrap_or_default();
+    let client = state.peer_registry.client();
 
-    let resp = client
-        .get(&url)
+    let self_id = state.peer_registry.get_self().await.instance_id;
+    let token = state.peer_registry.auth_token_for(&peer.host, peer.port).await;
+    let resp = with_peer_auth(client.get(&url), token.as_deref())
+        .header(INSTANCE_HEADER, self_id)
         .query(&params)
         .send()
         .await
         .map_err(|_| StatusCode::GATEWAY_TIMEOUT)?;
 
     if !resp.status().is_success() {
-        return Err(StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY));
+        let status =
+            StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        return Err(status.into());
     }
 
     let data: serde_json::Value = resp.json().await.map_err(|_| StatusCode::BAD_GATEWAY)?;
@@ -675,12 +1238,22 @@ async fn cross_file(
     State(state): State<Arc<FederationState>>,
     Path(path): Path<String>,
     Query(query): Query<CrossFileQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let peer_host = query.peer.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let peer_host = query.peer.as_deref().ok_or_else(|| ApiError::missing("peer"))?;
+    let checksum_only = query.checksum_only.as_deref() == Some("true");
 
-    let parts: Vec<&str> = peer_host.split(':').collect();
-    let host = parts[0];
-    let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+    let data = fetch_peer_file(&state, peer_host, &path, checksum_only).await?;
+    Ok(Json(data))
+}
+
+/// Fetch a document from an online peer's `/files/{path}` (the cross-file proxy).
+async fn fetch_peer_file(
+    state: &FederationState,
+    peer_host: &str,
+    path: &str,
+    checksum_only: bool,
+) -> Result<serde_json::Value, StatusCode> {
+    let (host, port) = split_host_port(peer_host).ok_or(StatusCode::BAD_REQUEST)?;
 
     let peers = state.peer_registry.get_peer_status().await;
     let peer = peers
@@ -689,7 +1262,7 @@ async fn cross_file(
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let mut params = Vec::new();
-    if query.checksum_only.as_deref() == Some("true") {
+    if checksum_only {
         params.push(("checksumOnly", "true"));
     }
 
@@ -698,14 +1271,12 @@ async fn cross_file(
         peer.protocol, peer.host, peer.port, path
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .danger_accept_invalid_certs(true)
-        .build()
-        .unwrap_or_default();
+    let client = state.peer_registry.client();
 
-    let resp = client
-        .get(&url)
+    let self_id = state.peer_registry.get_self().await.instance_id;
+    let token = state.peer_registry.auth_token_for(&peer.host, peer.port).await;
+    let resp = with_peer_auth(client.get(&url), token.as_deref())
+        .header(INSTANCE_HEADER, self_id)
         .query(&params)
         .send()
         .await
@@ -715,17 +1286,115 @@ async fn cross_file(
         return Err(StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY));
     }
 
-    let data: serde_json::Value = resp.json().await.map_err(|_| StatusCode::BAD_GATEWAY)?;
-    Ok(Json(data))
+    resp.json().await.map_err(|_| StatusCode::BAD_GATEWAY)
+}
+
+/// One side of a `/compare`: the peer's copy, or why it couldn't be fetched
+#[derive(Serialize)]
+struct CompareSide {
+    peer: String,
+    /// "ok" | "offline" | "not-found" | "error"
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl CompareSide {
+    fn from_fetch(peer: &str, result: Result<serde_json::Value, StatusCode>) -> Self {
+        match result {
+            Ok(data) => CompareSide {
+                peer: peer.to_string(),
+                status: "ok".to_string(),
+                checksum: data["checksum"].as_str().map(String::from),
+                content: Some(data["content"].as_str().unwrap_or("").to_string()),
+                error: None,
+            },
+            Err(code) => CompareSide {
+                peer: peer.to_string(),
+                status: match code {
+                    StatusCode::GATEWAY_TIMEOUT => "offline",
+                    StatusCode::NOT_FOUND => "not-found",
+                    _ => "error",
+                }
+                .to_string(),
+                checksum: None,
+                content: None,
+                error: Some(code.to_string()),
+            },
+        }
+    }
+}
+
+async fn compare_side(state: &FederationState, peer_host: &str, path: &str) -> CompareSide {
+    let is_online = match split_host_port(peer_host) {
+        Some((host, port)) => state
+            .peer_registry
+            .get_online_peers()
+            .await
+            .iter()
+            .any(|p| p.host == host && p.port == port),
+        None => false,
+    };
+
+    // Skip the request entirely for peers known to be offline
+    let result = if is_online {
+        fetch_peer_file(state, peer_host, path, false).await
+    } else {
+        Err(StatusCode::GATEWAY_TIMEOUT)
+    };
+    CompareSide::from_fetch(peer_host, result)
+}
+
+/// GET /compare?peerA=&peerB=&path= - Diff two peers' copies of the same document.
+/// If either peer is unavailable the response is partial (`diff` omitted).
+async fn compare(
+    State(state): State<Arc<FederationState>>,
+    Query(query): Query<CompareQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let peer_a = query.peer_a.as_deref().ok_or_else(|| ApiError::missing("peerA"))?;
+    let peer_b = query.peer_b.as_deref().ok_or_else(|| ApiError::missing("peerB"))?;
+    let path = query.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+
+    let (a, b) = tokio::join!(
+        compare_side(&state, peer_a, path),
+        compare_side(&state, peer_b, path),
+    );
+
+    let (identical, diff) = match (&a.content, &b.content) {
+        (Some(ca), Some(cb)) => (
+            Some(ca == cb),
+            Some(diffy::create_patch(ca, cb).to_string()),
+        ),
+        _ => (None, None),
+    };
+
+    Ok(Json(serde_json::json!({
+        "path": path,
+        "complete": diff.is_some(),
+        "peerA": a,
+        "peerB": b,
+        "identical": identical,
+        "diff": diff,
+    })))
 }
 
 async fn adopt(
     State(state): State<Arc<FederationState>>,
     Json(body): Json<AdoptRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let parts: Vec<&str> = body.peer_host.split(':').collect();
-    let host = parts[0];
-    let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let local_path = body.target_path.as_deref().unwrap_or(&body.source_path);
+    if resolve_safe_path(&state.app_state.org_root, local_path).is_none() {
+        warn!("[federation] Adopt rejected - path traversal attempt: {}", local_path);
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    let (host, port) = split_host_port(&body.peer_host).ok_or_else(|| {
+        ApiError::bad_request("invalid_peer", "`peer` must be host:port")
+    })?;
 
     let peers = state.peer_registry.get_peer_status().await;
     let peer = peers
@@ -752,31 +1421,99 @@ async fn adopt(
             "checksum": checksum,
         }))),
         Err(e) => {
-            log_to_file(&format!("Adoption failed: {}", e));
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            error!("Adoption failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
         }
     }
 }
 
+/// POST /adopt-batch - Adopt several documents concurrently (bounded by
+/// `ORG_VIEWER_BATCH_CONCURRENCY`). Failed items are reported, not fatal.
+async fn adopt_batch(
+    State(state): State<Arc<FederationState>>,
+    Json(items): Json<Vec<AdoptRequest>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let peers = state.peer_registry.get_peer_status().await;
+
+    let jobs = items
+        .iter()
+        .map(|item| {
+            let peers = &peers;
+            let sync_service = &state.sync_service;
+            let job = async move {
+                let (host, port) = split_host_port(&item.peer_host)
+                    .ok_or_else(|| format!("Peer {} is not host:port", item.peer_host))?;
+
+                let peer = peers
+                    .iter()
+                    .find(|p| p.host == host && p.port == port && p.status == "online")
+                    .ok_or_else(|| format!("Peer {} is not online", item.peer_host))?;
+
+                sync_service
+                    .adopt_document(
+                        &item.peer_id,
+                        host,
+                        peer.port,
+                        &peer.protocol,
+                        peer.display_name.as_deref().unwrap_or(&peer.name),
+                        &item.source_path,
+                        item.target_path.as_deref(),
+                    )
+                    .await
+            };
+            (item.source_path.clone(), job)
+        })
+        .collect();
+
+    let results = state.sync_service.run_batch("adopt", jobs, None).await;
+
+    let items: Vec<AdoptBatchItem> = results
+        .into_iter()
+        .map(|(source_path, result)| match result {
+            Ok((local_path, checksum)) => AdoptBatchItem {
+                source_path,
+                success: true,
+                local_path: Some(local_path),
+                checksum: Some(checksum),
+                error: None,
+            },
+            Err(e) => {
+                error!("Adoption of {} failed: {}", source_path, e);
+                AdoptBatchItem {
+                    source_path,
+                    success: false,
+                    local_path: None,
+                    checksum: None,
+                    error: Some(e),
+                }
+            }
+        })
+        .collect();
+
+    let succeeded = items.iter().filter(|i| i.success).count();
+    Ok(Json(serde_json::json!({
+        "total": items.len(),
+        "succeeded": succeeded,
+        "failed": items.len() - succeeded,
+        "items": items,
+    })))
+}
+
 async fn send(
     State(state): State<Arc<FederationState>>,
     Json(body): Json<SendRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let index = state.app_state.index.read().await;
     let doc = index
         .get_document(&body.source_path)
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let self_info = state.peer_registry.get_self().await;
-    let local = state.local_host.read().await;
-    let host_str = local
-        .as_ref()
-        .map(|(h, p)| format!("{}:{}", h, p))
-        .unwrap_or_else(|| "localhost:3847".to_string());
+    let host_str = format!("{}:{}", state.local_host.0, state.local_host.1);
 
-    let parts: Vec<&str> = body.peer_host.split(':').collect();
-    let host = parts[0];
-    let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+    let (host, port) = split_host_port(&body.peer_host).ok_or_else(|| {
+        ApiError::bad_request("invalid_peer", "`peer` must be host:port")
+    })?;
 
     let peers = state.peer_registry.get_peer_status().await;
     let peer = peers
@@ -789,7 +1526,21 @@ async fn send(
     let content = tokio::fs::read_to_string(&full_path)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let body_content = extract_body_from_content(&content);
+    let body_content = extract_body(&content);
+
+    let frontmatter = if body.include_frontmatter || body.fields.is_some() {
+        match parse_frontmatter_as_value(&content) {
+            serde_json::Value::Object(mut map) => {
+                if let Some(fields) = &body.fields {
+                    map.retain(|key, _| fields.contains(key));
+                }
+                Some(map)
+            }
+            _ => None,
+        }
+    } else {
+        None
+    };
 
     let url = format!(
         "{}://{}:{}/api/federation/receive",
@@ -807,29 +1558,32 @@ async fn send(
             "content": body_content,
             "tags": doc.tags,
             "sourcePath": doc.path,
+            "frontmatter": frontmatter,
         },
         "message": body.message,
     });
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .danger_accept_invalid_certs(true)
-        .build()
-        .unwrap_or_default();
+    let client = state.peer_registry.client();
 
-    match client.post(&url).json(&payload).send().await {
+    let token = state.peer_registry.auth_token_for(&peer.host, peer.port).await;
+    match with_peer_auth(client.post(&url), token.as_deref())
+        .json(&payload)
+        .send()
+        .await
+    {
         Ok(resp) if resp.status().is_success() => Ok(Json(serde_json::json!({
             "success": true,
             "sentTo": peer.display_name.as_deref().unwrap_or(&peer.name),
         }))),
-        _ => Err(StatusCode::BAD_GATEWAY),
+        _ => Err(StatusCode::BAD_GATEWAY.into()),
     }
 }
 
 async fn receive(
     State(state): State<Arc<FederationState>>,
+    Query(query): Query<ReceiveQuery>,
     Json(body): Json<ReceiveRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let tags = body.document.tags.unwrap_or_default();
 
     match state.sync_service.write_incoming_document(
@@ -841,14 +1595,17 @@ async fn receive(
         &tags,
         &body.document.source_path,
         body.message.as_deref(),
+        body.document.frontmatter.as_ref(),
+        query.force.as_deref() == Some("true"),
     ) {
-        Ok(inbox_path) => Ok(Json(serde_json::json!({
+        Ok((inbox_path, written)) => Ok(Json(serde_json::json!({
             "accepted": true,
             "inboxPath": inbox_path,
+            "duplicate": !written,
         }))),
         Err(e) => {
-            log_to_file(&format!("Failed to write incoming document: {}", e));
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            error!("Failed to write incoming document: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
         }
     }
 }
@@ -861,32 +1618,96 @@ async fn shared(State(state): State<Arc<FederationState>>) -> Json<serde_json::V
     }))
 }
 
+/// GET /by-origin - Adopted documents grouped by origin peer, with sync-status counts
+async fn by_origin(State(state): State<Arc<FederationState>>) -> Json<serde_json::Value> {
+    let shared = state.sync_service.get_shared_documents().await;
+    let peers = state.peer_registry.get_peer_status().await;
+
+    let mut groups: BTreeMap<String, OriginGroup> = BTreeMap::new();
+    for doc in shared {
+        let fed = &doc.federation;
+        let group = groups.entry(fed.origin_host.clone()).or_insert_with(|| {
+            let status = split_host_port(&fed.origin_host)
+                .and_then(|(host, port)| peers.iter().find(|p| p.host == host && p.port == port))
+                .map(|p| p.status.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            OriginGroup {
+                origin_host: fed.origin_host.clone(),
+                origin_name: fed.origin_name.clone(),
+                origin_peer: fed.origin_peer.clone(),
+                status,
+                count: 0,
+                sync_status: BTreeMap::new(),
+                items: Vec::new(),
+            }
+        });
+
+        group.count += 1;
+        *group.sync_status.entry(fed.sync_status.clone()).or_default() += 1;
+        group.items.push(doc);
+    }
+
+    let groups: Vec<OriginGroup> = groups.into_values().collect();
+    Json(serde_json::json!({
+        "count": groups.len(),
+        "origins": groups,
+    }))
+}
+
+async fn shared_status(
+    State(state): State<Arc<FederationState>>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = query.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+    if resolve_safe_path(&state.app_state.org_root, path).is_none() {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    match state.sync_service.get_federation_meta(path).await {
+        Some(meta) => Ok(Json(serde_json::to_value(meta).unwrap())),
+        None => Err(StatusCode::NOT_FOUND.into()),
+    }
+}
+
 async fn shared_diff(
     State(state): State<Arc<FederationState>>,
     Query(query): Query<DiffQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let path = query.path.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = query.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+    if resolve_safe_path(&state.app_state.org_root, path).is_none() {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
 
     match state.sync_service.get_conflict_diff(path).await {
         Some(diff) => Ok(Json(serde_json::to_value(diff).unwrap())),
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(StatusCode::NOT_FOUND.into()),
     }
 }
 
 async fn shared_resolve(
     State(state): State<Arc<FederationState>>,
     Json(body): Json<ResolveRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let path = body.path.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
-    let action = body.action.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = body.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+    if resolve_safe_path(&state.app_state.org_root, path).is_none() {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    let action = body.action.as_deref().ok_or_else(|| ApiError::missing("action"))?;
 
     let valid = ["accept-origin", "keep-local", "merge", "reject"];
     if !valid.contains(&action) {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request(
+            "invalid_action",
+            format!("Unknown action `{}`; expected one of {}", action, valid.join(", ")),
+        ));
     }
 
     if action == "merge" && body.merged_content.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request(
+            "missing_merged_content",
+            "`mergedContent` is required for the merge action",
+        ));
     }
 
     let success = state
@@ -906,14 +1727,71 @@ async fn shared_resolve(
             "action": action,
         })))
     } else {
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
+        Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+    }
+}
+
+/// POST /shared/push - Send a locally modified adopted document back to its origin
+async fn shared_push(
+    State(state): State<Arc<FederationState>>,
+    Json(body): Json<SharedPathRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = body.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+    if resolve_safe_path(&state.app_state.org_root, path).is_none() {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    let fed = state
+        .sync_service
+        .get_federation_meta(path)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if fed.sync_status != "local-modified" {
+        return Err(StatusCode::CONFLICT.into());
+    }
+
+    match state.sync_service.push_to_origin(path).await {
+        Ok(checksum) => Ok(Json(serde_json::json!({
+            "success": true,
+            "path": path,
+            "checksum": checksum,
+        }))),
+        Err(e) => {
+            error!("Push to origin failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+/// POST /shared/unadopt - Stop tracking an adopted document; the file stays as a plain note
+async fn shared_unadopt(
+    State(state): State<Arc<FederationState>>,
+    Json(body): Json<SharedPathRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = body.path.as_deref().ok_or_else(|| ApiError::missing("path"))?;
+    if resolve_safe_path(&state.app_state.org_root, path).is_none() {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    match state.sync_service.unadopt(path).await {
+        Ok(Some(fed)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "path": path,
+            "originPath": fed.origin_path,
+            "originName": fed.origin_name,
+        }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into()),
+        Err(e) => {
+            error!("Unadopt failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
     }
 }
 
 async fn shared_respond(
     State(state): State<Arc<FederationState>>,
     Json(body): Json<RespondRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     if body.action == "rejected" {
         if let Some(comment) = &body.comment {
             let _ = state.sync_service.write_incoming_document(
@@ -931,6 +1809,8 @@ async fn shared_respond(
                 &["federation".to_string(), "resolution".to_string()],
                 &body.original_path,
                 Some(comment.as_str()),
+                None,
+                false,
             );
         }
     }
@@ -968,36 +1848,12 @@ fn extract_snippet(content: &str, query: &str, context_length: usize) -> String
     }
 }
 
-fn extract_body_from_content(content: &str) -> String {
-    if !content.starts_with("---") {
-        return content.to_string();
-    }
-    let rest = &content[3..];
-    match rest.find("---") {
-        Some(idx) => {
-            let after = &rest[idx + 3..];
-            if after.starts_with('\n') {
-                after[1..].to_string()
-            } else {
-                after.to_string()
-            }
-        }
-        None => content.to_string(),
-    }
-}
-
 fn parse_frontmatter_as_value(content: &str) -> serde_json::Value {
-    if !content.starts_with("---") {
-        return serde_json::Value::Object(serde_json::Map::new());
-    }
-    let rest = &content[3..];
-    let end = match rest.find("---") {
-        Some(idx) => idx,
+    let fm_str = match extract_frontmatter(content) {
+        Some(fm) => fm,
         None => return serde_json::Value::Object(serde_json::Map::new()),
     };
 
-    let fm_str = &rest[..end];
-
     // Use gray_matter for proper parsing
     let full = format!("---{}---\n", fm_str);
     let matter = gray_matter::Matter::<gray_matter::engine::YAML>::new();
@@ -1031,3 +1887,97 @@ fn pod_to_json(pod: &gray_matter::Pod) -> serde_json::Value {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::peers::{SharedFolder, SharedPermission};
+
+    fn self_sharing(permission: SharedPermission) -> PeerSelf {
+        PeerSelf {
+            instance_id: "me".to_string(),
+            display_name: "Me".to_string(),
+            shared_folders: vec![SharedFolder {
+                path: "collab/".to_string(),
+                permission,
+                allowed_peers: Vec::new(),
+            }],
+            shared_tags: Vec::new(),
+            auth_token: None,
+            poll_interval_secs: None,
+            sync_interval_secs: None,
+            rate_limit_per_minute: None,
+        }
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    fn from(ip: [u8; 4]) -> Caller {
+        Some(Extension(ConnectInfo(SocketAddr::from((ip, 40000)))))
+    }
+
+    #[test]
+    fn peer_tokens_cannot_manage_peers() {
+        let mut self_info = self_sharing(SharedPermission::Write);
+        self_info.auth_token = Some("own-secret".to_string());
+        let remote = from([192, 168, 1, 20]);
+
+        let denied = authorize_peer_admin(&self_info, &remote, &bearer("laptop-secret"));
+        assert_eq!(
+            denied.unwrap_err().into_response().status(),
+            StatusCode::FORBIDDEN
+        );
+        assert!(authorize_peer_admin(&self_info, &remote, &HeaderMap::new()).is_err());
+        assert!(authorize_peer_admin(&self_info, &None, &bearer("laptop-secret")).is_err());
+        assert!(authorize_peer_admin(&self_info, &remote, &bearer("own-secret")).is_ok());
+        assert!(authorize_peer_admin(&self_info, &from([127, 0, 0, 1]), &HeaderMap::new()).is_ok());
+    }
+
+    #[test]
+    fn peer_management_is_local_only_without_an_auth_token() {
+        let self_info = self_sharing(SharedPermission::Write);
+        let remote = from([192, 168, 1, 20]);
+        assert!(authorize_peer_admin(&self_info, &remote, &HeaderMap::new()).is_err());
+        assert!(authorize_peer_admin(&self_info, &remote, &bearer("")).is_err());
+        assert!(authorize_peer_admin(&self_info, &from([127, 0, 0, 1]), &HeaderMap::new()).is_ok());
+    }
+
+    #[test]
+    fn writes_need_an_identified_peer_and_a_write_folder() {
+        let laptop = Requester {
+            identities: vec!["laptop".to_string()],
+            allowed_folders: None,
+        };
+        let anonymous = Requester {
+            identities: Vec::new(),
+            allowed_folders: None,
+        };
+        let write = self_sharing(SharedPermission::Write);
+        let read = self_sharing(SharedPermission::Read);
+
+        assert!(laptop.can_write(&write, "collab/a.md"));
+        assert!(!laptop.can_write(&read, "collab/a.md"));
+        assert!(laptop.can_read(&read, "collab/a.md"));
+        assert!(!anonymous.can_write(&write, "collab/a.md"));
+        assert!(anonymous.can_read(&write, "collab/a.md"));
+    }
+
+    #[test]
+    fn folder_acl_limits_writes_too() {
+        let laptop = Requester {
+            identities: vec!["laptop".to_string()],
+            allowed_folders: Some(vec!["collab/work".to_string()]),
+        };
+        let write = self_sharing(SharedPermission::Write);
+        assert!(laptop.can_write(&write, "collab/work/a.md"));
+        assert!(!laptop.can_write(&write, "collab/work-private/a.md"));
+        assert!(!laptop.can_write(&write, "collab/a.md"));
+    }
+}
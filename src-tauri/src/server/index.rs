@@ -1,11 +1,240 @@
+use crate::server::config::state_file;
 use crate::server::document::{parse_document, OrgDocument};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
+use tracing::{error, info, warn};
 use walkdir::WalkDir;
 
-const INDEX_FILENAME: &str = ".vitrum-index.json";
+const INDEX_FILENAME: &str = ".vitrum-index.json.gz";
+/// Uncompressed cache written by earlier versions; read if no gzipped cache exists yet and
+/// removed on the next save
+const LEGACY_INDEX_FILENAME: &str = ".vitrum-index.json";
+/// Bump when `OrgDocument` gains fields, or existing ones are derived differently, so that
+/// cached entries would be stale
+const INDEX_VERSION: u32 = 8;
+
+/// Default cap on indexed documents — a guard against pointing at e.g. a home directory
+pub const DEFAULT_MAX_DOCUMENTS: usize = 100_000;
+
+/// Default size cap for a single markdown file; larger ones (data dumps) are not indexed
+pub const DEFAULT_MAX_FILE_MB: u64 = 5;
+
+/// Top-level directories never indexed
+pub const EXCLUDED_DIRS: &[&str] = &[
+    "node_modules",
+    ".git",
+    ".obsidian",
+    "scratchpad",
+    "dist",
+    "build",
+    ".next",
+    "target",
+    "x",       // Twitter archive
+    "archive", // Semantic archive (emails, tickets, research, reports)
+];
+
+/// Log indexing progress every this many parsed files
+const PROGRESS_EVERY: usize = 1000;
+
+/// Search score bonuses when the query is a prefix of the title / of a word in the title.
+/// Large enough to sort above any interior fuzzy match.
+const TITLE_PREFIX_BOOST: i64 = 1000;
+const WORD_PREFIX_BOOST: i64 = 500;
+
+/// Guards for `mode=regex` searches. The regex crate runs in linear time, so these bound
+/// compile cost and memory rather than backtracking.
+const MAX_REGEX_QUERY_LEN: usize = 256;
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// How [`DocumentIndex::search`] matches a query against titles, paths and tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Fuzzy match, with the [`ParsedQuery`] filter syntax
+    #[default]
+    Fuzzy,
+    /// Case-insensitive contains
+    Substring,
+    /// Case-insensitive, on word boundaries (`API` doesn't match "capital")
+    Word,
+    /// Case-insensitive regular expression
+    Regex,
+}
+
+/// Multipliers applied to each field's fuzzy-match score in [`DocumentIndex::search`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchWeights {
+    pub title: i64,
+    pub path: i64,
+    pub tags: i64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        SearchWeights {
+            title: 3,
+            path: 1,
+            tags: 2,
+        }
+    }
+}
+
+impl SearchWeights {
+    /// Parse `title=3,path=1,tags=2`; omitted fields keep their defaults
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut weights = SearchWeights::default();
+        for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (field, value) = part.split_once('=')?;
+            let value = value.trim().parse::<i64>().ok().filter(|v| *v >= 0)?;
+            match field.trim() {
+                "title" => weights.title = value,
+                "path" => weights.path = value,
+                "tags" => weights.tags = value,
+                _ => return None,
+            }
+        }
+        Some(weights)
+    }
+}
+
+/// A search query split into its parts. Supports `tag:`, `type:` and `status:` filters,
+/// `"quoted phrases"` and `-excluded` terms (any of those prefixed with `-`), all ANDed;
+/// the remaining bare words are fuzzy-matched as before.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// Bare words, fuzzy-matched together
+    pub terms: Vec<String>,
+    /// Lowercased phrases that must appear in the title, path or a tag
+    pub phrases: Vec<String>,
+    /// Lowercased `(field, value)` filters that must all match
+    pub filters: Vec<(QueryField, String)>,
+    /// Lowercased words or phrases a match must not contain
+    pub excluded: Vec<String>,
+    /// Lowercased filters a match must not satisfy (`-tag:archived`)
+    pub excluded_filters: Vec<(QueryField, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    Tag,
+    Type,
+    Status,
+}
+
+impl QueryField {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "tag" | "tags" => Some(QueryField::Tag),
+            "type" => Some(QueryField::Type),
+            "status" => Some(QueryField::Status),
+            _ => None,
+        }
+    }
+
+    /// `tag:` also matches nested tags (`tag:project` matches `project/alpha`)
+    fn matches(self, doc: &OrgDocument, value: &str) -> bool {
+        match self {
+            QueryField::Tag => doc.tags.iter().any(|t| {
+                let t = t.to_lowercase();
+                t == value || t.strip_prefix(value).is_some_and(|rest| rest.starts_with('/'))
+            }),
+            QueryField::Type => doc.doc_type.to_lowercase() == value,
+            QueryField::Status => {
+                doc.status.as_deref().map(str::to_lowercase).as_deref() == Some(value)
+            }
+        }
+    }
+}
+
+impl ParsedQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = ParsedQuery::default();
+        let mut chars = query.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let negated = chars.next_if_eq(&'-').is_some();
+            let mut token = String::new();
+            let mut quoted = false;
+            while let Some(&c) = chars.peek() {
+                if c == '"' {
+                    // A quote runs to the closing quote (or the end), spaces included
+                    chars.next();
+                    quoted = true;
+                    for c in chars.by_ref() {
+                        if c == '"' {
+                            break;
+                        }
+                        token.push(c);
+                    }
+                } else if c.is_whitespace() {
+                    break;
+                } else {
+                    token.push(c);
+                    chars.next();
+                }
+            }
+
+            let token = token.trim();
+            if token.is_empty() {
+                // A lone `-` or `""` is just a word
+                if negated && !quoted {
+                    parsed.terms.push("-".to_string());
+                }
+                continue;
+            }
+            let filter = if quoted {
+                None
+            } else {
+                token.split_once(':').and_then(|(name, value)| {
+                    let field = QueryField::parse(name)?;
+                    Some((field, value.to_lowercase()))
+                })
+            };
+
+            match (filter, negated) {
+                (Some(filter), false) if !filter.1.is_empty() => parsed.filters.push(filter),
+                (Some(filter), true) if !filter.1.is_empty() => {
+                    parsed.excluded_filters.push(filter)
+                }
+                (_, true) => parsed.excluded.push(token.to_lowercase()),
+                (_, false) if quoted => parsed.phrases.push(token.to_lowercase()),
+                (_, false) => parsed.terms.push(token.to_string()),
+            }
+        }
+        parsed
+    }
+
+    /// Nothing but bare words: searched exactly as the raw query always was
+    pub fn is_plain(&self) -> bool {
+        self.phrases.is_empty()
+            && self.filters.is_empty()
+            && self.excluded.is_empty()
+            && self.excluded_filters.is_empty()
+    }
+
+    /// Whether `doc` passes the filters, phrases and exclusions (bare words aside)
+    pub fn matches(&self, doc: &OrgDocument) -> bool {
+        let contains = |needle: &str| {
+            doc.title.to_lowercase().contains(needle)
+                || doc.path.to_lowercase().contains(needle)
+                || doc.tags.iter().any(|t| t.to_lowercase().contains(needle))
+        };
+        self.filters.iter().all(|(field, value)| field.matches(doc, value))
+            && !self.excluded_filters.iter().any(|(field, value)| field.matches(doc, value))
+            && self.phrases.iter().all(|p| contains(p))
+            && !self.excluded.iter().any(|e| contains(e))
+    }
+}
 
 /// Cached entry with modification time for incremental updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,20 +253,114 @@ pub struct PersistedIndex {
     pub entries: HashMap<String, CachedEntry>,
 }
 
+/// The cache file as first read: the version is checked before any entry is decoded, and
+/// entries are decoded one at a time so one that no longer fits `CachedEntry` only costs
+/// a re-parse of that file rather than the whole cache
+#[derive(Deserialize)]
+struct RawPersistedIndex {
+    version: u32,
+    #[serde(default)]
+    entries: HashMap<String, serde_json::Value>,
+}
+
 impl Default for PersistedIndex {
     fn default() -> Self {
         Self {
-            version: 1,
+            version: INDEX_VERSION,
             entries: HashMap::new(),
         }
     }
 }
 
+/// The org root holds more markdown files than the configured limit
+#[derive(Debug)]
+pub struct DocumentLimitExceeded {
+    pub limit: usize,
+    pub org_root: PathBuf,
+}
+
+impl std::fmt::Display for DocumentLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Document limit exceeded: more than {} markdown files under {}. Check your org root, or raise ORG_VIEWER_MAX_DOCUMENTS.",
+            self.limit,
+            self.org_root.display()
+        )
+    }
+}
+
+impl std::error::Error for DocumentLimitExceeded {}
+
+/// A wikilink whose target matched more than one document, so it links to none
+#[derive(Debug, Clone, Serialize)]
+pub struct AmbiguousLink {
+    /// Document containing the link
+    pub source: String,
+    pub link: String,
+    pub candidates: Vec<String>,
+}
+
+/// What an [`IndexDiagnostic`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticKind {
+    /// Left out by an exclusion rule (excluded folder, `excludeFiles`, projects/ layout)
+    Excluded,
+    /// Larger than `maxFileMb`
+    TooLarge,
+    InvalidUtf8,
+    /// Indexed, but with parse warnings (e.g. unreadable frontmatter)
+    Parse,
+}
+
+/// A non-fatal indexing problem: why a file is missing from the index, or what went wrong
+/// parsing it
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexDiagnostic {
+    pub path: String,
+    pub kind: DiagnosticKind,
+    pub reason: String,
+    /// When the problem was recorded (RFC 3339)
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+const REASON_EXCLUDE_FILES: &str = "matches an excludeFiles pattern";
+const REASON_EXCLUDED_DIR: &str = "inside an excluded folder";
+const REASON_PROJECT_FILE: &str =
+    "only README.md and CLAUDE.md are indexed directly under projects/<name>/";
+const REASON_PROJECT_DIR: &str = "folders below projects/<name>/ are not indexed";
+const REASON_HIDDEN: &str = "hidden file or folder";
+
 pub struct DocumentIndex {
     org_root: PathBuf,
     documents: HashMap<String, OrgDocument>,
     /// Modification times for incremental updates
     mtimes: HashMap<String, u64>,
+    /// Links left unresolved by the last backlink rebuild because they were ambiguous
+    ambiguous_links: Vec<AmbiguousLink>,
+    /// Problems from the last load/build and later refreshes, by relative path
+    diagnostics: HashMap<String, IndexDiagnostic>,
+    /// Lowercased title → paths of documents with that title (sorted)
+    titles: HashMap<String, Vec<String>>,
+    /// Folder holding the cache file (the org root unless `ORG_VIEWER_STATE_DIR` is set)
+    state_dir: PathBuf,
+    max_documents: usize,
+    max_file_bytes: u64,
+    /// `excludeFiles` patterns (see [`matches_excluded_file`])
+    excluded_files: Vec<String>,
+    /// Descend into symlinked folders (see [`walk_vault`])
+    follow_symlinks: bool,
+    search_weights: SearchWeights,
+    /// Bumped on every document mutation so clients can cheaply detect stale caches.
+    /// Process-local: starts at 0 on each launch.
+    generation: u64,
+    /// Set while the real index is still being built in the background (see
+    /// [`DocumentIndex::with_pending_build`]): paths changed in the meantime, replayed onto
+    /// the built index by `replace_with`
+    touched_during_build: Option<HashSet<PathBuf>>,
 }
 
 impl DocumentIndex {
@@ -46,31 +369,138 @@ impl DocumentIndex {
             org_root: org_root.to_path_buf(),
             documents: HashMap::new(),
             mtimes: HashMap::new(),
+            ambiguous_links: Vec::new(),
+            diagnostics: HashMap::new(),
+            titles: HashMap::new(),
+            state_dir: org_root.to_path_buf(),
+            max_documents: DEFAULT_MAX_DOCUMENTS,
+            max_file_bytes: DEFAULT_MAX_FILE_MB * 1024 * 1024,
+            excluded_files: Vec::new(),
+            follow_symlinks: false,
+            search_weights: SearchWeights::default(),
+            generation: 0,
+            touched_during_build: None,
+        }
+    }
+
+    /// Keep the cache file in `state_dir` instead of the org root
+    pub fn with_state_dir(mut self, state_dir: PathBuf) -> Self {
+        self.state_dir = state_dir;
+        self
+    }
+
+    /// Stand-in served while the real index builds in the background: it never saves the
+    /// cache (that would overwrite it with a near-empty one), and remembers which paths it
+    /// was told about so `replace_with` can apply them to the built index
+    pub fn with_pending_build(mut self) -> Self {
+        self.touched_during_build = Some(HashSet::new());
+        self
+    }
+
+    /// Cap the number of documents a load/build will index
+    pub fn with_max_documents(mut self, max_documents: usize) -> Self {
+        self.max_documents = max_documents;
+        self
+    }
+
+    /// Skip markdown files larger than `max_file_mb` megabytes
+    pub fn with_max_file_mb(mut self, max_file_mb: u64) -> Self {
+        self.max_file_bytes = max_file_mb.saturating_mul(1024 * 1024);
+        self
+    }
+
+    /// Never index files matching these `excludeFiles` patterns
+    pub fn with_excluded_files(mut self, patterns: Vec<String>) -> Self {
+        self.excluded_files = patterns;
+        self
+    }
+
+    /// Index the contents of symlinked folders too
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Field weights used to rank search results
+    pub fn with_search_weights(mut self, weights: SearchWeights) -> Self {
+        self.search_weights = weights;
+        self
+    }
+
+    fn limit_exceeded(&self) -> DocumentLimitExceeded {
+        DocumentLimitExceeded {
+            limit: self.max_documents,
+            org_root: self.org_root.clone(),
         }
     }
 
     /// Get path to the persisted index file
     fn index_path(&self) -> PathBuf {
-        self.org_root.join(INDEX_FILENAME)
+        state_file(&self.org_root, &self.state_dir, INDEX_FILENAME)
     }
 
-    /// Load persisted index from disk, or return None if not found/invalid
-    fn load_persisted(&self) -> Option<PersistedIndex> {
+    /// Read the cache file: the gzipped one, else the legacy uncompressed one
+    fn read_persisted(&self) -> Option<std::io::Result<String>> {
         let path = self.index_path();
-        if !path.exists() {
-            return None;
+        if path.exists() {
+            return Some(std::fs::File::open(&path).and_then(|file| {
+                let mut content = String::new();
+                GzDecoder::new(std::io::BufReader::new(file)).read_to_string(&mut content)?;
+                Ok(content)
+            }));
+        }
+
+        let legacy = state_file(&self.org_root, &self.state_dir, LEGACY_INDEX_FILENAME);
+        if legacy.exists() {
+            return Some(std::fs::read_to_string(&legacy));
         }
+        None
+    }
 
-        match std::fs::read_to_string(&path) {
-            Ok(content) => match serde_json::from_str(&content) {
-                Ok(index) => Some(index),
+    /// Load persisted index from disk, or return None if not found/invalid
+    fn load_persisted(&self) -> Option<PersistedIndex> {
+        let started = Instant::now();
+        match self.read_persisted()? {
+            Ok(content) => match serde_json::from_str::<RawPersistedIndex>(&content) {
+                Ok(raw) if raw.version == INDEX_VERSION => {
+                    let total = raw.entries.len();
+                    let entries: HashMap<String, CachedEntry> = raw
+                        .entries
+                        .into_iter()
+                        .filter_map(|(path, value)| {
+                            serde_json::from_value::<CachedEntry>(value).ok().map(|e| (path, e))
+                        })
+                        .collect();
+                    if entries.len() < total {
+                        warn!(
+                            "Dropped {} unreadable index cache entries, re-parsing those files",
+                            total - entries.len()
+                        );
+                    }
+                    info!(
+                        "Loaded index cache ({} entries) in {} ms",
+                        entries.len(),
+                        started.elapsed().as_millis()
+                    );
+                    Some(PersistedIndex {
+                        version: raw.version,
+                        entries,
+                    })
+                }
+                Ok(index) => {
+                    info!(
+                        "Index cache version {} is stale (want {}), rebuilding",
+                        index.version, INDEX_VERSION
+                    );
+                    None
+                }
                 Err(e) => {
-                    println!("Failed to parse index cache: {}", e);
+                    warn!("Failed to parse index cache: {}", e);
                     None
                 }
             },
             Err(e) => {
-                println!("Failed to read index cache: {}", e);
+                warn!("Failed to read index cache: {}", e);
                 None
             }
         }
@@ -78,6 +508,9 @@ impl DocumentIndex {
 
     /// Save current index to disk
     pub fn save_to_disk(&self) {
+        if self.touched_during_build.is_some() {
+            return;
+        }
         let entries: HashMap<String, CachedEntry> = self
             .documents
             .iter()
@@ -95,19 +528,147 @@ impl DocumentIndex {
             .collect();
 
         let persisted = PersistedIndex {
-            version: 1,
+            version: INDEX_VERSION,
             entries,
         };
 
-        match serde_json::to_string_pretty(&persisted) {
-            Ok(json) => {
-                if let Err(e) = std::fs::write(self.index_path(), json) {
-                    println!("Failed to save index cache: {}", e);
-                } else {
-                    println!("Saved index cache ({} entries)", persisted.entries.len());
+        let json = match serde_json::to_vec(&persisted) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize index: {}", e);
+                return;
+            }
+        };
+
+        // Write to a temp file and rename it over the cache, so a crash mid-write can't leave
+        // a truncated cache behind
+        let path = self.index_path();
+        let tmp = path.with_file_name(format!("{}.tmp", INDEX_FILENAME));
+        let written = std::fs::File::create(&tmp)
+            .and_then(|file| {
+                let mut encoder =
+                    GzEncoder::new(std::io::BufWriter::new(file), Compression::fast());
+                encoder.write_all(&json)?;
+                encoder.finish()?.into_inner().map_err(|e| e.into_error())?.sync_all()
+            })
+            .and_then(|()| std::fs::rename(&tmp, &path));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        match written {
+            Ok(()) => {
+                info!("Saved index cache ({} entries)", persisted.entries.len());
+                let legacy = self.state_dir.join(LEGACY_INDEX_FILENAME);
+                if legacy.exists() {
+                    let _ = std::fs::remove_file(legacy);
+                }
+            }
+            Err(e) => warn!("Failed to save index cache: {}", e),
+        }
+    }
+
+    /// Read a markdown file for indexing. Files over the size cap or not valid UTF-8 are
+    /// skipped with a log line and a diagnostic.
+    async fn read_source(&mut self, path: &Path, relative: &str) -> Option<String> {
+        let bytes = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        let read = if bytes > self.max_file_bytes {
+            None
+        } else {
+            Some(tokio::fs::read_to_string(path).await)
+        };
+        self.accept_source(relative, bytes, read)
+    }
+
+    /// [`read_source`](Self::read_source) for the synchronous refresh paths
+    fn read_source_blocking(&mut self, path: &Path, relative: &str) -> Option<String> {
+        let bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let read = if bytes > self.max_file_bytes {
+            None
+        } else {
+            Some(std::fs::read_to_string(path))
+        };
+        self.accept_source(relative, bytes, read)
+    }
+
+    /// Record the outcome of reading a file; `read` is `None` if it was too large to read
+    fn accept_source(
+        &mut self,
+        relative: &str,
+        bytes: u64,
+        read: Option<std::io::Result<String>>,
+    ) -> Option<String> {
+        let (kind, reason) = match read {
+            Some(Ok(content)) => {
+                self.diagnostics.remove(relative);
+                return Some(content);
+            }
+            None => (
+                DiagnosticKind::TooLarge,
+                format!("larger than {} MB", self.max_file_bytes / (1024 * 1024)),
+            ),
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::InvalidData => {
+                (DiagnosticKind::InvalidUtf8, "not valid UTF-8".to_string())
+            }
+            Some(Err(e)) => {
+                error!("Failed to read {}: {}", relative, e);
+                return None;
+            }
+        };
+
+        warn!("Skipping {} ({} bytes): {}", relative, bytes, reason);
+        self.record_diagnostic(relative, kind, reason, Some(bytes));
+        None
+    }
+
+    fn record_diagnostic(
+        &mut self,
+        relative: &str,
+        kind: DiagnosticKind,
+        reason: String,
+        bytes: Option<u64>,
+    ) {
+        self.diagnostics.insert(
+            relative.to_string(),
+            IndexDiagnostic {
+                path: relative.to_string(),
+                kind,
+                reason,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                bytes,
+            },
+        );
+    }
+
+    /// Record (or clear) the parse diagnostic for an indexed document from its warnings
+    fn note_parse_warnings(&mut self, relative: &str) {
+        let warnings = match self.documents.get(relative) {
+            Some(doc) if !doc.warnings.is_empty() => doc.warnings.join("; "),
+            _ => {
+                if self.diagnostics.get(relative).map(|d| d.kind) == Some(DiagnosticKind::Parse) {
+                    self.diagnostics.remove(relative);
                 }
+                return;
             }
-            Err(e) => println!("Failed to serialize index: {}", e),
+        };
+        let unchanged = self
+            .diagnostics
+            .get(relative)
+            .map(|d| d.kind == DiagnosticKind::Parse && d.reason == warnings)
+            .unwrap_or(false);
+        if !unchanged {
+            self.record_diagnostic(relative, DiagnosticKind::Parse, warnings, None);
+        }
+    }
+
+    /// Record files the walk left out, as collected by [`walk_vault`]
+    fn note_excluded(&mut self, excluded: Vec<(PathBuf, &'static str)>) {
+        for (path, reason) in excluded {
+            let relative = path
+                .strip_prefix(&self.org_root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            self.record_diagnostic(&relative, DiagnosticKind::Excluded, reason.to_string(), None);
         }
     }
 
@@ -122,17 +683,22 @@ impl DocumentIndex {
 
     /// Load from cache and incrementally update only changed files
     /// Returns (total_docs, cached_count, parsed_count, removed_count)
-    pub async fn load_or_build(&mut self) -> (usize, usize, usize, usize) {
+    pub async fn load_or_build(
+        &mut self,
+    ) -> Result<(usize, usize, usize, usize), DocumentLimitExceeded> {
         let cached = self.load_persisted();
+        self.diagnostics.clear();
 
         // Collect all current markdown files with their mtimes
         let mut current_files: HashMap<String, u64> = HashMap::new();
-        for entry in WalkDir::new(&self.org_root)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| !Self::should_exclude(e.path(), &self.org_root))
-            .filter_map(|e| e.ok())
-        {
+        let mut excluded: Vec<(PathBuf, &'static str)> = Vec::new();
+        let walk = walk_vault(
+            &self.org_root,
+            &self.excluded_files,
+            self.follow_symlinks,
+            |path, reason| excluded.push((path.to_path_buf(), reason)),
+        );
+        for entry in walk {
             let path = entry.path();
             if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
                 let relative = path
@@ -144,9 +710,14 @@ impl DocumentIndex {
                 if let Some(mtime) = Self::get_mtime(path) {
                     current_files.insert(relative, mtime);
                 }
+                if current_files.len() > self.max_documents {
+                    return Err(self.limit_exceeded());
+                }
             }
         }
 
+        self.note_excluded(excluded);
+
         let mut cached_count = 0;
         let mut parsed_count = 0;
         let mut docs_to_parse: Vec<(PathBuf, String, u64)> = Vec::new();
@@ -175,14 +746,25 @@ impl DocumentIndex {
             }
         }
 
+        info!(
+            "Indexing: {} markdown files ({} cached, {} to parse)",
+            current_files.len(),
+            cached_count,
+            docs_to_parse.len()
+        );
+
         // Parse files that weren't in cache or were modified
+        let to_parse = docs_to_parse.len();
         let mut newly_parsed: Vec<OrgDocument> = Vec::new();
         for (full_path, rel_path, mtime) in docs_to_parse {
-            if let Ok(content) = tokio::fs::read_to_string(&full_path).await {
+            if let Some(content) = self.read_source(&full_path, &rel_path).await {
                 let doc = parse_document(&full_path, &self.org_root, &content);
                 self.mtimes.insert(rel_path.clone(), mtime);
                 newly_parsed.push(doc);
                 parsed_count += 1;
+                if parsed_count % PROGRESS_EVERY == 0 {
+                    info!("Indexing: parsed {}/{}", parsed_count, to_parse);
+                }
             }
         }
 
@@ -190,6 +772,10 @@ impl DocumentIndex {
         for doc in newly_parsed {
             self.documents.insert(doc.path.clone(), doc);
         }
+        let paths: Vec<String> = self.documents.keys().cloned().collect();
+        for path in paths {
+            self.note_parse_warnings(&path);
+        }
 
         // Count removed (files in cache but not on disk)
         let removed_count = cached.as_ref().map_or(0, |c| {
@@ -199,7 +785,7 @@ impl DocumentIndex {
         // Rebuild backlinks for all documents
         self.rebuild_backlinks();
 
-        println!(
+        info!(
             "Index loaded: {} total ({} cached, {} parsed, {} removed)",
             self.documents.len(),
             cached_count,
@@ -210,134 +796,205 @@ impl DocumentIndex {
         // Save updated index
         self.save_to_disk();
 
-        (self.documents.len(), cached_count, parsed_count, removed_count)
+        Ok((self.documents.len(), cached_count, parsed_count, removed_count))
     }
 
-    /// Rebuild backlinks across all documents
+    /// Rebuild backlinks across all documents.
+    ///
+    /// Each wikilink is resolved in order of preference: exact relative path (without
+    /// `.md`), a unique stem or `aliases` match, project folder name, then a unique fuzzy
+    /// match on the normalized stem. Links with several equally good candidates resolve
+    /// to nothing and are recorded in `ambiguous_links`.
     fn rebuild_backlinks(&mut self) {
-        // First, collect all links
-        let links_map: HashMap<String, Vec<String>> = self
-            .documents
-            .iter()
-            .map(|(path, doc)| (path.clone(), doc.links.clone()))
-            .collect();
+        let mut by_path: HashMap<String, String> = HashMap::new();
+        let mut by_name: HashMap<String, Vec<String>> = HashMap::new();
+        let mut by_project: HashMap<String, Vec<String>> = HashMap::new();
 
-        // Clear existing backlinks
-        for doc in self.documents.values_mut() {
-            doc.backlinks.clear();
-        }
+        for (doc_path, doc) in &self.documents {
+            // Path without .md extension (e.g., "tasks/my-task")
+            let doc_path_no_ext = doc_path.strip_suffix(".md").unwrap_or(doc_path);
+            by_path.insert(doc_path_no_ext.to_lowercase(), doc_path.clone());
 
-        // Rebuild backlinks
-        for (doc_path, doc) in self.documents.iter_mut() {
-            // Get filename stem (e.g., "my-task" from "tasks/my-task.md")
+            // Filename stem (e.g., "my-task"), skipping generic names like README and CLAUDE
             let doc_name = Path::new(doc_path)
                 .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
+                .map(|s| s.to_string_lossy().to_lowercase())
                 .unwrap_or_default();
+            let mut names: Vec<String> = doc.aliases.iter().map(|a| a.trim().to_lowercase()).collect();
+            if doc_name != "readme" && doc_name != "claude" {
+                names.push(doc_name);
+            }
+            names.sort();
+            names.dedup();
+            for name in names.into_iter().filter(|n| !n.is_empty()) {
+                by_name.entry(name).or_default().push(doc_path.clone());
+            }
 
-            // Get path without .md extension (e.g., "tasks/my-task")
-            let doc_path_no_ext = doc_path.strip_suffix(".md").unwrap_or(doc_path);
-
-            // For project files, also match the project folder name
+            // Project files also match the project folder name
             // e.g., "projects/org-viewer/README.md" should match [[org-viewer]]
-            let project_name = if doc_path.starts_with("projects/") {
-                doc_path
-                    .strip_prefix("projects/")
-                    .and_then(|p| p.split('/').next())
-                    .map(|s| s.to_string())
-            } else {
-                None
-            };
+            if let Some(project) = doc_path
+                .strip_prefix("projects/")
+                .and_then(|p| p.split('/').next())
+            {
+                by_project
+                    .entry(project.to_lowercase())
+                    .or_default()
+                    .push(doc_path.clone());
+            }
+        }
 
-            for (other_path, other_links) in &links_map {
-                if other_path != doc_path {
-                    let has_link = other_links.iter().any(|link| {
-                        let link_lower = link.to_lowercase();
-                        let doc_name_lower = doc_name.to_lowercase();
+        let mut backlinks: HashMap<String, Vec<String>> = HashMap::new();
+        let mut ambiguous: Vec<AmbiguousLink> = Vec::new();
+
+        for (source, doc) in &self.documents {
+            for link in &doc.links {
+                let key = link.trim().trim_end_matches(".md").to_lowercase();
+
+                let targets: Vec<String> = if let Some(path) = by_path.get(&key) {
+                    vec![path.clone()]
+                } else if let Some(candidates) = by_name.get(&key) {
+                    if candidates.len() == 1 {
+                        candidates.clone()
+                    } else {
+                        let mut candidates = candidates.clone();
+                        candidates.sort();
+                        ambiguous.push(AmbiguousLink {
+                            source: source.clone(),
+                            link: link.clone(),
+                            candidates,
+                        });
+                        continue;
+                    }
+                } else if let Some(files) = by_project.get(&key) {
+                    files.clone()
+                } else {
+                    let normalized = normalize_link_name(&key);
+                    let fuzzy: Vec<&String> = by_name
+                        .iter()
+                        .filter(|(name, _)| {
+                            let name = normalize_link_name(name);
+                            !normalized.is_empty()
+                                && (name == normalized || name.ends_with(&format!("-{}", normalized)))
+                        })
+                        .flat_map(|(_, paths)| paths)
+                        .collect();
+                    match fuzzy.len() {
+                        0 => continue,
+                        1 => vec![fuzzy[0].clone()],
+                        _ => {
+                            let mut candidates: Vec<String> = fuzzy.into_iter().cloned().collect();
+                            candidates.sort();
+                            candidates.dedup();
+                            ambiguous.push(AmbiguousLink {
+                                source: source.clone(),
+                                link: link.clone(),
+                                candidates,
+                            });
+                            continue;
+                        }
+                    }
+                };
 
-                        // Skip generic names like README and CLAUDE for stem matching
-                        let is_generic = doc_name_lower == "readme" || doc_name_lower == "claude";
+                for target in targets {
+                    if &target != source {
+                        backlinks.entry(target).or_default().push(source.clone());
+                    }
+                }
+            }
+        }
 
-                        // Match by full path (without .md)
-                        if link_lower == doc_path_no_ext.to_lowercase() {
-                            return true;
-                        }
+        for (doc_path, doc) in self.documents.iter_mut() {
+            let mut sources = backlinks.remove(doc_path).unwrap_or_default();
+            sources.sort();
+            sources.dedup();
+            doc.backlinks = sources;
+        }
 
-                        // Match by filename stem (but not for generic names)
-                        if !is_generic && link_lower == doc_name_lower {
-                            return true;
-                        }
+        ambiguous.sort_by(|a, b| a.source.cmp(&b.source).then_with(|| a.link.cmp(&b.link)));
+        self.ambiguous_links = ambiguous;
 
-                        // Match project folder name for project files
-                        if let Some(ref proj) = project_name {
-                            if link_lower == proj.to_lowercase() {
-                                return true;
-                            }
-                        }
+        self.rebuild_titles();
+    }
 
-                        false
-                    });
+    /// Rebuild the title → paths multimap and flag documents whose title isn't unique
+    fn rebuild_titles(&mut self) {
+        let mut titles: HashMap<String, Vec<String>> = HashMap::new();
+        for (doc_path, doc) in &self.documents {
+            titles
+                .entry(doc.title.trim().to_lowercase())
+                .or_default()
+                .push(doc_path.clone());
+        }
+        for paths in titles.values_mut() {
+            paths.sort();
+        }
 
-                    if has_link {
-                        doc.backlinks.push(other_path.clone());
-                    }
-                }
-            }
+        for doc in self.documents.values_mut() {
+            doc.title_ambiguous = titles
+                .get(&doc.title.trim().to_lowercase())
+                .map(|paths| paths.len() > 1)
+                .unwrap_or(false);
         }
+        self.titles = titles;
+    }
+
+    /// Documents titled `title` (case-insensitive), sorted by path
+    pub fn documents_titled(&self, title: &str) -> Vec<&OrgDocument> {
+        self.titles
+            .get(&title.trim().to_lowercase())
+            .map(|paths| paths.iter().filter_map(|p| self.documents.get(p)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Wikilinks that matched several documents in the last backlink rebuild
+    pub fn get_ambiguous_links(&self) -> &[AmbiguousLink] {
+        &self.ambiguous_links
+    }
+
+    /// Non-fatal indexing problems, by path
+    pub fn get_diagnostics(&self) -> Vec<&IndexDiagnostic> {
+        let mut diagnostics: Vec<&IndexDiagnostic> = self.diagnostics.values().collect();
+        diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+        diagnostics
     }
 
     /// Full rebuild - clears everything and re-parses all files
-    pub async fn build_index(&mut self) {
+    pub async fn build_index(&mut self) -> Result<(), DocumentLimitExceeded> {
         self.documents.clear();
         self.mtimes.clear();
+        self.diagnostics.clear();
         let mut docs: Vec<OrgDocument> = Vec::new();
 
         // Walk the directory
-        for entry in WalkDir::new(&self.org_root)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| !Self::should_exclude(e.path(), &self.org_root))
-            .filter_map(|e| e.ok())
-        {
+        let org_root = self.org_root.clone();
+        let excluded_files = self.excluded_files.clone();
+        let mut excluded: Vec<(PathBuf, &'static str)> = Vec::new();
+        let walk = walk_vault(&org_root, &excluded_files, self.follow_symlinks, |path, reason| {
+            excluded.push((path.to_path_buf(), reason))
+        });
+        for entry in walk {
             let path = entry.path();
             if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
-                if let Ok(content) = tokio::fs::read_to_string(path).await {
-                    let doc = parse_document(path, &self.org_root, &content);
+                if docs.len() >= self.max_documents {
+                    return Err(self.limit_exceeded());
+                }
+                let relative = path
+                    .strip_prefix(&org_root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if let Some(content) = self.read_source(path, &relative).await {
+                    let doc = parse_document(path, &org_root, &content);
 
                     // Track mtime
-                    let relative = path
-                        .strip_prefix(&self.org_root)
-                        .unwrap_or(path)
-                        .to_string_lossy()
-                        .replace('\\', "/");
                     if let Some(mtime) = Self::get_mtime(path) {
                         self.mtimes.insert(relative, mtime);
                     }
 
                     docs.push(doc);
-                }
-            }
-        }
-
-        // Build backlinks
-        let links_map: HashMap<String, Vec<String>> = docs
-            .iter()
-            .map(|d| (d.path.clone(), d.links.clone()))
-            .collect();
-
-        for doc in &mut docs {
-            let doc_name = Path::new(&doc.path)
-                .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default();
-
-            for (other_path, other_links) in &links_map {
-                if other_path != &doc.path
-                    && other_links
-                        .iter()
-                        .any(|link| link.to_lowercase() == doc_name.to_lowercase())
-                {
-                    doc.backlinks.push(other_path.clone());
+                    if docs.len().is_multiple_of(PROGRESS_EVERY) {
+                        info!("Indexing: parsed {}", docs.len());
+                    }
                 }
             }
         }
@@ -346,34 +1003,41 @@ impl DocumentIndex {
         for doc in docs {
             self.documents.insert(doc.path.clone(), doc);
         }
+        self.note_excluded(excluded);
+        let paths: Vec<String> = self.documents.keys().cloned().collect();
+        for path in paths {
+            self.note_parse_warnings(&path);
+        }
 
-        println!("Full index built: {} documents", self.documents.len());
+        // Build backlinks
+        self.rebuild_backlinks();
+
+        info!("Full index built: {} documents", self.documents.len());
 
         // Save to disk
         self.save_to_disk();
+        Ok(())
     }
 
-    fn should_exclude(path: &Path, org_root: &Path) -> bool {
+    /// Why `path` is left out of the index, if it is
+    fn exclusion_reason(
+        path: &Path,
+        org_root: &Path,
+        excluded_files: &[String],
+    ) -> Option<&'static str> {
         let relative = path.strip_prefix(org_root).unwrap_or(path);
         let components: Vec<_> = relative.components().collect();
 
+        // `excludeFiles` patterns name files; a folder that happens to match one is still walked
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+        if !path.is_dir() && matches_excluded_file(&relative_str, excluded_files) {
+            return Some(REASON_EXCLUDE_FILES);
+        }
+
         if let Some(first) = components.first() {
             let name = first.as_os_str().to_string_lossy();
-            let excluded = [
-                "node_modules",
-                ".git",
-                ".obsidian",
-                "scratchpad",
-                "dist",
-                "build",
-                ".next",
-                "target",
-                "x",       // Twitter archive
-                "archive", // Semantic archive (emails, tickets, research, reports)
-            ];
-
-            if excluded.contains(&name.as_ref()) {
-                return true;
+            if EXCLUDED_DIRS.contains(&name.as_ref()) {
+                return Some(REASON_EXCLUDED_DIR);
             }
 
             // Handle projects folder specially - only index CLAUDE.md and README.md
@@ -388,18 +1052,18 @@ impl DocumentIndex {
                         if let Some(filename) = path.file_name() {
                             let fname = filename.to_string_lossy();
                             if fname == "CLAUDE.md" || fname == "README.md" {
-                                return false; // Allow these files
+                                return None; // Allow these files
                             }
                         }
                     }
                     // Exclude all other files in projects/
-                    return true;
+                    return Some(REASON_PROJECT_FILE);
                 }
                 // For directories inside projects/, exclude deeply nested ones
                 // Allow: projects/, projects/<name>/
                 // Exclude: projects/<name>/<anything>/
                 if path.is_dir() && components.len() > 2 {
-                    return true;
+                    return Some(REASON_PROJECT_DIR);
                 }
             }
         }
@@ -408,11 +1072,45 @@ impl DocumentIndex {
         if let Some(name) = path.file_name() {
             let name = name.to_string_lossy();
             if name.starts_with('.') && name != ".obsidian" {
-                return true;
+                return Some(REASON_HIDDEN);
             }
         }
 
-        false
+        None
+    }
+
+    /// Whether the file at `path` is left out of the index, by its own name or by one of
+    /// the folders above it
+    pub fn excludes(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|p| p.starts_with(&self.org_root) && *p != self.org_root)
+            .any(|p| Self::exclusion_reason(p, &self.org_root, &self.excluded_files).is_some())
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Swap in a freshly built index (e.g. one finished in the background), bumping the
+    /// generation. Files changed while it was building are re-read, since the build may
+    /// have walked past them before the change.
+    pub fn replace_with(&mut self, built: DocumentIndex) {
+        let generation = self.generation.max(built.generation) + 1;
+        let touched = self.touched_during_build.take().unwrap_or_default();
+        *self = built;
+        self.generation = generation;
+
+        if !touched.is_empty() {
+            let (refreshed, removed): (Vec<PathBuf>, Vec<PathBuf>) =
+                touched.into_iter().partition(|path| path.exists());
+            self.apply_bulk(&removed, &refreshed);
+        }
+    }
+
+    fn note_touched(&mut self, path: &Path) {
+        if let Some(touched) = &mut self.touched_during_build {
+            touched.insert(path.to_path_buf());
+        }
     }
 
     pub fn get_documents(&self) -> Vec<&OrgDocument> {
@@ -423,6 +1121,11 @@ impl DocumentIndex {
         self.documents.get(path)
     }
 
+    /// Cached modification time (unix seconds) for an indexed document
+    pub fn get_document_mtime(&self, path: &str) -> Option<u64> {
+        self.mtimes.get(path).copied()
+    }
+
     pub async fn get_document_with_content(&self, path: &str) -> Option<OrgDocument> {
         let doc = self.documents.get(path)?;
         let mut doc = doc.clone();
@@ -435,17 +1138,95 @@ impl DocumentIndex {
         Some(doc)
     }
 
-    pub fn search(&self, query: &str) -> Vec<&OrgDocument> {
+    /// Top 50 matches for `query` in `mode`, best first. Errors if a regex query doesn't
+    /// compile or is too large.
+    pub fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<&OrgDocument>, String> {
+        let scored = self.search_scored_in(query, mode)?;
+        Ok(scored.into_iter().map(|(doc, _)| doc).take(50).collect())
+    }
+
+    /// Every match for `query` in `mode`, best first (ties by path). Non-fuzzy modes take
+    /// the query literally (no filter syntax) and score by the number of matches in each
+    /// field, times the field's search weight.
+    fn search_scored_in(
+        &self,
+        query: &str,
+        mode: SearchMode,
+    ) -> Result<Vec<(&OrgDocument, i64)>, String> {
+        let literal = query.trim();
+        let pattern = match mode {
+            SearchMode::Fuzzy => return Ok(self.search_scored(query)),
+            _ if literal.is_empty() => return Ok(Vec::new()),
+            SearchMode::Substring => regex::escape(literal),
+            // Only assert a boundary next to word characters, so `C++` still matches
+            SearchMode::Word => {
+                let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                format!(
+                    "{}{}{}",
+                    if is_word(literal.chars().next()) { r"\b" } else { "" },
+                    regex::escape(literal),
+                    if is_word(literal.chars().last()) { r"\b" } else { "" }
+                )
+            }
+            SearchMode::Regex => {
+                if literal.len() > MAX_REGEX_QUERY_LEN {
+                    return Err(format!("Regex longer than {} bytes", MAX_REGEX_QUERY_LEN));
+                }
+                literal.to_string()
+            }
+        };
+        let re = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let weights = self.search_weights;
+        let mut results: Vec<(&OrgDocument, i64)> = self
+            .documents
+            .values()
+            .filter_map(|doc| {
+                let count = |text: &str| re.find_iter(text).count() as i64;
+                let tag_count: i64 = doc.tags.iter().map(|t| count(t)).sum();
+                let score = count(&doc.title) * weights.title
+                    + count(&doc.path) * weights.path
+                    + tag_count * weights.tags;
+                (score > 0).then_some((doc, score))
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+        Ok(results)
+    }
+
+    /// Every match for `query` with its fuzzy score, best first (ties by path). See
+    /// [`ParsedQuery`] for the syntax; a query of only filters scores every match the same.
+    pub fn search_scored(&self, query: &str) -> Vec<(&OrgDocument, i64)> {
         use fuzzy_matcher::skim::SkimMatcherV2;
         use fuzzy_matcher::FuzzyMatcher;
 
+        let parsed = ParsedQuery::parse(query);
+        let fuzzy = if parsed.is_plain() {
+            query.to_string()
+        } else {
+            parsed.terms.join(" ")
+        };
+        let filter_only = !parsed.is_plain() && parsed.terms.is_empty();
+
         let matcher = SkimMatcherV2::default();
-        let query_lower = query.to_lowercase();
+        let query_lower = fuzzy.to_lowercase();
+        let weights = self.search_weights;
 
         let mut results: Vec<(&OrgDocument, i64)> = self
             .documents
             .values()
+            .filter(|doc| parsed.matches(doc))
             .filter_map(|doc| {
+                if filter_only {
+                    return Some((doc, 1));
+                }
+
                 // Search in title
                 let title_score = matcher.fuzzy_match(&doc.title, &query_lower).unwrap_or(0);
 
@@ -460,7 +1241,25 @@ impl DocumentIndex {
                     .max()
                     .unwrap_or(0);
 
-                let total_score = title_score * 3 + path_score + tag_score * 2;
+                // Prefix matches ("conf" -> "Configuration") outrank scattered fuzzy matches
+                let title_lower = doc.title.to_lowercase();
+                let prefix_boost = if query_lower.is_empty() {
+                    0
+                } else if title_lower.starts_with(&query_lower) {
+                    TITLE_PREFIX_BOOST
+                } else if title_lower
+                    .split(|c: char| !c.is_alphanumeric())
+                    .any(|word| word.starts_with(&query_lower))
+                {
+                    WORD_PREFIX_BOOST
+                } else {
+                    0
+                };
+
+                let total_score = title_score * weights.title
+                    + path_score * weights.path
+                    + tag_score * weights.tags
+                    + prefix_boost;
 
                 if total_score > 0 {
                     Some((doc, total_score))
@@ -470,8 +1269,8 @@ impl DocumentIndex {
             })
             .collect();
 
-        results.sort_by(|a, b| b.1.cmp(&a.1));
-        results.into_iter().map(|(doc, _)| doc).take(50).collect()
+        results.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+        results
     }
 
     pub fn get_stats(&self) -> IndexStats {
@@ -493,31 +1292,162 @@ impl DocumentIndex {
     }
 
     pub fn refresh_document(&mut self, path: &Path) {
+        self.note_touched(path);
         let relative = path
             .strip_prefix(&self.org_root)
             .unwrap_or(path)
             .to_string_lossy()
             .replace('\\', "/");
 
-        if let Ok(content) = std::fs::read_to_string(path) {
-            let doc = parse_document(path, &self.org_root, &content);
+        if matches_excluded_file(&relative, &self.excluded_files) {
+            self.record_diagnostic(
+                &relative,
+                DiagnosticKind::Excluded,
+                REASON_EXCLUDE_FILES.to_string(),
+                None,
+            );
+            if self.documents.contains_key(&relative) {
+                self.remove_document(path);
+            }
+            return;
+        }
+
+        let content = match self.read_source_blocking(path, &relative) {
+            Some(content) => content,
+            None => {
+                // A document that grew past the cap (or stopped being UTF-8) leaves the index
+                let skipped = self.diagnostics.contains_key(&relative);
+                if skipped && self.documents.contains_key(&relative) {
+                    self.remove_document(path);
+                }
+                return;
+            }
+        };
+
+        let doc = parse_document(path, &self.org_root, &content);
+
+        // Update mtime
+        if let Some(mtime) = Self::get_mtime(path) {
+            self.mtimes.insert(relative.clone(), mtime);
+        }
+
+        self.documents.insert(relative.clone(), doc);
+        self.note_parse_warnings(&relative);
+        self.generation += 1;
+
+        // Rebuild backlinks since links may have changed
+        self.rebuild_backlinks();
+
+        // Save updated index (debounce this in production)
+        self.save_to_disk();
+    }
+
+    /// Apply many removals and refreshes (e.g. a folder move) under one call, rebuilding
+    /// backlinks and saving the index once instead of per document.
+    pub fn apply_bulk(&mut self, removed: &[PathBuf], refreshed: &[PathBuf]) {
+        for path in removed.iter().chain(refreshed) {
+            self.note_touched(path);
+        }
+        let relative = |root: &Path, path: &Path| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+
+        for path in removed {
+            let rel = relative(&self.org_root, path);
+            self.documents.remove(&rel);
+            self.mtimes.remove(&rel);
+            self.diagnostics.remove(&rel);
+        }
 
-            // Update mtime
-            if let Some(mtime) = Self::get_mtime(path) {
-                self.mtimes.insert(relative.clone(), mtime);
+        for path in refreshed {
+            let rel = relative(&self.org_root, path);
+            if matches_excluded_file(&rel, &self.excluded_files) {
+                self.documents.remove(&rel);
+                self.mtimes.remove(&rel);
+                self.record_diagnostic(
+                    &rel,
+                    DiagnosticKind::Excluded,
+                    REASON_EXCLUDE_FILES.to_string(),
+                    None,
+                );
+                continue;
             }
+            match self.read_source_blocking(path, &rel) {
+                Some(content) => {
+                    let doc = parse_document(path, &self.org_root, &content);
+                    if let Some(mtime) = Self::get_mtime(path) {
+                        self.mtimes.insert(rel.clone(), mtime);
+                    }
+                    self.documents.insert(rel.clone(), doc);
+                    self.note_parse_warnings(&rel);
+                }
+                None if self.diagnostics.contains_key(&rel) => {
+                    self.documents.remove(&rel);
+                    self.mtimes.remove(&rel);
+                }
+                None => {}
+            }
+        }
+
+        self.generation += 1;
+        self.rebuild_backlinks();
+        self.save_to_disk();
+    }
 
-            self.documents.insert(relative, doc);
+    /// Indexed paths inside the folder `dir` (relative to the org root)
+    pub fn paths_under(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        self.documents
+            .keys()
+            .filter(|p| p.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Move an index entry to a new path, keeping its parsed data and cached mtime.
+    /// Falls back to a fresh parse if the old path wasn't indexed.
+    pub fn rename_document(&mut self, old_path: &Path, new_path: &Path) {
+        self.note_touched(old_path);
+        self.note_touched(new_path);
+        let old_relative = old_path
+            .strip_prefix(&self.org_root)
+            .unwrap_or(old_path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let new_relative = new_path
+            .strip_prefix(&self.org_root)
+            .unwrap_or(new_path)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut doc = match self.documents.remove(&old_relative) {
+            Some(doc) => doc,
+            None => {
+                self.refresh_document(new_path);
+                return;
+            }
+        };
 
-            // Rebuild backlinks since links may have changed
-            self.rebuild_backlinks();
+        doc.path = new_relative.clone();
+        self.documents.insert(new_relative.clone(), doc);
 
-            // Save updated index (debounce this in production)
-            self.save_to_disk();
+        if let Some(mtime) = self.mtimes.remove(&old_relative) {
+            self.mtimes.insert(new_relative, mtime);
         }
+        self.generation += 1;
+
+        // Rebuild backlinks since paths (and stems) may have changed
+        self.rebuild_backlinks();
+
+        // Save updated index
+        self.save_to_disk();
     }
 
     pub fn remove_document(&mut self, path: &Path) {
+        self.note_touched(path);
         let relative = path
             .strip_prefix(&self.org_root)
             .unwrap_or(path)
@@ -526,6 +1456,11 @@ impl DocumentIndex {
 
         self.documents.remove(&relative);
         self.mtimes.remove(&relative);
+        // Keep the diagnostic explaining why a file that still exists left the index
+        if !path.exists() {
+            self.diagnostics.remove(&relative);
+        }
+        self.generation += 1;
 
         // Rebuild backlinks since a document was removed
         self.rebuild_backlinks();
@@ -535,9 +1470,318 @@ impl DocumentIndex {
     }
 }
 
+/// Entries under the org root that aren't excluded. With `follow_symlinks`, symlinked
+/// folders are descended into, but each real folder only once: a link cycle, or a folder
+/// linked from two places, is walked a single time. Excluded markdown files, and folders
+/// excluded by a rule a user might not expect (`excludeFiles`, the projects/ layout), are
+/// passed to `on_excluded`; hidden and always-excluded folders are not.
+pub fn walk_vault<'a>(
+    org_root: &'a Path,
+    excluded_files: &'a [String],
+    follow_symlinks: bool,
+    mut on_excluded: impl FnMut(&Path, &'static str) + 'a,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    WalkDir::new(org_root)
+        .follow_links(follow_symlinks)
+        .into_iter()
+        .filter_entry(move |e| {
+            let reason = DocumentIndex::exclusion_reason(e.path(), org_root, excluded_files);
+            if let Some(reason) = reason {
+                let is_markdown = e.file_type().is_file()
+                    && e.path().extension().map(|x| x == "md").unwrap_or(false);
+                let is_ruled_dir = e.file_type().is_dir()
+                    && reason != REASON_HIDDEN
+                    && reason != REASON_EXCLUDED_DIR;
+                if is_markdown || is_ruled_dir {
+                    on_excluded(e.path(), reason);
+                }
+                return false;
+            }
+            if follow_symlinks && e.file_type().is_dir() {
+                return match e.path().canonicalize() {
+                    Ok(real) => visited.insert(real),
+                    Err(_) => false,
+                };
+            }
+            true
+        })
+        .filter_map(|e| e.ok())
+}
+
+/// Whether an org-root-relative path matches one of the `excludeFiles` patterns. Patterns
+/// containing `/` match the whole relative path, others match the file name alone; `*`
+/// matches any run of characters within one path segment.
+pub fn matches_excluded_file(relative: &str, patterns: &[String]) -> bool {
+    let file_name = relative.rsplit('/').next().unwrap_or(relative);
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.contains('/') {
+            wildcard_match(pattern, relative)
+        } else {
+            wildcard_match(pattern, file_name)
+        }
+    })
+}
+
+/// Glob-style match supporting `*` (never crossing `/`)
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else {
+            match star {
+                // Let the last `*` swallow one more character, unless that's a separator
+                Some((sp, st)) if text[st] != '/' => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct IndexStats {
     pub total: usize,
     pub by_type: HashMap<String, usize>,
     pub by_status: HashMap<String, usize>,
 }
+
+/// Normalize a link target or stem for fuzzy matching: lowercase, with spaces and
+/// underscores folded to hyphens ("Meeting Notes" → "meeting-notes").
+pub fn normalize_link_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(files: &[(&str, &str)]) -> tempfile::TempDir {
+        // Not the default `.tmp` prefix: hidden directories are skipped by the walk
+        let dir = tempfile::Builder::new().prefix("vault").tempdir().unwrap();
+        for (path, content) in files {
+            let full = dir.path().join(path);
+            std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+            std::fs::write(full, content).unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn save_replaces_cache_without_leaving_temp_file() {
+        let dir = vault(&[("note.md", "# Note\n")]);
+        let mut index = DocumentIndex::new(dir.path());
+        index.load_or_build().await.unwrap();
+
+        assert!(dir.path().join(INDEX_FILENAME).exists());
+        assert!(!dir.path().join(format!("{}.tmp", INDEX_FILENAME)).exists());
+
+        let mut reloaded = DocumentIndex::new(dir.path());
+        let (total, cached, parsed, _) = reloaded.load_or_build().await.unwrap();
+        assert_eq!((total, cached, parsed), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn pending_build_does_not_save_and_replays_changes() {
+        let dir = vault(&[("a.md", "# A\n"), ("b.md", "# B\n")]);
+        let mut built = DocumentIndex::new(dir.path());
+        built.load_or_build().await.unwrap();
+        std::fs::remove_file(dir.path().join(INDEX_FILENAME)).unwrap();
+
+        // Changes the watcher reports while the build is still running
+        let mut serving = DocumentIndex::new(dir.path()).with_pending_build();
+        let c = dir.path().join("c.md");
+        std::fs::write(&c, "# C\n").unwrap();
+        serving.refresh_document(&c);
+        let b = dir.path().join("b.md");
+        std::fs::remove_file(&b).unwrap();
+        serving.remove_document(&b);
+        assert!(!dir.path().join(INDEX_FILENAME).exists());
+
+        serving.replace_with(built);
+        assert!(serving.get_document("a.md").is_some());
+        assert!(serving.get_document("b.md").is_none());
+        assert!(serving.get_document("c.md").is_some());
+        assert!(dir.path().join(INDEX_FILENAME).exists());
+
+        let mut reloaded = DocumentIndex::new(dir.path());
+        let (total, cached, parsed, _) = reloaded.load_or_build().await.unwrap();
+        assert_eq!((total, cached, parsed), (2, 2, 0));
+    }
+
+    fn write_cache(dir: &Path, cache: serde_json::Value) {
+        let file = std::fs::File::create(dir.join(INDEX_FILENAME)).unwrap();
+        let mut encoder = GzEncoder::new(file, Compression::fast());
+        encoder.write_all(cache.to_string().as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn cache_from_an_older_version_is_rejected() {
+        let dir = vault(&[("note.md", "# Note\n")]);
+        let mut index = DocumentIndex::new(dir.path());
+        index.load_or_build().await.unwrap();
+        let mut cache = serde_json::to_value(index.load_persisted().unwrap()).unwrap();
+        cache["version"] = serde_json::json!(INDEX_VERSION - 1);
+        write_cache(dir.path(), cache);
+
+        assert!(index.load_persisted().is_none());
+    }
+
+    #[tokio::test]
+    async fn unreadable_cache_entries_are_dropped_one_at_a_time() {
+        let dir = vault(&[("a.md", "# A\n"), ("b.md", "# B\n")]);
+        let mut index = DocumentIndex::new(dir.path());
+        index.load_or_build().await.unwrap();
+        let mut cache = serde_json::to_value(index.load_persisted().unwrap()).unwrap();
+        cache["entries"]["b.md"] = serde_json::json!({ "document": "not a document" });
+        write_cache(dir.path(), cache);
+
+        let loaded = index.load_persisted().unwrap();
+        assert!(loaded.entries.contains_key("a.md"));
+        assert!(!loaded.entries.contains_key("b.md"));
+
+        let mut reloaded = DocumentIndex::new(dir.path());
+        let (total, cached, parsed, _) = reloaded.load_or_build().await.unwrap();
+        assert_eq!((total, cached, parsed), (2, 1, 1));
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn excluded_file_patterns() {
+        let patterns = strings(&["*.draft.md", "/private/secret.md", "inbox/*-tmp.md"]);
+        assert!(matches_excluded_file("ideas.draft.md", &patterns));
+        assert!(matches_excluded_file("notes/ideas.draft.md", &patterns));
+        assert!(matches_excluded_file("private/secret.md", &patterns));
+        assert!(!matches_excluded_file("notes/private/secret.md", &patterns));
+        assert!(matches_excluded_file("inbox/scratch-tmp.md", &patterns));
+        assert!(!matches_excluded_file(
+            "inbox/deep/scratch-tmp.md",
+            &patterns
+        ));
+        assert!(!matches_excluded_file("ideas.md", &patterns));
+    }
+
+    #[tokio::test]
+    async fn excluded_file_patterns_do_not_exclude_folders() {
+        let dir = vault(&[
+            ("journal/note.md", "# Note\n"),
+            ("journal.md", "# Journal\n"),
+        ]);
+        let mut index = DocumentIndex::new(dir.path()).with_excluded_files(strings(&["journal*"]));
+        index.load_or_build().await.unwrap();
+
+        assert!(index.get_document("journal/note.md").is_some());
+        assert!(index.get_document("journal.md").is_none());
+    }
+
+    #[test]
+    fn excludes_checks_every_folder_above_a_file() {
+        let dir = vault(&[
+            ("archive/notes/a.md", ""),
+            ("notes/.hidden/b.md", ""),
+            ("notes/c.md", ""),
+            ("notes/d.draft.md", ""),
+        ]);
+        let index = DocumentIndex::new(dir.path()).with_excluded_files(strings(&["*.draft.md"]));
+        assert!(index.excludes(&dir.path().join("archive/notes/a.md")));
+        assert!(index.excludes(&dir.path().join("notes/.hidden/b.md")));
+        assert!(index.excludes(&dir.path().join("notes/d.draft.md")));
+        assert!(!index.excludes(&dir.path().join("notes/c.md")));
+    }
+
+    #[tokio::test]
+    async fn excluded_file_stays_out_of_the_index_after_edits() {
+        let dir = vault(&[("keep.md", "# Keep\n"), ("secret.md", "# Secret\n")]);
+        let mut index = DocumentIndex::new(dir.path()).with_excluded_files(strings(&["secret.md"]));
+        index.load_or_build().await.unwrap();
+        assert!(index.get_document("secret.md").is_none());
+
+        let secret = dir.path().join("secret.md");
+        std::fs::write(&secret, "# Secret\n\nEdited\n").unwrap();
+        index.refresh_document(&secret);
+        assert!(index.get_document("secret.md").is_none());
+        assert!(index.get_document("keep.md").is_some());
+    }
+
+    #[test]
+    fn query_field_filters() {
+        let parsed = ParsedQuery::parse("type:Task status:open Tag:Project deadline");
+        assert_eq!(
+            parsed.filters,
+            vec![
+                (QueryField::Type, "task".to_string()),
+                (QueryField::Status, "open".to_string()),
+                (QueryField::Tag, "project".to_string()),
+            ]
+        );
+        assert_eq!(parsed.terms, strings(&["deadline"]));
+        assert!(!parsed.is_plain());
+    }
+
+    #[test]
+    fn query_quoted_phrases() {
+        let parsed = ParsedQuery::parse(r#"release "Weekly Review" notes"#);
+        assert_eq!(parsed.phrases, strings(&["weekly review"]));
+        assert_eq!(parsed.terms, strings(&["release", "notes"]));
+    }
+
+    #[test]
+    fn query_exclusions() {
+        let parsed = ParsedQuery::parse(r#"plan -draft -"old idea" -tag:archived"#);
+        assert_eq!(parsed.terms, strings(&["plan"]));
+        assert_eq!(parsed.excluded, strings(&["draft", "old idea"]));
+        assert_eq!(
+            parsed.excluded_filters,
+            vec![(QueryField::Tag, "archived".to_string())]
+        );
+    }
+
+    #[test]
+    fn query_unknown_field_is_a_bare_word() {
+        let parsed = ParsedQuery::parse("author:alex http://example.com type:");
+        assert!(parsed.filters.is_empty());
+        assert_eq!(
+            parsed.terms,
+            strings(&["author:alex", "http://example.com", "type:"])
+        );
+        assert!(parsed.is_plain());
+    }
+
+    #[test]
+    fn query_unterminated_quote_runs_to_the_end() {
+        let parsed = ParsedQuery::parse(r#"todo "half open phrase"#);
+        assert_eq!(parsed.terms, strings(&["todo"]));
+        assert_eq!(parsed.phrases, strings(&["half open phrase"]));
+    }
+
+    #[test]
+    fn query_without_operators_is_plain() {
+        let parsed = ParsedQuery::parse("  meeting   notes - ");
+        assert_eq!(parsed.terms, strings(&["meeting", "notes", "-"]));
+        assert!(parsed.is_plain());
+    }
+}
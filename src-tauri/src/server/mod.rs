@@ -1,11 +1,17 @@
+pub mod activity;
+pub mod config;
 pub mod document;
+pub mod error;
 pub mod federation;
 pub mod index;
 pub mod peers;
+pub mod pinning;
 pub mod projects;
 pub mod routes;
+pub mod searches;
 pub mod static_files;
 pub mod sync;
+pub mod tls;
 pub mod watcher;
 
 use axum::{
@@ -14,33 +20,106 @@ use axum::{
         State, WebSocketUpgrade,
     },
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
-use std::env;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::{DefaultMakeSpan, TraceLayer};
+use tracing::{error, info, warn, Level};
 
+use activity::ActivityLog;
+use config::{IndexTimeoutMode, ServerConfig};
 use index::DocumentIndex;
 use peers::PeerRegistry;
+use searches::SavedSearches;
 use sync::SyncService;
 use watcher::FileWatcher;
 
-pub fn log_to_file(msg: &str) {
-    let log_path = env::temp_dir().join("vitrum.log");
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-    {
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-        let _ = writeln!(file, "[{}] [server] {}", timestamp, msg);
+/// Resolve a user-supplied relative path against `org_root`, rejecting anything that
+/// could escape it: absolute paths, Windows drive letters, and `..` that climbs above the root.
+/// Backslashes are treated as separators so `..\` is caught on every platform.
+/// This is purely lexical — callers touching existing files may still canonicalize.
+pub fn resolve_safe_path(org_root: &Path, path: &str) -> Option<PathBuf> {
+    let normalized = path.replace('\\', "/");
+
+    // Reject absolute paths and drive letters (C:/, C:foo)
+    if normalized.starts_with('/') {
+        return None;
+    }
+    let bytes = normalized.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return None;
+    }
+
+    let mut parts: Vec<&std::ffi::OsStr> = Vec::new();
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    let mut resolved = org_root.to_path_buf();
+    resolved.extend(parts);
+    Some(resolved)
+}
+
+/// Aborts the task when dropped. `start_server` holds its background tasks this way, so
+/// cancelling the server task stops everything it started.
+pub struct AbortOnDrop<T>(pub tokio::task::JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Counts a WebSocket connection in `AppState::ws_clients` for as long as it lives, so the
+/// count drops however the connection ends (close, send error, or the task being aborted)
+struct WsClientGuard<'a>(&'a AtomicUsize);
+
+impl<'a> WsClientGuard<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        WsClientGuard(count)
+    }
+}
+
+impl Drop for WsClientGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Keyed async mutexes serializing writes to the same document
+#[derive(Default)]
+pub struct PathLocks {
+    locks: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl PathLocks {
+    /// Wait for exclusive write access to `path`; released when the guard drops.
+    pub async fn lock(&self, path: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            // Forget locks nobody is holding or waiting on
+            locks.retain(|_, l| Arc::strong_count(l) > 1);
+            Arc::clone(locks.entry(path.to_string()).or_default())
+        };
+        lock.lock_owned().await
     }
 }
 
@@ -49,6 +128,16 @@ pub struct AppState {
     pub org_root: PathBuf,
     pub start_time: std::time::Instant,
     pub ws_tx: broadcast::Sender<String>,
+    pub config: ServerConfig,
+    pub activity: ActivityLog,
+    pub write_locks: PathLocks,
+    /// False while the initial index build is still running in the background
+    pub index_ready: AtomicBool,
+    /// Document bodies for the unlinked-mentions scan
+    pub body_cache: RwLock<routes::BodyCache>,
+    /// Open WebSocket connections
+    pub ws_clients: AtomicUsize,
+    pub saved_searches: SavedSearches,
 }
 
 /// Federation state wraps AppState + federation-specific services
@@ -56,23 +145,41 @@ pub struct FederationState {
     pub app_state: Arc<AppState>,
     pub peer_registry: Arc<PeerRegistry>,
     pub sync_service: Arc<SyncService>,
-    pub local_host: RwLock<Option<(String, u16)>>,
+    /// Host and port this instance advertises to peers
+    pub local_host: (String, u16),
+    pub snippet_cache: RwLock<federation::SnippetCache>,
+    pub rate_limiter: federation::RateLimiter,
 }
 
 /// WebSocket upgrade handler
 async fn ws_handler(
     ws: WebSocketUpgrade,
-    State(state): State<Arc<AppState>>,
+    State(fed): State<Arc<FederationState>>,
 ) -> impl IntoResponse {
-    log_to_file("[ws] Client connecting...");
-    ws.on_upgrade(move |socket| handle_ws_connection(socket, state))
+    info!("[ws] Client connecting...");
+    ws.on_upgrade(move |socket| handle_ws_connection(socket, fed))
 }
 
-/// Handle an individual WebSocket connection
-async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
-    log_to_file("[ws] Client connected");
+/// Handle an individual WebSocket connection. The client first gets a `sync-snapshot` of
+/// every adopted document, so a reconnecting UI doesn't wait for the next status change.
+async fn handle_ws_connection(mut socket: WebSocket, fed: Arc<FederationState>) {
+    let state = &fed.app_state;
+    let _client = WsClientGuard::new(&state.ws_clients);
+    info!("[ws] Client connected ({} open)", state.ws_clients.load(Ordering::Relaxed));
+    // Subscribe before building the snapshot so no change between the two is lost
     let mut rx = state.ws_tx.subscribe();
 
+    let shared = fed.sync_service.get_shared_documents().await;
+    let snapshot = serde_json::json!({
+        "type": "sync-snapshot",
+        "count": shared.len(),
+        "items": shared,
+    });
+    if socket.send(Message::Text(snapshot.to_string().into())).await.is_err() {
+        info!("[ws] Client disconnected (send failed)");
+        return;
+    }
+
     loop {
         tokio::select! {
             // Forward broadcast messages to this client
@@ -80,15 +187,15 @@ async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
                 match msg {
                     Ok(text) => {
                         if socket.send(Message::Text(text.into())).await.is_err() {
-                            log_to_file("[ws] Client disconnected (send failed)");
+                            info!("[ws] Client disconnected (send failed)");
                             break;
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
-                        log_to_file(&format!("[ws] Client lagged by {} messages", n));
+                        warn!("[ws] Client lagged by {} messages", n);
                     }
                     Err(broadcast::error::RecvError::Closed) => {
-                        log_to_file("[ws] Broadcast channel closed");
+                        warn!("[ws] Broadcast channel closed");
                         break;
                     }
                 }
@@ -97,7 +204,7 @@ async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(Message::Close(_))) | None => {
-                        log_to_file("[ws] Client disconnected");
+                        info!("[ws] Client disconnected");
                         break;
                     }
                     Some(Ok(Message::Ping(data))) => {
@@ -107,7 +214,7 @@ async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
                         // Ignore other messages
                     }
                     Some(Err(e)) => {
-                        log_to_file(&format!("[ws] Client error: {}", e));
+                        warn!("[ws] Client error: {}", e);
                         break;
                     }
                 }
@@ -116,22 +223,98 @@ async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
     }
 }
 
-pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    log_to_file(&format!("start_server called with org_root={:?}, port={}", org_root, port));
+pub async fn start_server(
+    org_root: PathBuf,
+    port: config::Setting<u16>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    info!("start_server called with org_root={:?}, port={}", org_root, port.value);
 
     // Install rustls crypto provider (required before any TLS operations)
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
 
     let start_time = std::time::Instant::now();
+    // Background tasks live as long as this future; dropping it (on abort) stops them
+    let mut tasks: Vec<AbortOnDrop<()>> = Vec::new();
+
+    // Resolve configuration (defaults < .vitrum-config.json < env vars)
+    let config = ServerConfig::load(&org_root, port);
+    let port = config.port.value;
+    let bind = config.bind.value;
+    sync::set_checksum_algorithm(config.checksum.value);
+
+    // Load index from cache or build incrementally. If it outlasts the configured timeout,
+    // either fail or serve an empty index and swap the full one in when it finishes.
+    info!("Loading document index...");
+    let build_root = org_root.clone();
+    let max_documents = config.max_documents.value;
+    let max_file_mb = config.max_file_mb.value;
+    let exclude_files = config.exclude_files.value.clone();
+    let build_excludes = exclude_files.clone();
+    let search_weights = config.search_weights.value;
+    let follow_symlinks = config.follow_symlinks.value;
+    let state_dir = config.state_dir.value.clone();
+    let build_state_dir = state_dir.clone();
+    let mut build = tokio::spawn(async move {
+        let mut index = DocumentIndex::new(&build_root)
+            .with_state_dir(build_state_dir)
+            .with_follow_symlinks(follow_symlinks)
+            .with_max_documents(max_documents)
+            .with_max_file_mb(max_file_mb)
+            .with_excluded_files(build_excludes)
+            .with_search_weights(search_weights);
+        index.load_or_build().await.map(|counts| (index, counts))
+    });
 
-    // Load index from cache or build incrementally
-    log_to_file("Loading document index...");
-    let mut index = DocumentIndex::new(&org_root);
-    let (total, cached, parsed, removed) = index.load_or_build().await;
-    log_to_file(&format!(
-        "Index loaded: {} total ({} cached, {} parsed, {} removed)",
-        total, cached, parsed, removed
-    ));
+    let timeout_secs = config.index_timeout_secs.value;
+    let finished = if timeout_secs == 0 {
+        Some((&mut build).await)
+    } else {
+        tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), &mut build)
+            .await
+            .ok()
+    };
+
+    let (index, pending_build) = match finished {
+        Some(Ok(Ok((index, (total, cached, parsed, removed))))) => {
+            info!(
+                "Index loaded: {} total ({} cached, {} parsed, {} removed)",
+                total, cached, parsed, removed
+            );
+            (index, None)
+        }
+        Some(Ok(Err(e))) => {
+            error!("FATAL: {}", e);
+            return Err(Box::new(e));
+        }
+        Some(Err(e)) => {
+            error!("FATAL: index build failed: {}", e);
+            return Err(Box::new(e));
+        }
+        None if config.index_timeout_mode.value == IndexTimeoutMode::Abort => {
+            build.abort();
+            let msg = format!(
+                "Index build did not finish within {}s. Check the org root, or raise ORG_VIEWER_INDEX_TIMEOUT_SECS.",
+                timeout_secs
+            );
+            error!("FATAL: {}", msg);
+            return Err(msg.into());
+        }
+        None => {
+            warn!(
+                "Index build still running after {}s; serving an empty index until it finishes",
+                timeout_secs
+            );
+            let index = DocumentIndex::new(&org_root)
+                .with_state_dir(state_dir.clone())
+                .with_follow_symlinks(follow_symlinks)
+                .with_max_documents(max_documents)
+                .with_max_file_mb(max_file_mb)
+                .with_excluded_files(exclude_files)
+                .with_search_weights(search_weights)
+                .with_pending_build();
+            (index, Some(build))
+        }
+    };
 
     // Create broadcast channel for WebSocket live reload
     let (ws_tx, _) = broadcast::channel::<String>(64);
@@ -141,52 +324,92 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
         org_root: org_root.clone(),
         start_time,
         ws_tx,
+        activity: ActivityLog::new(&org_root, config.activity_log.value),
+        write_locks: PathLocks::default(),
+        index_ready: AtomicBool::new(pending_build.is_none()),
+        body_cache: RwLock::new(HashMap::new()),
+        ws_clients: AtomicUsize::new(0),
+        saved_searches: SavedSearches::new(&org_root, &state_dir),
+        config,
     });
 
+    if let Some(build) = pending_build {
+        let state = Arc::clone(&app_state);
+        let mut build = AbortOnDrop(build);
+        tasks.push(AbortOnDrop(tokio::spawn(async move {
+            match (&mut build.0).await {
+                Ok(Ok((built, (total, cached, parsed, removed)))) => {
+                    state.index.write().await.replace_with(built);
+                    state.index_ready.store(true, Ordering::SeqCst);
+                    info!(
+                        "Index loaded in background: {} total ({} cached, {} parsed, {} removed)",
+                        total, cached, parsed, removed
+                    );
+                    let msg = serde_json::json!({ "type": "index-ready" });
+                    let _ = state.ws_tx.send(msg.to_string());
+                }
+                Ok(Err(e)) => error!("Background index build failed: {}", e),
+                Err(e) => error!("Background index build failed: {}", e),
+            }
+        })));
+    }
+
     // Initialize federation services
-    log_to_file("Initializing federation services...");
-    let peer_registry = Arc::new(PeerRegistry::new(&org_root));
+    info!("Initializing federation services...");
+    let peer_registry = Arc::new(PeerRegistry::new(&org_root, &state_dir));
     let sync_service = Arc::new(SyncService::new(
         &org_root,
         Arc::clone(&app_state.index),
         Arc::clone(&peer_registry),
+        app_state.config.batch_concurrency.value,
     ));
 
     let fed_state = Arc::new(FederationState {
         app_state: Arc::clone(&app_state),
         peer_registry: Arc::clone(&peer_registry),
         sync_service: Arc::clone(&sync_service),
-        local_host: RwLock::new(None),
+        local_host: ("localhost".to_string(), port),
+        snippet_cache: RwLock::new(std::collections::HashMap::new()),
+        rate_limiter: federation::RateLimiter::default(),
     });
 
     // Set local host info
     sync_service.set_local_host("localhost".to_string(), port).await;
-    *fed_state.local_host.write().await = Some(("localhost".to_string(), port));
 
     // Start peer discovery polling
     let peer_count = peer_registry.get_peers().await.len();
-    log_to_file(&format!("Starting peer polling ({} peers configured)...", peer_count));
-    peer_registry.start_polling();
+    info!("Starting peer polling ({} peers configured)...", peer_count);
+    tasks.push(AbortOnDrop(peer_registry.start_polling()));
+
+    // Optional LAN discovery; advertise the listener peers can reach (HTTPS when TLS is configured)
+    if app_state.config.mdns.value {
+        let discovery = if app_state.config.tls_enabled() {
+            peer_registry.start_discovery(port + 1, "https")
+        } else {
+            peer_registry.start_discovery(port, "http")
+        };
+        tasks.push(AbortOnDrop(discovery));
+    }
 
     // Count shared documents BEFORE spawning file watcher to avoid RwLock deadlock.
     // The file watcher takes write locks on index for every file event, and
     // get_shared_documents() needs a read lock — on a large repo, events flood in
     // immediately and the write lock blocks the read lock indefinitely.
     let shared_count = sync_service.get_shared_documents().await.len();
-    log_to_file(&format!("Starting sync polling ({} adopted documents)...", shared_count));
+    info!("Starting sync polling ({} adopted documents)...", shared_count);
 
     // Set up sync status callback to broadcast via WebSocket
     let ws_tx_for_sync = app_state.ws_tx.clone();
     sync_service.on_status_change(Box::new(move |event| {
-        log_to_file(&format!(
+        info!(
             "Sync: {} {} → {}{}",
             event.path,
             event.old_status,
             event.new_status,
             event.peer.as_ref().map(|p| format!(" ({})", p)).unwrap_or_default()
-        ));
+        );
         let msg = serde_json::json!({
-            "type": "sync-status-changed",
+            "type": event.event_type,
             "path": event.path,
             "peer": event.peer,
             "timestamp": event.timestamp,
@@ -194,151 +417,299 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
         let _ = ws_tx_for_sync.send(msg.to_string());
     })).await;
 
-    sync_service.start_sync_polling();
+    // Broadcast batch operation progress ("n of total") via WebSocket
+    let ws_tx_for_batch = app_state.ws_tx.clone();
+    sync_service.on_batch_progress(Box::new(move |event| {
+        if let Ok(msg) = serde_json::to_string(&event) {
+            let _ = ws_tx_for_batch.send(msg);
+        }
+    })).await;
+
+    tasks.push(AbortOnDrop(sync_service.start_sync_polling()));
 
     // Start file watcher LAST — it takes write locks on the index for every file
     // event, so all setup that needs read locks must complete first.
-    log_to_file("Starting file watcher...");
-    let watcher_state = Arc::clone(&app_state);
-    let watcher_sync = Arc::clone(&sync_service);
-    tokio::spawn(async move {
-        if let Err(e) = FileWatcher::watch_with_sync(watcher_state, watcher_sync).await {
-            log_to_file(&format!("File watcher error: {}", e));
-        }
-    });
+    info!("Starting file watcher...");
+    tasks.push(AbortOnDrop(FileWatcher::spawn_supervised(
+        Arc::clone(&app_state),
+        Arc::clone(&sync_service),
+    )));
 
     // CORS configuration
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        // Checksums for If-Match writes travel in the ETag
+        .expose_headers([axum::http::header::ETAG]);
+
+    // gzip/brotli when the client accepts it; skip small bodies (health checks) and
+    // content that is already compressed (images, fonts) or streamed (SSE)
+    let compression = CompressionLayer::new().compress_when(
+        SizeAbove::new(1024)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::const_new("font/")),
+    );
 
     // Build federation sub-router with its own state
-    let fed_router = federation::create_federation_routes().with_state(Arc::clone(&fed_state));
+    let fed_router = federation::create_federation_routes(Arc::clone(&fed_state))
+        .with_state(Arc::clone(&fed_state));
 
     // Build router — API routes first, then static file fallback
     let app = Router::new()
         .route("/api/health", get(routes::health))
+        .route("/api/ping", get(routes::ping))
         .route("/api/status", get(routes::status))
-        .route("/api/files", get(routes::list_files))
-        .route("/api/files/{*path}", get(routes::get_file).put(routes::put_file))
+        .route("/api/files", get(routes::list_files).post(routes::create_file))
+        .route("/api/recent", get(routes::recent))
+        .route("/api/calendar", get(routes::calendar))
+        .route("/api/files/archive", post(routes::archive_file))
+        .route("/api/files/move", post(routes::move_file))
+        .route(
+            "/api/files/{*path}",
+            get(routes::get_file)
+                .put(routes::put_file)
+                .post(routes::refresh_file)
+                .delete(routes::delete_file),
+        )
+        .route("/api/raw/{*path}", get(routes::raw_file))
         .route("/api/search", get(routes::search))
+        .route("/api/resolve", get(routes::resolve_title))
+        .route("/api/backlinks/{*path}", get(routes::backlinks))
+        .route("/api/unlinked-mentions/{*path}", get(routes::unlinked_mentions))
         .route("/api/graph", get(routes::graph))
+        .route("/api/graph/path", get(routes::graph_path))
+        .route("/api/folder-index", get(routes::folder_index))
+        .route("/api/tree", get(routes::folder_tree))
+        .route(
+            "/api/saved-searches",
+            get(searches::list_saved_searches).post(searches::save_search),
+        )
+        .route("/api/saved-searches/{name}", delete(searches::delete_saved_search))
+        .route("/api/index-warnings", get(routes::index_warnings))
+        .route("/api/diagnostics", get(routes::diagnostics))
+        .route("/api/index/generation", get(routes::index_generation))
+        .route("/api/external-links", get(routes::external_links))
+        .route("/api/tasks", get(routes::tasks))
+        .route("/api/tags", get(routes::tags))
+        .route("/api/tags/tree", get(routes::tag_tree))
+        .route("/api/tags/rename", post(routes::rename_tag))
+        .route("/api/config/effective", get(routes::effective_config))
+        .route("/api/activity", get(routes::activity))
         .route("/api/projects", get(projects::list_projects))
         .route("/api/projects/{name}/tree", get(projects::get_tree))
         .route("/api/projects/{name}/file/{*path}", get(projects::get_file).put(projects::put_file))
         .route("/api/debug-log", post(routes::debug_log))
-        .route("/ws", get(ws_handler))
+        .route("/ws", get(ws_handler).with_state(Arc::clone(&fed_state)))
         // Federation routes (nested with their own state)
         .nest("/api/federation", fed_router)
         // Static file serving (embedded client dist)
         .fallback(static_files::static_handler)
+        .layer(compression)
         .layer(cors)
-        .with_state(app_state);
-
-    log_to_file("File watcher spawned, now binding server...");
-    log_to_file(&format!("Federation: {} peers configured", peer_count));
-    log_to_file(&format!("Sync: watching {} adopted document(s)", shared_count));
-
-    // Check for TLS certificates (for Tailscale HTTPS access)
-    let tls_cert = env::var("ORG_VIEWER_TLS_CERT").ok();
-    let tls_key = env::var("ORG_VIEWER_TLS_KEY").ok();
-
-    match (&tls_cert, &tls_key) {
-        (Some(cert_path), Some(key_path)) => {
-            // Dual-listener mode: HTTP on localhost (for Tauri WebView) + HTTPS on 0.0.0.0 (for Tailscale)
-            log_to_file(&format!("TLS enabled: cert={}, key={}", cert_path, key_path));
+        // One span per request, so handler logs carry the method and URI
+        .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
+        .with_state(Arc::clone(&app_state));
+
+    info!("File watcher spawned, now binding server...");
+    info!("Federation: {} peers configured", peer_count);
+    info!("Sync: watching {} adopted document(s)", shared_count);
+
+    // HTTPS (Tailscale certs, or a generated self-signed one for the LAN) alongside the
+    // local HTTP listener; without it, a single HTTP listener
+    let tls = load_tls_config(&app_state.config).await;
+
+    if let Some((tls_config, label)) = tls {
+        // Pick up renewed cert files (self-signed ones only change when deleted, on restart)
+        let config = &app_state.config;
+        if let (Some(cert), Some(key)) = (&config.tls_cert.value, &config.tls_key.value) {
+            let (cert, key) = (PathBuf::from(cert), PathBuf::from(key));
+            tasks.push(AbortOnDrop(tls::watch_cert_files(tls_config.clone(), cert, key)));
+        }
 
-            let config = match RustlsConfig::from_pem_file(cert_path, key_path).await {
-                Ok(c) => Some(c),
+        // Dual-listener: HTTP on localhost (WebView) + HTTPS on the bind address (peers/devices).
+        // Both run inside this future so cancelling the server closes both sockets.
+        let local_addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let local_app = app.clone();
+        let local = async move {
+            match tokio::net::TcpListener::bind(local_addr).await {
+                Ok(listener) => {
+                    info!("SUCCESS: HTTP listener on http://{} (WebView)", local_addr);
+                    if let Err(e) = axum::serve(
+                        listener,
+                        local_app.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .await
+                    {
+                        error!("HTTP serve error: {}", e);
+                    }
+                }
                 Err(e) => {
-                    log_to_file(&format!("FAILED to load TLS certs: {}", e));
-                    log_to_file("Hint: Run 'tailscale cert <your-hostname>' to generate certs");
-                    log_to_file("Falling back to HTTP-only mode");
-                    None
+                    error!("FAILED to bind HTTP on {}: {}", local_addr, e);
                 }
-            };
-
-            if let Some(tls_config) = config {
-                // Dual-listener: HTTP on localhost (WebView) + HTTPS on 0.0.0.0 (Tailscale)
-                let local_addr = SocketAddr::from(([127, 0, 0, 1], port));
-                let local_app = app.clone();
-                tokio::spawn(async move {
-                    match tokio::net::TcpListener::bind(local_addr).await {
-                        Ok(listener) => {
-                            log_to_file(&format!("SUCCESS: HTTP listener on http://{} (WebView)", local_addr));
-                            if let Err(e) = axum::serve(listener, local_app).await {
-                                log_to_file(&format!("HTTP serve error: {}", e));
-                            }
-                        }
-                        Err(e) => {
-                            log_to_file(&format!("FAILED to bind HTTP on {}: {}", local_addr, e));
-                        }
-                    }
-                });
-
-                let tls_port = port + 1;
-                let tls_addr = SocketAddr::from(([0, 0, 0, 0], tls_port));
-                log_to_file(&format!("SUCCESS: HTTPS listener on https://0.0.0.0:{} (Tailscale)", tls_port));
+            }
+            // Keep serving HTTPS without the local listener
+            std::future::pending::<()>().await
+        };
 
-                if let Err(e) = axum_server::bind_rustls(tls_addr, tls_config)
-                    .serve(app.into_make_service())
-                    .await
-                {
-                    log_to_file(&format!("Axum TLS serve error: {}", e));
-                    return Err(e.into());
-                }
-            } else {
-                // TLS certs failed to load — fall through to HTTP-only
-                let addr = SocketAddr::from(([0, 0, 0, 0], port));
-                log_to_file(&format!("Attempting to bind to http://{}", addr));
-
-                let listener = match tokio::net::TcpListener::bind(addr).await {
-                    Ok(l) => {
-                        log_to_file(&format!("SUCCESS: Server listening on http://{}", addr));
-                        l
-                    }
-                    Err(e) => {
-                        log_to_file(&format!("FAILED to bind: {}", e));
-                        return Err(e.into());
-                    }
-                };
+        let tls_port = port + 1;
+        let tls_addr = SocketAddr::new(bind, tls_port);
+        info!("SUCCESS: HTTPS listener on https://{} ({})", tls_addr, label);
 
-                log_to_file("Starting axum serve loop...");
-                if let Err(e) = axum::serve(listener, app).await {
-                    log_to_file(&format!("Axum serve error: {}", e));
+        let serve_tls = axum_server::bind_rustls(tls_addr, tls_config)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+        tokio::select! {
+            _ = local => {}
+            result = serve_tls => {
+                if let Err(e) = result {
+                    error!("Axum TLS serve error: {}", e);
                     return Err(e.into());
                 }
             }
         }
-        _ => {
-            if tls_cert.is_some() || tls_key.is_some() {
-                log_to_file("WARNING: Both ORG_VIEWER_TLS_CERT and ORG_VIEWER_TLS_KEY must be set for TLS. Falling back to HTTP.");
+    } else {
+        // Single HTTP listener on the bind address (no TLS)
+        let addr = SocketAddr::new(bind, port);
+        info!("Attempting to bind to http://{}", addr);
+
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(l) => {
+                info!("SUCCESS: Server listening on http://{}", addr);
+                l
+            }
+            Err(e) => {
+                error!("FAILED to bind: {}", e);
+                return Err(e.into());
             }
+        };
+
+        info!("Starting axum serve loop...");
+        if let Err(e) = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        {
+            error!("Axum serve error: {}", e);
+            return Err(e.into());
+        }
+    }
 
-            // Single HTTP listener on 0.0.0.0 (no TLS)
-            let addr = SocketAddr::from(([0, 0, 0, 0], port));
-            log_to_file(&format!("Attempting to bind to http://{}", addr));
+    info!("Server shut down normally");
+    Ok(())
+}
 
-            let listener = match tokio::net::TcpListener::bind(addr).await {
-                Ok(l) => {
-                    log_to_file(&format!("SUCCESS: Server listening on http://{}", addr));
-                    l
+/// TLS settings for the HTTPS listener, with a label for the log. Cert/key files win when
+/// both are set; otherwise `tlsSelfSigned` generates (or reuses) a self-signed certificate.
+/// Any failure falls back to HTTP-only.
+async fn load_tls_config(config: &ServerConfig) -> Option<(RustlsConfig, &'static str)> {
+    match (&config.tls_cert.value, &config.tls_key.value) {
+        (Some(cert_path), Some(key_path)) => {
+            info!("TLS enabled: cert={}, key={}", cert_path, key_path);
+            match RustlsConfig::from_pem_file(cert_path, key_path).await {
+                Ok(c) => Some((c, "Tailscale")),
+                Err(e) => {
+                    error!("FAILED to load TLS certs: {}", e);
+                    warn!("Hint: Run 'tailscale cert <your-hostname>' to generate certs");
+                    warn!("Falling back to HTTP-only mode");
+                    None
+                }
+            }
+        }
+        (cert, key) => {
+            if cert.is_some() || key.is_some() {
+                warn!("Both ORG_VIEWER_TLS_CERT and ORG_VIEWER_TLS_KEY must be set for TLS.");
+            }
+            if !config.tls_self_signed.value {
+                if cert.is_some() || key.is_some() {
+                    warn!("Falling back to HTTP-only mode");
                 }
+                return None;
+            }
+
+            let dir = tls::self_signed_dir();
+            let loaded = tokio::task::spawn_blocking(move || tls::load_or_create_self_signed(&dir))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            let (cert, key) = match loaded {
+                Ok(pair) => pair,
                 Err(e) => {
-                    log_to_file(&format!("FAILED to bind: {}", e));
-                    return Err(e.into());
+                    error!("FAILED to set up self-signed TLS: {}", e);
+                    warn!("Falling back to HTTP-only mode");
+                    return None;
                 }
             };
-
-            log_to_file("Starting axum serve loop...");
-            if let Err(e) = axum::serve(listener, app).await {
-                log_to_file(&format!("Axum serve error: {}", e));
-                return Err(e.into());
+            if let Some(fp) = tls::pem_fingerprint(&cert) {
+                info!("Self-signed certificate fingerprint (certFingerprint for peers): {}", fp);
+            }
+            match RustlsConfig::from_pem(cert, key).await {
+                Ok(c) => Some((c, "self-signed")),
+                Err(e) => {
+                    error!("FAILED to load self-signed certificate: {}", e);
+                    warn!("Falling back to HTTP-only mode");
+                    None
+                }
             }
         }
     }
+}
 
-    log_to_file("Server shut down normally");
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_path_stays_under_root() {
+        let root = Path::new("/vault");
+        assert_eq!(
+            resolve_safe_path(root, "notes/a.md"),
+            Some(root.join("notes/a.md"))
+        );
+        assert_eq!(
+            resolve_safe_path(root, "./notes/../a.md"),
+            Some(root.join("a.md"))
+        );
+        assert_eq!(
+            resolve_safe_path(root, "notes\\a.md"),
+            Some(root.join("notes/a.md"))
+        );
+    }
+
+    #[test]
+    fn safe_path_rejects_parent_dirs_above_root() {
+        let root = Path::new("/vault");
+        assert_eq!(resolve_safe_path(root, ".."), None);
+        assert_eq!(resolve_safe_path(root, "../etc/passwd"), None);
+        assert_eq!(resolve_safe_path(root, "notes/../../etc/passwd"), None);
+        assert_eq!(resolve_safe_path(root, "notes\\..\\..\\secret.md"), None);
+    }
+
+    #[test]
+    fn safe_path_rejects_absolute_paths_and_drive_letters() {
+        let root = Path::new("/vault");
+        assert_eq!(resolve_safe_path(root, "/etc/passwd"), None);
+        assert_eq!(resolve_safe_path(root, "\\\\server\\share\\a.md"), None);
+        assert_eq!(resolve_safe_path(root, "C:\\Windows\\win.ini"), None);
+        assert_eq!(resolve_safe_path(root, "c:/a.md"), None);
+        assert_eq!(resolve_safe_path(root, "C:a.md"), None);
+    }
+
+    #[test]
+    fn safe_path_keeps_encoded_slashes_literal() {
+        // Extractors have already percent-decoded the path; anything still encoded is part of
+        // a file name, not a separator
+        let root = Path::new("/vault");
+        assert_eq!(
+            resolve_safe_path(root, "..%2F..%2Fetc%2Fpasswd"),
+            Some(root.join("..%2F..%2Fetc%2Fpasswd"))
+        );
+        assert_eq!(
+            resolve_safe_path(root, "notes%2F..%2F..%5Ca.md")
+                .unwrap()
+                .parent(),
+            Some(root)
+        );
+    }
 }
@@ -1,18 +1,42 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::RwLock;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use crate::server::log_to_file;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+use crate::server::config::state_file;
+use crate::server::pinning::{federation_client, parse_fingerprint, PinnedCertVerifier};
 
 const PEER_CONFIG_FILE: &str = ".vitrum-peers.json";
+/// Last known live status of each peer, so `lastSeen` and backoff survive restarts
+const PEER_STATUS_FILE: &str = ".vitrum-peer-status.json";
+/// Default for `self.pollIntervalSecs`
 const POLL_INTERVAL_SECS: u64 = 30;
-const BACKOFF_INTERVAL_SECS: u64 = 120;
+/// Floor for the configurable poll and sync intervals
+const MIN_INTERVAL_SECS: u64 = 5;
+/// Default for `self.rateLimitPerMinute` (inbound federation requests per remote IP)
+const RATE_LIMIT_PER_MINUTE: u32 = 120;
+/// Longest a failing peer is left alone between retries
+const BACKOFF_MAX_SECS: u64 = 3600;
 const FAILURE_THRESHOLD: u32 = 3;
 const HELLO_TIMEOUT_SECS: u64 = 3;
+/// Default timeout of the shared federation client; document transfers set a longer one
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+/// Timeout for requests that move whole documents (adopt, push, fetching the origin body)
+pub const TRANSFER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Poll outcomes kept per peer for the latency/uptime history
+const HISTORY_WINDOW: usize = 50;
+
+/// mDNS service type advertised and browsed for automatic LAN discovery
+const MDNS_SERVICE_TYPE: &str = "_vitrum._tcp.local.";
+
+/// Header carrying the requesting instance's `instanceId` on federation reads
+pub const INSTANCE_HEADER: &str = "x-vitrum-instance";
 
 // --- Config types ---
 
@@ -30,9 +54,150 @@ pub struct PeerSelf {
     #[serde(rename = "displayName")]
     pub display_name: String,
     #[serde(rename = "sharedFolders")]
-    pub shared_folders: Vec<String>,
+    pub shared_folders: Vec<SharedFolder>,
     #[serde(rename = "sharedTags")]
     pub shared_tags: Vec<String>,
+    /// When set, federation routes require `Authorization: Bearer <authToken>`
+    #[serde(rename = "authToken", default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Seconds between peer polls; read at startup
+    #[serde(rename = "pollIntervalSecs", default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_secs: Option<u64>,
+    /// Seconds between origin-checksum sync checks; read at startup
+    #[serde(rename = "syncIntervalSecs", default, skip_serializing_if = "Option::is_none")]
+    pub sync_interval_secs: Option<u64>,
+    /// Inbound federation requests allowed per remote IP per minute; 0 disables the limit
+    #[serde(rename = "rateLimitPerMinute", default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_minute: Option<u32>,
+}
+
+impl PeerSelf {
+    pub fn poll_interval(&self) -> std::time::Duration {
+        interval_or(self.poll_interval_secs, POLL_INTERVAL_SECS, "pollIntervalSecs")
+    }
+
+    pub fn rate_limit_per_minute(&self) -> u32 {
+        self.rate_limit_per_minute.unwrap_or(RATE_LIMIT_PER_MINUTE)
+    }
+
+    /// Sync check interval, falling back to the sync service's default
+    pub fn sync_interval(&self, default_secs: u64) -> std::time::Duration {
+        interval_or(self.sync_interval_secs, default_secs, "syncIntervalSecs")
+    }
+
+    /// Shared folder paths, as advertised in `/hello`
+    pub fn shared_folder_paths(&self) -> Vec<String> {
+        self.shared_folders.iter().map(|f| f.path.clone()).collect()
+    }
+
+    /// Most specific shared folder containing `path`
+    fn folder_for(&self, path: &str) -> Option<&SharedFolder> {
+        self.shared_folders
+            .iter()
+            .filter(|f| in_folder(path, &f.path))
+            .max_by_key(|f| f.path.len())
+    }
+
+    /// Whether a peer known by any of `requester` (instanceId / names) may read `path`
+    pub fn can_read(&self, path: &str, requester: &[String]) -> bool {
+        self.folder_for(path)
+            .map(|f| f.allows(requester))
+            .unwrap_or(false)
+    }
+
+    /// Whether a peer known by any of `requester` may write `path`
+    pub fn can_write(&self, path: &str, requester: &[String]) -> bool {
+        self.folder_for(path)
+            .map(|f| f.permission == SharedPermission::Write && f.allows(requester))
+            .unwrap_or(false)
+    }
+}
+
+/// Split a peer address (`host:port`, e.g. an `origin-host`). None if the port is missing
+/// or not a number: instances pick their own port, so there is no default to assume.
+pub fn split_host_port(addr: &str) -> Option<(&str, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    Some((host, port.parse().ok()?))
+}
+
+/// Whether vault path `path` is `folder` or inside it. Compares whole segments, so
+/// `notes` (or `notes/`) doesn't admit `notes-private/todo.md`; an empty folder is the root.
+pub fn in_folder(path: &str, folder: &str) -> bool {
+    let folder = folder.trim_end_matches('/');
+    folder.is_empty()
+        || path
+            .strip_prefix(folder)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharedPermission {
+    #[default]
+    Read,
+    Write,
+}
+
+/// A shared folder root. In `.vitrum-peers.json` this is either a plain path string
+/// (read-only, any peer) or `{ "path", "permission", "allowedPeers" }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SharedFolderEntry", into = "SharedFolderEntry")]
+pub struct SharedFolder {
+    pub path: String,
+    pub permission: SharedPermission,
+    /// Peer instanceIds or names allowed to access the folder; empty means any peer
+    pub allowed_peers: Vec<String>,
+}
+
+impl SharedFolder {
+    fn allows(&self, requester: &[String]) -> bool {
+        self.allowed_peers.is_empty() || self.allowed_peers.iter().any(|p| requester.contains(p))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SharedFolderEntry {
+    Path(String),
+    Rule {
+        path: String,
+        #[serde(default)]
+        permission: SharedPermission,
+        #[serde(rename = "allowedPeers", default, skip_serializing_if = "Vec::is_empty")]
+        allowed_peers: Vec<String>,
+    },
+}
+
+impl From<SharedFolderEntry> for SharedFolder {
+    fn from(entry: SharedFolderEntry) -> Self {
+        match entry {
+            SharedFolderEntry::Path(path) => SharedFolder {
+                path,
+                permission: SharedPermission::Read,
+                allowed_peers: Vec::new(),
+            },
+            SharedFolderEntry::Rule { path, permission, allowed_peers } => SharedFolder {
+                path,
+                permission,
+                allowed_peers,
+            },
+        }
+    }
+}
+
+impl From<SharedFolder> for SharedFolderEntry {
+    fn from(folder: SharedFolder) -> Self {
+        // Keep plain read-only shares as plain strings
+        if folder.permission == SharedPermission::Read && folder.allowed_peers.is_empty() {
+            SharedFolderEntry::Path(folder.path)
+        } else {
+            SharedFolderEntry::Rule {
+                path: folder.path,
+                permission: folder.permission,
+                allowed_peers: folder.allowed_peers,
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,11 +206,84 @@ pub struct PeerEntry {
     pub host: String,
     pub port: u16,
     pub protocol: String,
+    /// Token sent to this peer (must match its `self.authToken`)
+    #[serde(rename = "authToken", default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Token this peer sends us. A request bearing it is identified as this peer for
+    /// `allowedPeers`, `allowedFolders` and adoption.
+    #[serde(rename = "inboundToken", default, skip_serializing_if = "Option::is_none")]
+    pub inbound_token: Option<String>,
+    /// This peer's instanceId, pinned here so `allowedPeers` and adoption records can use
+    /// it. What the peer reports in `/hello` is never used as its identity.
+    #[serde(rename = "instanceId", default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    /// Restrict this peer to these folders (within `self.sharedFolders`); absent means all shares
+    #[serde(rename = "allowedFolders", default, skip_serializing_if = "Option::is_none")]
+    pub allowed_folders: Option<Vec<String>>,
+    /// SHA-256 of the peer's TLS certificate (DER, hex). When set, any other cert is rejected.
+    #[serde(rename = "certFingerprint", default, skip_serializing_if = "Option::is_none")]
+    pub cert_fingerprint: Option<String>,
+}
+
+impl PeerEntry {
+    /// Trim the name, and trim and lowercase the host and protocol, so `HTTPS` or
+    /// ` Host.local` in hand-edited config still form a working URL
+    pub fn normalize(&mut self) {
+        self.name = self.name.trim().to_string();
+        self.host = self.host.trim().to_lowercase();
+        self.protocol = self.protocol.trim().to_lowercase();
+    }
+
+    /// Reject entries that can't form a peer URL
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.host.is_empty()
+            || self
+                .host
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, '/' | '?' | '#' | '@'))
+        {
+            return Err(format!("invalid host '{}'", self.host));
+        }
+        if self.port == 0 {
+            return Err("port must be 1-65535".to_string());
+        }
+        if self.protocol != "http" && self.protocol != "https" {
+            return Err(format!("protocol must be http or https, got '{}'", self.protocol));
+        }
+        if let Some(fp) = &self.cert_fingerprint {
+            if self.protocol != "https" {
+                return Err("certFingerprint requires protocol https".to_string());
+            }
+            if parse_fingerprint(fp).is_none() {
+                return Err(format!("invalid certFingerprint '{}'", fp));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Attach a peer's federation token, if any, as `Authorization: Bearer`
+pub fn with_peer_auth(
+    request: reqwest::RequestBuilder,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
 }
 
 // --- Live status ---
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerLiveStatus {
     pub name: String,
     pub host: String,
@@ -66,8 +304,34 @@ pub struct PeerLiveStatus {
     pub last_seen: Option<String>,
     #[serde(rename = "latencyMs", skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<u64>,
-    #[serde(rename = "consecutiveFailures")]
+    #[serde(rename = "consecutiveFailures", default)]
     pub consecutive_failures: u32,
+    /// Earliest time a failing peer is polled again (RFC 3339); None when not backing off
+    #[serde(rename = "nextRetry", default, skip_serializing_if = "Option::is_none")]
+    pub next_retry: Option<String>,
+    /// Found via mDNS rather than configured in `.vitrum-peers.json`
+    #[serde(default)]
+    pub discovered: bool,
+}
+
+/// One poll of a peer, as kept in the rolling history window
+#[derive(Debug, Clone, Serialize)]
+pub struct PollSample {
+    pub timestamp: String,
+    pub success: bool,
+    #[serde(rename = "latencyMs", skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerHistory {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    /// Percentage of successful polls in the window; None before the first poll
+    #[serde(rename = "uptimePercent")]
+    pub uptime_percent: Option<f64>,
+    pub samples: Vec<PollSample>,
 }
 
 // --- Hello response (from remote peer) ---
@@ -91,26 +355,91 @@ pub struct PeerHelloStats {
     pub document_count: usize,
 }
 
+/// A configured interval, or `default_secs` when unset. Values below `MIN_INTERVAL_SECS`
+/// are raised to it.
+fn interval_or(configured: Option<u64>, default_secs: u64, key: &str) -> std::time::Duration {
+    let secs = match configured {
+        Some(secs) if secs < MIN_INTERVAL_SECS => {
+            warn!(
+                "{}: self.{} of {}s is below the {}s minimum, using {}s",
+                PEER_CONFIG_FILE, key, secs, MIN_INTERVAL_SECS, MIN_INTERVAL_SECS
+            );
+            MIN_INTERVAL_SECS
+        }
+        Some(secs) => secs,
+        None => default_secs,
+    };
+    std::time::Duration::from_secs(secs)
+}
+
+/// How long to leave a peer alone after `consecutive_failures` failed polls: nothing below
+/// `FAILURE_THRESHOLD`, then doubling from the poll interval up to `BACKOFF_MAX_SECS`.
+pub fn backoff_secs(consecutive_failures: u32) -> u64 {
+    if consecutive_failures < FAILURE_THRESHOLD {
+        return 0;
+    }
+    let doublings = (consecutive_failures - FAILURE_THRESHOLD).min(16);
+    (POLL_INTERVAL_SECS << doublings).min(BACKOFF_MAX_SECS)
+}
+
+/// Shuts the mDNS daemon down when dropped
+struct DaemonShutdown(ServiceDaemon);
+
+impl Drop for DaemonShutdown {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown();
+    }
+}
+
 // --- PeerRegistry ---
 
 pub struct PeerRegistry {
     config_path: PathBuf,
+    status_path: PathBuf,
     config: RwLock<PeerConfig>,
+    /// Configured peers that failed validation, as written; kept out of `config` but saved
+    /// back with it
+    invalid_peers: RwLock<Vec<PeerEntry>>,
     status: RwLock<HashMap<String, PeerLiveStatus>>,
+    /// Last `HISTORY_WINDOW` polls per peer, oldest first (in memory only)
+    history: RwLock<HashMap<String, VecDeque<PollSample>>>,
     last_config_mtime: RwLock<u64>,
+    /// One connection pool for all outbound federation requests
+    client: reqwest::Client,
+    /// Certificate pins from `certFingerprint`, kept in step with the config
+    verifier: Arc<PinnedCertVerifier>,
 }
 
 impl PeerRegistry {
-    pub fn new(org_root: &Path) -> Self {
-        let config_path = org_root.join(PEER_CONFIG_FILE);
-        let config = Self::load_or_create(&config_path);
-        let status = Self::init_status(&config);
+    /// Peer config and status are kept in `state_dir` (the org root unless
+    /// `ORG_VIEWER_STATE_DIR` is set)
+    pub fn new(org_root: &Path, state_dir: &Path) -> Self {
+        let config_path = state_file(org_root, state_dir, PEER_CONFIG_FILE);
+        let mut config = Self::load_or_create(&config_path);
+        let invalid_peers = Self::split_invalid_peers(&mut config);
+        let status_path = state_file(org_root, state_dir, PEER_STATUS_FILE);
+        let mut status = Self::init_status(&config);
+        Self::restore_status(&status_path, &mut status);
+
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let verifier = Arc::new(PinnedCertVerifier::new(Arc::clone(&provider)));
+        verifier.set_pins(&config.peers);
+        let client = federation_client(
+            Arc::clone(&verifier),
+            provider,
+            std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS),
+        );
 
         PeerRegistry {
             config_path,
+            status_path,
             config: RwLock::new(config),
+            invalid_peers: RwLock::new(invalid_peers),
             status: RwLock::new(status),
+            history: RwLock::new(HashMap::new()),
             last_config_mtime: RwLock::new(0),
+            client,
+            verifier,
         }
     }
 
@@ -120,7 +449,7 @@ impl PeerRegistry {
                 if let Ok(config) = serde_json::from_str::<PeerConfig>(&raw) {
                     return config;
                 }
-                log_to_file(&format!("Failed to parse {}", PEER_CONFIG_FILE));
+                error!("Failed to parse {}", PEER_CONFIG_FILE);
             }
         }
 
@@ -128,49 +457,208 @@ impl PeerRegistry {
             self_info: PeerSelf {
                 instance_id: Uuid::new_v4().to_string(),
                 display_name: "My Org".to_string(),
-                shared_folders: vec!["knowledge/".to_string()],
+                shared_folders: vec![SharedFolder {
+                    path: "knowledge/".to_string(),
+                    permission: SharedPermission::Read,
+                    allowed_peers: Vec::new(),
+                }],
                 shared_tags: vec![],
+                auth_token: None,
+                poll_interval_secs: None,
+                sync_interval_secs: None,
+                rate_limit_per_minute: None,
             },
             peers: vec![],
         };
 
         if let Ok(json) = serde_json::to_string_pretty(&config) {
             let _ = std::fs::write(path, json);
-            log_to_file(&format!(
-                "Created {} with instanceId: {}",
-                PEER_CONFIG_FILE, config.self_info.instance_id
-            ));
+            info!("Created {} with instanceId: {}", PEER_CONFIG_FILE, config.self_info.instance_id);
         }
 
         config
     }
 
+    /// Normalize configured peers and move any that still fail validation out of the active
+    /// set, so a typo like `htttp` is reported once instead of polling a broken URL forever.
+    /// Returns the moved entries as written; `save_config` writes them back, so saving the
+    /// peer list from the API never deletes a hand-edited entry.
+    fn split_invalid_peers(config: &mut PeerConfig) -> Vec<PeerEntry> {
+        let mut invalid = Vec::new();
+        config.peers.retain_mut(|peer| {
+            let as_written = peer.clone();
+            peer.normalize();
+            match peer.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Ignoring peer '{}' in {}: {}", peer.name, PEER_CONFIG_FILE, e);
+                    invalid.push(as_written);
+                    false
+                }
+            }
+        });
+        invalid
+    }
+
     fn init_status(config: &PeerConfig) -> HashMap<String, PeerLiveStatus> {
         let mut map = HashMap::new();
         for peer in &config.peers {
             let key = format!("{}:{}", peer.host, peer.port);
-            map.insert(
-                key,
-                PeerLiveStatus {
-                    name: peer.name.clone(),
-                    host: peer.host.clone(),
-                    port: peer.port,
-                    protocol: peer.protocol.clone(),
-                    status: "unknown".to_string(),
-                    instance_id: None,
-                    display_name: None,
-                    shared_folders: None,
-                    shared_tags: None,
-                    document_count: None,
-                    last_seen: None,
-                    latency_ms: None,
-                    consecutive_failures: 0,
-                },
-            );
+            map.insert(key, Self::unknown_status(peer));
         }
         map
     }
 
+    /// Carry over what the last run knew about configured peers. Everyone stays `unknown`
+    /// until polled (we can't assume they're still up), but `lastSeen`, latency and the
+    /// failure count — which drives backoff — are kept.
+    fn restore_status(path: &Path, status: &mut HashMap<String, PeerLiveStatus>) {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(_) => return,
+        };
+        let saved: HashMap<String, PeerLiveStatus> = match serde_json::from_str(&raw) {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!("Failed to parse {}: {}", PEER_STATUS_FILE, e);
+                return;
+            }
+        };
+
+        for (key, entry) in status.iter_mut() {
+            if let Some(prev) = saved.get(key) {
+                entry.instance_id = prev.instance_id.clone();
+                entry.display_name = prev.display_name.clone();
+                entry.shared_folders = prev.shared_folders.clone();
+                entry.shared_tags = prev.shared_tags.clone();
+                entry.document_count = prev.document_count;
+                entry.last_seen = prev.last_seen.clone();
+                entry.latency_ms = prev.latency_ms;
+                entry.consecutive_failures = prev.consecutive_failures;
+                entry.next_retry = prev.next_retry.clone();
+            }
+        }
+    }
+
+    /// Persist the status map (configured peers only; discovered ones are re-found via mDNS)
+    async fn save_status(&self) {
+        let status: HashMap<String, PeerLiveStatus> = self
+            .status
+            .read()
+            .await
+            .iter()
+            .filter(|(_, s)| !s.discovered)
+            .map(|(k, s)| (k.clone(), s.clone()))
+            .collect();
+
+        match serde_json::to_string_pretty(&status) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&self.status_path, json) {
+                    error!("Failed to write {}: {}", PEER_STATUS_FILE, e);
+                }
+            }
+            Err(e) => error!("Failed to serialize peer status: {}", e),
+        }
+    }
+
+    /// Initial live status for a configured peer, before it has been polled
+    fn unknown_status(peer: &PeerEntry) -> PeerLiveStatus {
+        PeerLiveStatus {
+            name: peer.name.clone(),
+            host: peer.host.clone(),
+            port: peer.port,
+            protocol: peer.protocol.clone(),
+            status: "unknown".to_string(),
+            instance_id: None,
+            display_name: None,
+            shared_folders: None,
+            shared_tags: None,
+            document_count: None,
+            last_seen: None,
+            latency_ms: None,
+            consecutive_failures: 0,
+            next_retry: None,
+            discovered: false,
+        }
+    }
+
+    /// Write the config back to `.vitrum-peers.json`, along with the invalid entries left
+    /// out of it, recording its mtime so the hot-reload check doesn't treat our own write
+    /// as an external edit.
+    async fn save_config(&self, config: &PeerConfig) -> Result<(), String> {
+        let mut on_disk = config.clone();
+        on_disk.peers.extend(self.invalid_peers.read().await.iter().cloned());
+        let json = serde_json::to_string_pretty(&on_disk).map_err(|e| e.to_string())?;
+        std::fs::write(&self.config_path, json)
+            .map_err(|e| format!("Failed to write {}: {}", PEER_CONFIG_FILE, e))?;
+
+        let mtime = std::fs::metadata(&self.config_path)
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        *self.last_config_mtime.write().await = mtime;
+        Ok(())
+    }
+
+    /// Add a configured peer, persist it, and poll it once. Returns false if a peer with
+    /// the same host:port is already configured.
+    pub async fn add_peer(&self, peer: PeerEntry) -> Result<bool, String> {
+        let key = format!("{}:{}", peer.host, peer.port);
+        // Config lock is released before saving (save_config takes the mtime lock,
+        // which check_config_reload holds while reading the config)
+        let updated = {
+            let config = self.config.read().await;
+            if config.peers.iter().any(|p| p.host == peer.host && p.port == peer.port) {
+                return Ok(false);
+            }
+            let mut updated = config.clone();
+            updated.peers.push(peer.clone());
+            updated
+        };
+        self.save_config(&updated).await?;
+        self.verifier.set_pins(&updated.peers);
+        *self.config.write().await = updated;
+
+        // Same reconciliation as a hot reload: a configured entry replaces a discovered one
+        self.status
+            .write()
+            .await
+            .insert(key.clone(), Self::unknown_status(&peer));
+        info!("Peer added: {} ({})", peer.name, key);
+
+        self.poll_peer(peer).await;
+        Ok(true)
+    }
+
+    /// Remove a configured peer and persist. Returns false if no such peer was configured.
+    pub async fn remove_peer(&self, host: &str, port: u16) -> Result<bool, String> {
+        let updated = {
+            let config = self.config.read().await;
+            let mut updated = config.clone();
+            updated.peers.retain(|p| !(p.host == host && p.port == port));
+            if updated.peers.len() == config.peers.len() {
+                return Ok(false);
+            }
+            updated
+        };
+        self.save_config(&updated).await?;
+        self.verifier.set_pins(&updated.peers);
+        *self.config.write().await = updated;
+
+        let key = format!("{}:{}", host, port);
+        self.status.write().await.remove(&key);
+        self.history.write().await.remove(&key);
+        info!("Peer removed: {}", key);
+        Ok(true)
+    }
+
+    /// Shared HTTP client for talking to peers. Clones share the connection pool.
+    pub fn client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
     pub async fn get_self(&self) -> PeerSelf {
         self.config.read().await.self_info.clone()
     }
@@ -179,10 +667,110 @@ impl PeerRegistry {
         self.config.read().await.peers.clone()
     }
 
+    /// Token configured for the peer at `host:port`, for outbound requests
+    pub async fn auth_token_for(&self, host: &str, port: u16) -> Option<String> {
+        self.config
+            .read()
+            .await
+            .peers
+            .iter()
+            .find(|p| p.host == host && p.port == port)
+            .and_then(|p| p.auth_token.clone())
+    }
+
     pub async fn get_peer_status(&self) -> Vec<PeerLiveStatus> {
         self.status.read().await.values().cloned().collect()
     }
 
+    /// Recent poll history for the peer at `host:port`, or None if it isn't known
+    pub async fn get_peer_history(&self, host: &str, port: u16) -> Option<PeerHistory> {
+        let key = format!("{}:{}", host, port);
+        let name = self.status.read().await.get(&key)?.name.clone();
+        let samples: Vec<PollSample> = self
+            .history
+            .read()
+            .await
+            .get(&key)
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default();
+
+        let uptime_percent = if samples.is_empty() {
+            None
+        } else {
+            let ok = samples.iter().filter(|s| s.success).count();
+            Some(ok as f64 * 100.0 / samples.len() as f64)
+        };
+
+        Some(PeerHistory {
+            name,
+            host: host.to_string(),
+            port,
+            uptime_percent,
+            samples,
+        })
+    }
+
+    async fn record_poll(&self, key: &str, latency_ms: Option<u64>) {
+        let mut history = self.history.write().await;
+        let samples = history.entry(key.to_string()).or_default();
+        if samples.len() >= HISTORY_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(PollSample {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            success: latency_ms.is_some(),
+            latency_ms,
+        });
+    }
+
+    /// Identities of the configured peer whose `inboundToken` is `token`: its name, plus the
+    /// `instanceId` pinned in its entry. Both come from our config, never from what the peer
+    /// reports about itself. Empty if no configured peer has that token; peers found over
+    /// mDNS never match, since they have no config entry.
+    pub async fn identities_for_token(&self, token: Option<&str>) -> Vec<String> {
+        let token = match token {
+            Some(token) if !token.is_empty() => token,
+            _ => return Vec::new(),
+        };
+        let presents_token = |peer: &&PeerEntry| match peer.inbound_token.as_deref() {
+            Some(expected) if !expected.is_empty() => {
+                constant_time_eq(expected.as_bytes(), token.as_bytes())
+            }
+            _ => false,
+        };
+        let config = self.config.read().await;
+        let peer = match config.peers.iter().find(presents_token) {
+            Some(peer) => peer,
+            None => return Vec::new(),
+        };
+
+        let mut identities = vec![peer.name.clone()];
+        identities.extend(peer.instance_id.iter().filter(|id| !id.is_empty()).cloned());
+        identities
+    }
+
+    /// Folder ACL for a requester known by `identities` (see `identities_for_token`): the
+    /// union of `allowedFolders` across matching peer entries, or None if none of them set
+    /// one. An unidentified requester gets no folders once any peer has an `allowedFolders`,
+    /// so the ACL can't be skipped by leaving the token off.
+    pub async fn allowed_folders_for(&self, identities: &[String]) -> Option<Vec<String>> {
+        let config = self.config.read().await;
+        if identities.is_empty() {
+            return config
+                .peers
+                .iter()
+                .any(|p| p.allowed_folders.is_some())
+                .then(Vec::new);
+        }
+        let mut allowed: Option<Vec<String>> = None;
+        for peer in config.peers.iter().filter(|p| identities.contains(&p.name)) {
+            if let Some(folders) = &peer.allowed_folders {
+                allowed.get_or_insert_with(Vec::new).extend(folders.iter().cloned());
+            }
+        }
+        allowed
+    }
+
     pub async fn get_online_peers(&self) -> Vec<PeerLiveStatus> {
         self.status
             .read()
@@ -200,8 +788,9 @@ impl PeerRegistry {
             // Initial poll
             registry.poll_all_peers().await;
 
-            let mut interval =
-                tokio::time::interval(std::time::Duration::from_secs(POLL_INTERVAL_SECS));
+            let period = registry.get_self().await.poll_interval();
+            info!("Polling peers every {}s", period.as_secs());
+            let mut interval = tokio::time::interval(period);
             loop {
                 interval.tick().await;
                 registry.poll_all_peers().await;
@@ -209,10 +798,140 @@ impl PeerRegistry {
         })
     }
 
+    /// Advertise this instance over mDNS (`_vitrum._tcp`) and add instances found on the
+    /// LAN to the live status map as `discovered` peers. Failures are logged only.
+    pub fn start_discovery(
+        self: &Arc<Self>,
+        port: u16,
+        protocol: &str,
+    ) -> tokio::task::JoinHandle<()> {
+        let registry = Arc::clone(self);
+        let protocol = protocol.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = registry.run_discovery(port, &protocol).await {
+                warn!("mDNS discovery unavailable: {}", e);
+            }
+        })
+    }
+
+    async fn run_discovery(&self, port: u16, protocol: &str) -> Result<(), mdns_sd::Error> {
+        let self_info = self.get_self().await;
+        let daemon = ServiceDaemon::new()?;
+        // The daemon runs on its own thread; stop it when this task ends or is aborted
+        let _shutdown = DaemonShutdown(daemon.clone());
+
+        let host_name = format!("{}.local.", self_info.instance_id);
+        let properties = [
+            ("instanceId", self_info.instance_id.as_str()),
+            ("displayName", self_info.display_name.as_str()),
+            ("protocol", protocol),
+        ];
+        let service = ServiceInfo::new(
+            MDNS_SERVICE_TYPE,
+            &self_info.instance_id,
+            &host_name,
+            "",
+            port,
+            &properties[..],
+        )?
+        .enable_addr_auto();
+        daemon.register(service)?;
+        info!("mDNS: advertising {} on port {}", MDNS_SERVICE_TYPE, port);
+
+        // mDNS fullname -> status key, so removals can be matched up
+        let mut found: HashMap<String, String> = HashMap::new();
+        let events = daemon.browse(MDNS_SERVICE_TYPE)?;
+        while let Ok(event) = events.recv_async().await {
+            match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let instance_id = info.get_property_val_str("instanceId").unwrap_or("");
+                    if instance_id.is_empty() || instance_id == self_info.instance_id {
+                        continue;
+                    }
+                    // Prefer IPv4 for URLs; fall back to any address
+                    let host = match info
+                        .get_addresses_v4()
+                        .into_iter()
+                        .next()
+                        .map(|ip| ip.to_string())
+                        .or_else(|| info.get_addresses().iter().next().map(|ip| ip.to_string()))
+                    {
+                        Some(h) => h,
+                        None => continue,
+                    };
+                    let display_name = info
+                        .get_property_val_str("displayName")
+                        .unwrap_or(instance_id)
+                        .to_string();
+                    let key = format!("{}:{}", host, info.get_port());
+
+                    let mut status = self.status.write().await;
+                    // Configured peers keep their own entry
+                    if status.get(&key).map(|s| !s.discovered).unwrap_or(false) {
+                        continue;
+                    }
+                    if !status.contains_key(&key) {
+                        info!("mDNS: discovered {} ({})", display_name, key);
+                    }
+                    status.insert(
+                        key.clone(),
+                        PeerLiveStatus {
+                            name: display_name.clone(),
+                            host,
+                            port: info.get_port(),
+                            protocol: info
+                                .get_property_val_str("protocol")
+                                .unwrap_or("http")
+                                .to_string(),
+                            status: "online".to_string(),
+                            instance_id: Some(instance_id.to_string()),
+                            display_name: Some(display_name),
+                            shared_folders: None,
+                            shared_tags: None,
+                            document_count: None,
+                            last_seen: Some(chrono::Utc::now().to_rfc3339()),
+                            latency_ms: None,
+                            consecutive_failures: 0,
+                            next_retry: None,
+                            discovered: true,
+                        },
+                    );
+                    found.insert(info.get_fullname().to_string(), key);
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => {
+                    if let Some(key) = found.remove(&fullname) {
+                        let mut status = self.status.write().await;
+                        if status.get(&key).map(|s| s.discovered).unwrap_or(false) {
+                            status.remove(&key);
+                            info!("mDNS: {} went away", key);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     async fn poll_all_peers(&self) {
         self.check_config_reload().await;
 
-        let peers = self.config.read().await.peers.clone();
+        let mut peers = self.config.read().await.peers.clone();
+        // Peers found via mDNS are polled like configured ones (without a token)
+        peers.extend(self.status.read().await.values().filter(|s| s.discovered).map(|s| {
+            PeerEntry {
+                name: s.name.clone(),
+                host: s.host.clone(),
+                port: s.port,
+                protocol: s.protocol.clone(),
+                auth_token: None,
+                inbound_token: None,
+                instance_id: None,
+                allowed_folders: None,
+                cert_fingerprint: None,
+            }
+        }));
         let mut handles = Vec::new();
 
         for peer in peers {
@@ -222,21 +941,13 @@ impl PeerRegistry {
             let should_skip = {
                 let status = self.status.read().await;
                 if let Some(s) = status.get(&key) {
-                    if s.consecutive_failures >= FAILURE_THRESHOLD {
-                        if let Some(last) = &s.last_seen {
-                            if let Ok(last_time) = chrono::DateTime::parse_from_rfc3339(last) {
-                                let elapsed = chrono::Utc::now()
-                                    .signed_duration_since(last_time)
-                                    .num_seconds();
-                                elapsed < BACKOFF_INTERVAL_SECS as i64
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
+                    match s
+                        .next_retry
+                        .as_deref()
+                        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    {
+                        Some(next) => chrono::Utc::now() < next,
+                        None => false,
                     }
                 } else {
                     true // no status entry, skip
@@ -251,6 +962,7 @@ impl PeerRegistry {
         }
 
         futures::future::join_all(handles).await;
+        self.save_status().await;
     }
 
     async fn poll_peer(&self, peer: PeerEntry) {
@@ -262,44 +974,58 @@ impl PeerRegistry {
 
         let start = std::time::Instant::now();
 
-        let client = reqwest::Client::builder()
+        let hello = match with_peer_auth(self.client.get(&url), peer.auth_token.as_deref())
             .timeout(std::time::Duration::from_secs(HELLO_TIMEOUT_SECS))
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap_or_default();
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => resp.json::<PeerHelloResponse>().await.ok(),
+            _ => None,
+        };
+        let latency = start.elapsed().as_millis() as u64;
+        self.record_poll(&key, hello.as_ref().map(|_| latency)).await;
 
-        match client.get(&url).send().await {
-            Ok(resp) if resp.status().is_success() => {
-                if let Ok(data) = resp.json::<PeerHelloResponse>().await {
-                    let latency = start.elapsed().as_millis() as u64;
-                    let mut status = self.status.write().await;
-                    if let Some(s) = status.get_mut(&key) {
-                        let was_offline = s.status != "online";
-                        s.status = "online".to_string();
-                        s.instance_id = Some(data.instance_id);
-                        s.display_name = Some(data.display_name);
-                        s.shared_folders = Some(data.shared_folders);
-                        s.shared_tags = Some(data.shared_tags);
-                        s.document_count = Some(data.stats.document_count);
-                        s.last_seen = Some(chrono::Utc::now().to_rfc3339());
-                        s.latency_ms = Some(latency);
-                        s.consecutive_failures = 0;
-
-                        if was_offline {
-                            log_to_file(&format!("Peer {} ({}): online", peer.name, key));
-                        }
+        match hello {
+            Some(data) => {
+                let mut status = self.status.write().await;
+                if let Some(s) = status.get_mut(&key) {
+                    let was_offline = s.status != "online";
+                    s.status = "online".to_string();
+                    s.instance_id = Some(data.instance_id);
+                    s.display_name = Some(data.display_name);
+                    s.shared_folders = Some(data.shared_folders);
+                    s.shared_tags = Some(data.shared_tags);
+                    s.document_count = Some(data.stats.document_count);
+                    s.last_seen = Some(chrono::Utc::now().to_rfc3339());
+                    s.latency_ms = Some(latency);
+                    s.consecutive_failures = 0;
+                    s.next_retry = None;
+
+                    if was_offline {
+                        info!("Peer {} ({}): online", peer.name, key);
                     }
                 }
             }
-            _ => {
+            None => {
                 let mut status = self.status.write().await;
                 if let Some(s) = status.get_mut(&key) {
                     let was_online = s.status == "online";
                     s.consecutive_failures += 1;
                     s.status = "offline".to_string();
 
+                    let wait = backoff_secs(s.consecutive_failures);
+                    s.next_retry = if wait > 0 {
+                        // Up to 10% jitter so peers that went down together don't retry in step
+                        let jitter = (Uuid::new_v4().as_u128() % (wait as u128 / 10 + 1)) as u64;
+                        let next = chrono::Utc::now()
+                            + chrono::Duration::seconds((wait + jitter) as i64);
+                        Some(next.to_rfc3339())
+                    } else {
+                        None
+                    };
+
                     if was_online {
-                        log_to_file(&format!("Peer {} ({}): offline", peer.name, key));
+                        warn!("Peer {} ({}): offline", peer.name, key);
                     }
                 }
             }
@@ -318,7 +1044,8 @@ impl PeerRegistry {
         if mtime > *last {
             if *last > 0 {
                 // Config changed — reload
-                let new_config = Self::load_or_create(&self.config_path);
+                let mut new_config = Self::load_or_create(&self.config_path);
+                let invalid_peers = Self::split_invalid_peers(&mut new_config);
                 let old_count = self.config.read().await.peers.len();
                 let new_count = new_config.peers.len();
 
@@ -334,41 +1061,269 @@ impl PeerRegistry {
                     // Add new peers
                     for peer in &new_config.peers {
                         let key = format!("{}:{}", peer.host, peer.port);
-                        if !status.contains_key(&key) {
-                            status.insert(
-                                key,
-                                PeerLiveStatus {
-                                    name: peer.name.clone(),
-                                    host: peer.host.clone(),
-                                    port: peer.port,
-                                    protocol: peer.protocol.clone(),
-                                    status: "unknown".to_string(),
-                                    instance_id: None,
-                                    display_name: None,
-                                    shared_folders: None,
-                                    shared_tags: None,
-                                    document_count: None,
-                                    last_seen: None,
-                                    latency_ms: None,
-                                    consecutive_failures: 0,
-                                },
-                            );
+                        // A configured entry takes over a peer previously found via mDNS
+                        if status.get(&key).map(|s| s.discovered).unwrap_or(true) {
+                            status.insert(key, Self::unknown_status(peer));
                         }
                     }
-                    // Remove peers no longer in config
-                    status.retain(|k, _| new_keys.contains(k));
+                    // Remove peers no longer in config (discovered peers are managed by mDNS)
+                    status.retain(|k, s| s.discovered || new_keys.contains(k));
                 }
 
+                self.verifier.set_pins(&new_config.peers);
                 *self.config.write().await = new_config;
+                *self.invalid_peers.write().await = invalid_peers;
 
                 if old_count != new_count {
-                    log_to_file(&format!(
-                        "Peer config hot-reloaded: {} → {} peers",
-                        old_count, new_count
-                    ));
+                    info!("Peer config hot-reloaded: {} → {} peers", old_count, new_count);
                 }
             }
             *last = mtime;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(name: &str, host: &str, port: u16, protocol: &str) -> PeerEntry {
+        PeerEntry {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+            protocol: protocol.to_string(),
+            auth_token: None,
+            inbound_token: None,
+            instance_id: None,
+            allowed_folders: None,
+            cert_fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn normalize_trims_and_lowercases() {
+        let mut entry = peer(" Laptop ", " Host.Local ", 3847, "HTTPS ");
+        entry.normalize();
+        assert_eq!(entry.name, "Laptop");
+        assert_eq!(entry.host, "host.local");
+        assert_eq!(entry.protocol, "https");
+        assert!(entry.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_unusable_entries() {
+        assert!(peer("", "host", 3847, "http").validate().is_err());
+        assert!(peer("a", "", 3847, "http").validate().is_err());
+        assert!(peer("a", "host/path", 3847, "http").validate().is_err());
+        assert!(peer("a", "user@host", 3847, "http").validate().is_err());
+        assert!(peer("a", "host", 0, "http").validate().is_err());
+        assert!(peer("a", "host", 3847, "htttp").validate().is_err());
+
+        let mut pinned = peer("a", "host", 3847, "http");
+        pinned.cert_fingerprint = Some("ab".repeat(32));
+        assert!(pinned.validate().is_err());
+        pinned.protocol = "https".to_string();
+        assert!(pinned.validate().is_ok());
+        pinned.cert_fingerprint = Some("not hex".to_string());
+        assert!(pinned.validate().is_err());
+    }
+
+    /// Registry over a fresh `.vitrum-peers.json` with the given `peers` array
+    fn registry_with(peers: serde_json::Value) -> (tempfile::TempDir, PeerRegistry) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = serde_json::json!({
+            "self": {
+                "instanceId": "me",
+                "displayName": "Me",
+                "sharedFolders": [],
+                "sharedTags": [],
+            },
+            "peers": peers,
+        });
+        std::fs::write(dir.path().join(PEER_CONFIG_FILE), config.to_string()).unwrap();
+        let registry = PeerRegistry::new(dir.path(), dir.path());
+        (dir, registry)
+    }
+
+    #[tokio::test]
+    async fn requester_is_identified_by_inbound_token_only() {
+        let (_dir, registry) = registry_with(serde_json::json!([
+            { "name": "laptop", "host": "laptop.local", "port": 3847, "protocol": "http",
+              "inboundToken": "laptop-secret", "authToken": "my-secret",
+              "instanceId": "laptop-id" },
+            { "name": "desktop", "host": "desktop.local", "port": 3847, "protocol": "http" },
+        ]));
+
+        assert_eq!(
+            registry.identities_for_token(Some("laptop-secret")).await,
+            vec!["laptop", "laptop-id"]
+        );
+        assert!(registry
+            .identities_for_token(Some("my-secret"))
+            .await
+            .is_empty());
+        assert!(registry
+            .identities_for_token(Some("laptop-secre"))
+            .await
+            .is_empty());
+        assert!(registry.identities_for_token(Some("")).await.is_empty());
+        assert!(registry.identities_for_token(None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reported_instance_id_grants_nothing() {
+        let (_dir, registry) = registry_with(serde_json::json!([
+            { "name": "laptop", "host": "laptop.local", "port": 3847, "protocol": "http",
+              "inboundToken": "laptop-secret" },
+            { "name": "desktop", "host": "desktop.local", "port": 3847, "protocol": "http",
+              "instanceId": "desktop-id", "allowedFolders": ["desktop/"] },
+        ]));
+        // The laptop claims the desktop's instanceId in its /hello
+        registry
+            .status
+            .write()
+            .await
+            .get_mut("laptop.local:3847")
+            .unwrap()
+            .instance_id = Some("desktop-id".to_string());
+
+        let identities = registry.identities_for_token(Some("laptop-secret")).await;
+        assert_eq!(identities, vec!["laptop"]);
+        assert_eq!(registry.allowed_folders_for(&identities).await, None);
+
+        let self_info = PeerSelf {
+            instance_id: "me".to_string(),
+            display_name: "Me".to_string(),
+            shared_folders: vec![SharedFolder {
+                path: "desktop".to_string(),
+                permission: SharedPermission::Write,
+                allowed_peers: vec!["desktop-id".to_string()],
+            }],
+            shared_tags: Vec::new(),
+            auth_token: None,
+            poll_interval_secs: None,
+            sync_interval_secs: None,
+            rate_limit_per_minute: None,
+        };
+        assert!(!self_info.can_read("desktop/a.md", &identities));
+        assert!(!self_info.can_write("desktop/a.md", &identities));
+    }
+
+    #[test]
+    fn split_host_port_needs_an_explicit_port() {
+        assert_eq!(
+            split_host_port("laptop.local:3900"),
+            Some(("laptop.local", 3900))
+        );
+        assert_eq!(split_host_port("laptop.local"), None);
+        assert_eq!(split_host_port("laptop.local:"), None);
+        assert_eq!(split_host_port("laptop.local:http"), None);
+    }
+
+    #[test]
+    fn in_folder_matches_whole_segments() {
+        assert!(in_folder("notes/a.md", "notes"));
+        assert!(in_folder("notes/a.md", "notes/"));
+        assert!(in_folder("notes/deep/a.md", "notes"));
+        assert!(in_folder("notes", "notes/"));
+        assert!(in_folder("anything.md", ""));
+        assert!(!in_folder("notes-private/a.md", "notes"));
+        assert!(!in_folder("notesa.md", "notes"));
+        assert!(!in_folder("other/notes/a.md", "notes"));
+    }
+
+    #[test]
+    fn shared_folder_does_not_cover_sibling_prefix() {
+        let self_info = PeerSelf {
+            instance_id: "me".to_string(),
+            display_name: "Me".to_string(),
+            shared_folders: vec![SharedFolder {
+                path: "notes".to_string(),
+                permission: SharedPermission::Write,
+                allowed_peers: Vec::new(),
+            }],
+            shared_tags: Vec::new(),
+            auth_token: None,
+            poll_interval_secs: None,
+            sync_interval_secs: None,
+            rate_limit_per_minute: None,
+        };
+        let requester = vec!["laptop".to_string()];
+        assert!(self_info.can_read("notes/a.md", &requester));
+        assert!(self_info.can_write("notes/a.md", &requester));
+        assert!(!self_info.can_read("notes-private/a.md", &requester));
+        assert!(!self_info.can_write("notes-private/a.md", &requester));
+    }
+
+    #[tokio::test]
+    async fn folder_acl_fails_closed_for_unidentified_requesters() {
+        let (_dir, registry) = registry_with(serde_json::json!([
+            { "name": "laptop", "host": "laptop.local", "port": 3847, "protocol": "http",
+              "allowedFolders": ["work/"] },
+            { "name": "desktop", "host": "desktop.local", "port": 3847, "protocol": "http" },
+        ]));
+        assert_eq!(registry.allowed_folders_for(&[]).await, Some(Vec::new()));
+        assert_eq!(
+            registry.allowed_folders_for(&["laptop".to_string()]).await,
+            Some(vec!["work/".to_string()])
+        );
+        assert_eq!(
+            registry.allowed_folders_for(&["desktop".to_string()]).await,
+            None
+        );
+
+        let (_dir, open) = registry_with(serde_json::json!([
+            { "name": "desktop", "host": "desktop.local", "port": 3847, "protocol": "http" },
+        ]));
+        assert_eq!(open.allowed_folders_for(&[]).await, None);
+    }
+
+    #[tokio::test]
+    async fn invalid_peers_are_inactive_but_kept_on_save() {
+        let (dir, registry) = registry_with(serde_json::json!([
+            { "name": "good", "host": "Good.Local", "port": 3847, "protocol": "http" },
+            { "name": "other", "host": "other.local", "port": 3847, "protocol": "http" },
+            { "name": "typo", "host": "typo.local", "port": 3847, "protocol": "htttp" },
+        ]));
+        let path = dir.path().join(PEER_CONFIG_FILE);
+        let active: Vec<String> = registry
+            .get_peers()
+            .await
+            .into_iter()
+            .map(|p| p.host)
+            .collect();
+        assert_eq!(active, vec!["good.local", "other.local"]);
+
+        assert!(registry.remove_peer("other.local", 3847).await.unwrap());
+        let saved: PeerConfig =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let names: Vec<&str> = saved.peers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["good", "typo"]);
+        assert_eq!(saved.peers[1].protocol, "htttp");
+    }
+
+    #[test]
+    fn backoff_starts_at_the_failure_threshold() {
+        for failures in 0..FAILURE_THRESHOLD {
+            assert_eq!(backoff_secs(failures), 0);
+        }
+        assert_eq!(backoff_secs(FAILURE_THRESHOLD), POLL_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn backoff_doubles_per_failure() {
+        assert_eq!(backoff_secs(FAILURE_THRESHOLD + 1), POLL_INTERVAL_SECS * 2);
+        assert_eq!(backoff_secs(FAILURE_THRESHOLD + 2), POLL_INTERVAL_SECS * 4);
+        assert_eq!(backoff_secs(FAILURE_THRESHOLD + 3), POLL_INTERVAL_SECS * 8);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff_secs(FAILURE_THRESHOLD + 20), BACKOFF_MAX_SECS);
+        assert_eq!(backoff_secs(u32::MAX), BACKOFF_MAX_SECS);
+        for failures in 0..100 {
+            assert!(backoff_secs(failures) <= BACKOFF_MAX_SECS);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::{error, warn};
+
+use crate::server::peers::PeerEntry;
+
+/// Parse a SHA-256 certificate fingerprint: 64 hex digits, optionally colon-separated
+/// (`AB:CD:...` as printed by `openssl x509 -fingerprint -sha256`).
+pub fn parse_fingerprint(raw: &str) -> Option<[u8; 32]> {
+    let hex: String = raw.chars().filter(|c| *c != ':' && !c.is_whitespace()).collect();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+/// Server certificate check for outbound federation requests. Hosts with a pinned
+/// fingerprint must present exactly that certificate; other hosts are accepted as before
+/// (self-signed peer certs are the norm). Handshake signatures are always verified.
+#[derive(Debug)]
+pub struct PinnedCertVerifier {
+    /// SHA-256 of the expected end-entity certificate (DER), by host. TLS only sees the
+    /// host, so peers sharing a host on different ports share their pins.
+    pins: RwLock<HashMap<String, Vec<[u8; 32]>>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl PinnedCertVerifier {
+    pub fn new(provider: Arc<CryptoProvider>) -> Self {
+        PinnedCertVerifier {
+            pins: RwLock::new(HashMap::new()),
+            provider,
+        }
+    }
+
+    /// Replace the pins with the fingerprints configured on `peers`
+    pub fn set_pins(&self, peers: &[PeerEntry]) {
+        let mut pins: HashMap<String, Vec<[u8; 32]>> = HashMap::new();
+        for peer in peers {
+            if let Some(fp) = peer.cert_fingerprint.as_deref().and_then(parse_fingerprint) {
+                pins.entry(peer.host.to_lowercase()).or_default().push(fp);
+            }
+        }
+        *self.pins.write().unwrap_or_else(|e| e.into_inner()) = pins;
+    }
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let host = server_name.to_str().to_lowercase();
+        let pins = self.pins.read().unwrap_or_else(|e| e.into_inner());
+        let expected = match pins.get(&host) {
+            Some(expected) => expected,
+            None => return Ok(ServerCertVerified::assertion()),
+        };
+
+        let actual: [u8; 32] = Sha256::digest(end_entity.as_ref()).into();
+        if expected.contains(&actual) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            warn!("Rejected certificate from {}: fingerprint does not match certFingerprint", host);
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// HTTP client for talking to peers, checking server certificates with `verifier`
+pub fn federation_client(
+    verifier: Arc<PinnedCertVerifier>,
+    provider: Arc<CryptoProvider>,
+    timeout: std::time::Duration,
+) -> reqwest::Client {
+    let tls = match rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+    {
+        Ok(builder) => builder
+            .dangerous()
+            .with_custom_certificate_verifier(verifier)
+            .with_no_client_auth(),
+        Err(e) => {
+            error!("Failed to build federation TLS config: {}", e);
+            return reqwest::Client::default();
+        }
+    };
+
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .use_preconfigured_tls(tls)
+        .build()
+        .unwrap_or_default()
+}
@@ -6,8 +6,10 @@ use axum::{
 use serde::Serialize;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tracing::{error, info, warn};
 
-use crate::server::{log_to_file, AppState};
+use crate::server::error::ApiError;
+use crate::server::AppState;
 
 // --- Types ---
 
@@ -230,15 +232,15 @@ pub async fn list_projects(
 pub async fn get_tree(
     State(state): State<Arc<AppState>>,
     Path(name): Path<String>,
-) -> Result<Json<Vec<TreeEntry>>, StatusCode> {
+) -> Result<Json<Vec<TreeEntry>>, ApiError> {
     let project_dir = match resolve_project_dir(&state, &name) {
         Some(dir) => dir,
-        None => return Err(StatusCode::NOT_FOUND),
+        None => return Err(StatusCode::NOT_FOUND.into()),
     };
 
     // Validate project exists
     if !project_dir.is_dir() {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(StatusCode::NOT_FOUND.into());
     }
 
     // Validate no path traversal
@@ -249,7 +251,7 @@ pub async fn get_tree(
         .canonicalize()
         .map_err(|_| StatusCode::NOT_FOUND)?;
     if !canonical_project.starts_with(&canonical_org) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(StatusCode::FORBIDDEN.into());
     }
 
     let is_org = is_org_root_project(&state, &name);
@@ -343,10 +345,10 @@ fn build_tree(dir: &PathBuf, project_root: &PathBuf, is_org_root: bool) -> Vec<T
 pub async fn get_file(
     State(state): State<Arc<AppState>>,
     Path((name, file_path)): Path<(String, String)>,
-) -> Result<Json<ProjectFile>, StatusCode> {
+) -> Result<Json<ProjectFile>, ApiError> {
     let project_dir = match resolve_project_dir(&state, &name) {
         Some(dir) => dir,
-        None => return Err(StatusCode::NOT_FOUND),
+        None => return Err(StatusCode::NOT_FOUND.into()),
     };
 
     let full_path = project_dir.join(&file_path);
@@ -359,19 +361,19 @@ pub async fn get_file(
         .canonicalize()
         .map_err(|_| StatusCode::NOT_FOUND)?;
     if !canonical_path.starts_with(&canonical_org) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(StatusCode::FORBIDDEN.into());
     }
 
     // Check it's a file
     if !canonical_path.is_file() {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(StatusCode::NOT_FOUND.into());
     }
 
     // Read content
     let content = tokio::fs::read_to_string(&canonical_path)
         .await
         .map_err(|e| {
-            log_to_file(&format!("[projects] Failed to read file: {}", e));
+            error!("[projects] Failed to read file: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
@@ -405,12 +407,12 @@ pub async fn put_file(
     State(state): State<Arc<AppState>>,
     Path((name, file_path)): Path<(String, String)>,
     Json(payload): Json<PutProjectFileRequest>,
-) -> Result<StatusCode, StatusCode> {
-    log_to_file(&format!("[projects] PUT /api/projects/{}/file/{}", name, file_path));
+) -> Result<StatusCode, ApiError> {
+    info!("[projects] PUT /api/projects/{}/file/{}", name, file_path);
 
     let project_dir = match resolve_project_dir(&state, &name) {
         Some(dir) => dir,
-        None => return Err(StatusCode::NOT_FOUND),
+        None => return Err(StatusCode::NOT_FOUND.into()),
     };
 
     let full_path = project_dir.join(&file_path);
@@ -427,16 +429,16 @@ pub async fn put_file(
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
     if !canonical_path.starts_with(&canonical_org) {
-        log_to_file(&format!("[projects] PUT rejected - path traversal: {}", file_path));
-        return Err(StatusCode::FORBIDDEN);
+        warn!("[projects] PUT rejected - path traversal: {}", file_path);
+        return Err(StatusCode::FORBIDDEN.into());
     }
 
     // Write content
     if let Err(e) = tokio::fs::write(&canonical_path, &payload.content).await {
-        log_to_file(&format!("[projects] PUT failed to write: {}", e));
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("[projects] PUT failed to write: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
     }
 
-    log_to_file(&format!("[projects] PUT success: {}/{}", name, file_path));
+    info!("[projects] PUT success: {}/{}", name, file_path);
     Ok(StatusCode::OK)
 }
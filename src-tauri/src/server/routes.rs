@@ -1,26 +1,64 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
+use regex::Regex;
+use tower_http::services::ServeFile;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use tracing::{debug, error, info, warn};
 
-use crate::server::{log_to_file, AppState};
-use crate::server::document::serialize_document;
+use crate::server::activity::ActivityEvent;
+use crate::server::config::ServerConfig;
+use crate::server::error::ApiError;
+use crate::server::{resolve_safe_path, AppState};
+use crate::server::document::{
+    self, extract_checkboxes, rewrite_markdown_links, rewrite_wikilinks, serialize_document,
+    OrgDocument,
+};
+use crate::server::index::{
+    normalize_link_name, AmbiguousLink, DiagnosticKind, IndexDiagnostic, SearchMode,
+};
+use crate::server::peers::in_folder;
+use crate::server::sync::{
+    compute_checksum, compute_checksum_like, extract_body, strip_federation_block,
+};
+use crate::server::watcher::FileWatcher;
 
 #[derive(Serialize)]
 pub struct HealthResponse {
     status: String,
     timestamp: String,
+    /// False while the initial index build is still finishing in the background
+    #[serde(rename = "indexReady")]
+    index_ready: bool,
 }
 
-pub async fn health() -> Json<HealthResponse> {
-    log_to_file("[server] /api/health endpoint hit");
+pub async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    debug!("/api/health endpoint hit");
     Json(HealthResponse {
         status: "ok".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
+        index_ready: state.index_ready.load(std::sync::atomic::Ordering::SeqCst),
+    })
+}
+
+#[derive(Serialize)]
+pub struct PingResponse {
+    pong: bool,
+    #[serde(rename = "serverTime")]
+    server_time: String,
+}
+
+/// Liveness probe for latency measurement: no state, no locks, no logging
+pub async fn ping() -> Json<PingResponse> {
+    Json(PingResponse {
+        pong: true,
+        server_time: chrono::Utc::now().to_rfc3339(),
     })
 }
 
@@ -31,12 +69,14 @@ pub struct DebugLogRequest {
 }
 
 pub async fn debug_log(Json(payload): Json<DebugLogRequest>) -> StatusCode {
-    log_to_file(&payload.msg);
+    info!(target: "vitrum::frontend", "{}", payload.msg);
     StatusCode::OK
 }
 
 #[derive(Serialize)]
 pub struct StatusResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
     server: ServerStats,
     documents: DocumentStats,
     tags: TagStats,
@@ -47,7 +87,8 @@ pub struct StatusResponse {
 pub struct ServerStats {
     uptime: u64,
     #[serde(rename = "connectedClients")]
-    connected_clients: u32,
+    /// Open WebSocket connections (one per open viewer tab or device)
+    connected_clients: usize,
     #[serde(rename = "lastIndexed")]
     last_indexed: String,
 }
@@ -55,6 +96,8 @@ pub struct ServerStats {
 #[derive(Serialize)]
 pub struct DocumentStats {
     total: usize,
+    #[serde(rename = "totalWords")]
+    total_words: usize,
     #[serde(rename = "byType")]
     by_type: HashMap<String, usize>,
     #[serde(rename = "byStatus")]
@@ -80,10 +123,24 @@ pub struct RecentDoc {
     #[serde(rename = "type")]
     doc_type: String,
     updated: String,
+    #[serde(rename = "wordCount")]
+    word_count: usize,
+    #[serde(rename = "readingMinutes")]
+    reading_minutes: usize,
+}
+
+#[derive(Deserialize)]
+pub struct StatusQuery {
+    /// Also count each document under every ancestor of its tags (`project` for
+    /// `project/alpha`)
+    prefix: Option<bool>,
 }
 
-pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse> {
-    log_to_file("[server] /api/status endpoint hit");
+pub async fn status(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatusQuery>,
+) -> Json<StatusResponse> {
+    debug!("/api/status endpoint hit");
     let index = state.index.read().await;
     let stats = index.get_stats();
     let docs = index.get_documents();
@@ -91,8 +148,17 @@ pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse>
     // Get tag counts
     let mut tag_counts: HashMap<String, usize> = HashMap::new();
     for doc in &docs {
-        for tag in &doc.tags {
-            *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+        if query.prefix.unwrap_or(false) {
+            // A document tagged `a/b` and `a/c` counts once towards `a`
+            let expanded: HashSet<&str> =
+                doc.tags.iter().flat_map(|t| tag_with_ancestors(t)).collect();
+            for tag in expanded {
+                *tag_counts.entry(tag.to_string()).or_insert(0) += 1;
+            }
+        } else {
+            for tag in &doc.tags {
+                *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+            }
         }
     }
     let mut top_tags: Vec<TagCount> = tag_counts
@@ -111,19 +177,23 @@ pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse>
             title: d.title.clone(),
             doc_type: d.doc_type.clone(),
             updated: d.updated.clone().unwrap_or_default(),
+            word_count: d.word_count,
+            reading_minutes: d.reading_minutes,
         })
         .collect();
     recent.sort_by(|a, b| b.updated.cmp(&a.updated));
     recent.truncate(5);
 
     Json(StatusResponse {
+        index_generation: index.generation(),
         server: ServerStats {
             uptime: state.start_time.elapsed().as_secs(),
-            connected_clients: 1,
+            connected_clients: state.ws_clients.load(std::sync::atomic::Ordering::Relaxed),
             last_indexed: chrono::Utc::now().to_rfc3339(),
         },
         documents: DocumentStats {
             total: stats.total,
+            total_words: docs.iter().map(|d| d.word_count).sum(),
             by_type: stats.by_type,
             by_status: stats.by_status,
         },
@@ -139,20 +209,103 @@ pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse>
 pub struct ListFilesQuery {
     #[serde(rename = "type")]
     doc_type: Option<String>,
+    status: Option<String>,
+    tag: Option<String>,
+    /// Let `tag` match descendant tags too (`project` matches `project/alpha`)
+    prefix: Option<bool>,
+    /// RFC 3339 or `YYYY-MM-DD`; documents dated at or after it (by `updated`, else `created`)
+    #[serde(rename = "updatedAfter")]
+    updated_after: Option<String>,
+    /// RFC 3339 or `YYYY-MM-DD`; documents dated before it
+    #[serde(rename = "updatedBefore")]
+    updated_before: Option<String>,
+}
+
+/// Separator for hierarchical tags (`project/alpha/frontend`). Tags are stored verbatim;
+/// only the tag tree, prefix filters and prefix counts split on it. `/` rather than `.` or
+/// `:` because it reads like a folder path and is what Obsidian uses for nested tags.
+const TAG_SEPARATOR: char = '/';
+
+/// Whether `tag` satisfies a `wanted` tag filter: exactly, or with `prefix` also when
+/// `wanted` is one of its ancestors (`project` or `project/` matches `project/alpha`)
+fn tag_matches(tag: &str, wanted: &str, prefix: bool) -> bool {
+    if tag == wanted {
+        return true;
+    }
+    if !prefix {
+        return false;
+    }
+    let wanted = wanted.trim_end_matches(TAG_SEPARATOR);
+    !wanted.is_empty()
+        && tag
+            .strip_prefix(wanted)
+            .map(|rest| rest.starts_with(TAG_SEPARATOR))
+            .unwrap_or(false)
+}
+
+/// `tag` and each of its ancestors: `a/b/c` gives `a`, `a/b` and `a/b/c`
+fn tag_with_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(move |(i, _)| &tag[..i])
+        .filter(|t| !t.is_empty())
+        .chain(std::iter::once(tag))
+}
+
+/// Parse an RFC 3339 timestamp or a `YYYY-MM-DD` date (taken as midnight UTC)
+fn parse_date(raw: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let raw = raw.trim();
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(t.with_timezone(&chrono::Utc));
+    }
+    // Frontmatter dates often carry a time after the day; the day is enough here
+    let day = raw.get(..10).unwrap_or(raw);
+    chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc())
 }
 
 #[derive(Serialize)]
 pub struct ListFilesResponse {
+    /// Index generation this response was built from (see GET /api/index/generation)
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
     count: usize,
     items: Vec<serde_json::Value>,
 }
 
+/// Serialize a document for a response; a failure is logged and the document skipped
+/// rather than failing the whole request.
+fn document_value(doc: &OrgDocument) -> Option<serde_json::Value> {
+    match serde_json::to_value(doc) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            error!("Failed to serialize {}: {}", doc.path, e);
+            None
+        }
+    }
+}
+
 pub async fn list_files(
     State(state): State<Arc<AppState>>,
     Query(query): Query<ListFilesQuery>,
-) -> Json<ListFilesResponse> {
+) -> Result<Json<ListFilesResponse>, ApiError> {
+    let bound = |raw: &Option<String>| match raw.as_deref() {
+        Some(raw) => parse_date(raw).map(Some).ok_or_else(|| {
+            ApiError::bad_request(
+                "invalid_date",
+                format!("`{}` is not an RFC 3339 timestamp or YYYY-MM-DD date", raw),
+            )
+        }),
+        None => Ok(None),
+    };
+    let after = bound(&query.updated_after)?;
+    let before = bound(&query.updated_before)?;
+
     let index = state.index.read().await;
-    let docs = index.get_documents();
+    let mut docs = index.get_documents();
+    // Stable order across refreshes (the index is a HashMap)
+    docs.sort_by(|a, b| a.path.cmp(&b.path));
 
     let items: Vec<serde_json::Value> = docs
         .into_iter()
@@ -163,25 +316,242 @@ pub async fn list_files(
                 .map(|t| &d.doc_type == t)
                 .unwrap_or(true)
         })
-        .map(|d| serde_json::to_value(d).unwrap())
+        .filter(|d| {
+            query
+                .status
+                .as_ref()
+                .map(|s| d.status.as_ref() == Some(s))
+                .unwrap_or(true)
+        })
+        .filter(|d| {
+            let prefix = query.prefix.unwrap_or(false);
+            query
+                .tag
+                .as_ref()
+                .map(|t| d.tags.iter().any(|tag| tag_matches(tag, t, prefix)))
+                .unwrap_or(true)
+        })
+        .filter(|d| {
+            if after.is_none() && before.is_none() {
+                return true;
+            }
+            // Undated documents never match a date filter
+            let date = match d.updated.as_deref().or(d.created.as_deref()).and_then(parse_date) {
+                Some(date) => date,
+                None => return false,
+            };
+            after.map(|a| date >= a).unwrap_or(true) && before.map(|b| date < b).unwrap_or(true)
+        })
+        .filter_map(document_value)
+        .collect();
+
+    Ok(Json(ListFilesResponse {
+        index_generation: index.generation(),
+        count: items.len(),
+        items,
+    }))
+}
+
+/// Default and maximum `limit` for GET /api/recent
+const DEFAULT_RECENT_LIMIT: usize = 20;
+const MAX_RECENT_LIMIT: usize = 500;
+
+#[derive(Deserialize)]
+pub struct RecentQuery {
+    limit: Option<usize>,
+    #[serde(rename = "type")]
+    doc_type: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RecentItem {
+    path: String,
+    title: String,
+    #[serde(rename = "type")]
+    doc_type: String,
+    status: Option<String>,
+    updated: Option<String>,
+    created: Option<String>,
+    /// When the file last changed on disk (RFC 3339), as of the last index refresh
+    mtime: Option<String>,
+    /// What the feed is ordered by (RFC 3339): `updated`, else `created`, else `mtime`
+    touched: String,
+    /// Which of those `touched` came from
+    #[serde(rename = "touchedFrom")]
+    touched_from: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct RecentResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<RecentItem>,
+}
+
+/// GET /api/recent - Most recently touched documents first. Notes without a frontmatter
+/// date fall back to their file mtime, so they still show up.
+pub async fn recent(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RecentQuery>,
+) -> Json<RecentResponse> {
+    let limit = query.limit.unwrap_or(DEFAULT_RECENT_LIMIT).clamp(1, MAX_RECENT_LIMIT);
+    let index = state.index.read().await;
+
+    let mut dated: Vec<(chrono::DateTime<chrono::Utc>, RecentItem)> = index
+        .get_documents()
+        .into_iter()
+        .filter(|d| query.doc_type.as_ref().map(|t| &d.doc_type == t).unwrap_or(true))
+        .filter_map(|d| {
+            let mtime = index
+                .get_document_mtime(&d.path)
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0));
+            let (touched, touched_from) = match (
+                d.updated.as_deref().and_then(parse_date),
+                d.created.as_deref().and_then(parse_date),
+                mtime,
+            ) {
+                (Some(t), _, _) => (t, "updated"),
+                (None, Some(t), _) => (t, "created"),
+                (None, None, Some(t)) => (t, "mtime"),
+                (None, None, None) => return None,
+            };
+            let item = RecentItem {
+                path: d.path.clone(),
+                title: d.title.clone(),
+                doc_type: d.doc_type.clone(),
+                status: d.status.clone(),
+                updated: d.updated.clone(),
+                created: d.created.clone(),
+                mtime: mtime.map(|t| t.to_rfc3339()),
+                touched: touched.to_rfc3339(),
+                touched_from,
+            };
+            Some((touched, item))
+        })
         .collect();
+    dated.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+    dated.truncate(limit);
 
-    Json(ListFilesResponse {
+    let items: Vec<RecentItem> = dated.into_iter().map(|(_, item)| item).collect();
+    Json(RecentResponse {
+        index_generation: index.generation(),
         count: items.len(),
         items,
     })
 }
 
+/// Days covered by GET /api/calendar when `from` is omitted
+const DEFAULT_CALENDAR_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+pub struct CalendarQuery {
+    /// First day (`YYYY-MM-DD`, inclusive); a year before `to` if omitted
+    from: Option<String>,
+    /// Last day (`YYYY-MM-DD`, inclusive); today if omitted
+    to: Option<String>,
+}
+
+/// The `YYYY-MM-DD` a date string starts with, as written (no timezone conversion, so a
+/// late-evening `updated` stays on the day the user saw)
+fn leading_day(raw: &str) -> Option<chrono::NaiveDate> {
+    let raw = raw.trim();
+    chrono::NaiveDate::parse_from_str(raw.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// GET /api/calendar - Per-day document counts for a heatmap: each document counts once
+/// on its `created` day and once on its `updated` day (once if they match), or on the
+/// date its filename starts with (daily notes like `2024-06-01.md`) when it has neither.
+/// Days with nothing are left out.
+pub async fn calendar(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Json<BTreeMap<String, usize>>, ApiError> {
+    let day = |raw: &Option<String>| match raw.as_deref() {
+        Some(raw) => leading_day(raw).map(Some).ok_or_else(|| {
+            ApiError::bad_request("invalid_date", format!("`{}` is not a YYYY-MM-DD date", raw))
+        }),
+        None => Ok(None),
+    };
+    let to = day(&query.to)?.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let from = day(&query.from)?
+        .unwrap_or_else(|| to - chrono::Duration::days(DEFAULT_CALENDAR_DAYS - 1));
+    if from > to {
+        return Err(ApiError::bad_request("invalid_range", "`from` is after `to`"));
+    }
+
+    let index = state.index.read().await;
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for doc in index.get_documents() {
+        let mut days: Vec<chrono::NaiveDate> = [doc.created.as_deref(), doc.updated.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter_map(leading_day)
+            .collect();
+        if days.is_empty() {
+            let stem = std::path::Path::new(&doc.path).file_stem().map(|s| s.to_string_lossy());
+            days.extend(stem.as_deref().and_then(leading_day));
+        }
+        days.dedup();
+        for d in days.into_iter().filter(|d| (from..=to).contains(d)) {
+            *counts.entry(d.format("%Y-%m-%d").to_string()).or_default() += 1;
+        }
+    }
+
+    Ok(Json(counts))
+}
+
 pub async fn get_file(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
+    if resolve_safe_path(&state.org_root, &path).is_none() {
+        warn!("GET rejected - path traversal attempt: {}", path);
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
     let index = state.index.read().await;
 
     if let Some(doc) = index.get_document_with_content(&path).await {
-        Ok(Json(serde_json::to_value(doc).unwrap()))
+        drop(index);
+        state.activity.record(&path, "view").await;
+
+        // Checksum of the file as read; send it back in If-Match to detect concurrent edits
+        let checksum = compute_checksum(doc.content.as_deref().unwrap_or(""));
+        let mut value = document_value(&doc).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        value["checksum"] = serde_json::Value::String(checksum.clone());
+
+        Ok(([(header::ETAG, format!("\"{}\"", checksum))], Json(value)))
     } else {
-        Err(StatusCode::NOT_FOUND)
+        Err(StatusCode::NOT_FOUND.into())
+    }
+}
+
+/// GET /api/raw/{*path} - Any file under the org root as stored (images and other
+/// attachments), streamed with its MIME type and Range support. Dotfiles and dot-directories
+/// (peer config, `.vitrum/`) are not served.
+pub async fn raw_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    request: Request,
+) -> Result<Response, ApiError> {
+    let resolved = match resolve_safe_path(&state.org_root, &path) {
+        Some(resolved) => resolved,
+        None => {
+            warn!("GET raw rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+    if path.split(['/', '\\']).any(|part| part.starts_with('.')) || !resolved.is_file() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    match ServeFile::new(&resolved).try_call(request).await {
+        Ok(response) => Ok(response.map(Body::new)),
+        Err(e) => {
+            error!("GET raw failed for {}: {}", path, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
     }
 }
 
@@ -191,23 +561,56 @@ pub struct UpdateFileRequest {
     content: String,
 }
 
+/// PUT /api/files/{*path} - Replace a document. With `If-Match: <checksum>` (as returned by
+/// GET), the write only happens if the file still has that checksum; otherwise 409 with
+/// `{error, checksum}` carrying the current one. The new checksum comes back as the ETag.
 pub async fn put_file(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateFileRequest>,
-) -> Result<StatusCode, StatusCode> {
-    log_to_file(&format!("[server] PUT /api/files/{}", path));
+) -> Result<Response, ApiError> {
+    info!("PUT /api/files/{}", path);
 
     // Validate path - prevent directory traversal
-    let full_path = state.org_root.join(&path);
+    let full_path = match resolve_safe_path(&state.org_root, &path) {
+        Some(p) => p,
+        None => {
+            warn!("PUT rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
     let canonical_root = state.org_root.canonicalize()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let canonical_path = full_path.canonicalize()
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
     if !canonical_path.starts_with(&canonical_root) {
-        log_to_file(&format!("[server] PUT rejected - path traversal attempt: {}", path));
-        return Err(StatusCode::FORBIDDEN);
+        warn!("PUT rejected - path traversal attempt: {}", path);
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    // Serialize writes to this document
+    let _write_guard = state.write_locks.lock(&path).await;
+
+    // Optimistic concurrency: reject if the file changed since the client read it
+    if let Some(expected) = headers.get(header::IF_MATCH).and_then(|v| v.to_str().ok()) {
+        let expected = expected.trim().trim_start_matches("W/").trim_matches('"');
+        if expected != "*" {
+            let current = tokio::fs::read_to_string(&full_path)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if compute_checksum_like(expected, &current) != expected {
+                warn!("PUT rejected - {} changed since last read", path);
+                let checksum = compute_checksum(&current);
+                let body = Json(serde_json::json!({
+                    "error": "File changed since it was read",
+                    "checksum": checksum,
+                }));
+                let etag = [(header::ETAG, format!("\"{}\"", checksum))];
+                return Ok((StatusCode::CONFLICT, etag, body).into_response());
+            }
+        }
     }
 
     // Reconstruct file with frontmatter
@@ -215,22 +618,527 @@ pub async fn put_file(
 
     // Write to filesystem
     if let Err(e) = std::fs::write(&full_path, &file_content) {
-        log_to_file(&format!("[server] PUT failed to write: {}", e));
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("PUT failed to write: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
     }
 
-    log_to_file(&format!("[server] PUT success: {}", path));
+    info!("PUT success: {}", path);
+    state.activity.record(&path, "edit").await;
     // File watcher will auto-refresh index
-    Ok(StatusCode::OK)
+    let etag = [(header::ETAG, format!("\"{}\"", compute_checksum(&file_content)))];
+    Ok((StatusCode::OK, etag).into_response())
+}
+
+pub async fn delete_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    info!("DELETE /api/files/{}", path);
+
+    // Validate path - prevent directory traversal
+    let full_path = match resolve_safe_path(&state.org_root, &path) {
+        Some(p) => p,
+        None => {
+            warn!("DELETE rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+    let _write_guard = state.write_locks.lock(&path).await;
+    if !full_path.is_file() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    if let Err(e) = tokio::fs::remove_file(&full_path).await {
+        error!("DELETE failed: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+
+    state.index.write().await.remove_document(&full_path);
+
+    // Notify WebSocket clients
+    let msg = serde_json::json!({
+        "type": "remove",
+        "path": path,
+        "timestamp": chrono::Utc::now().timestamp_millis()
+    });
+    let _ = state.ws_tx.send(msg.to_string());
+
+    info!("DELETE success: {}", path);
+    state.activity.record(&path, "delete").await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/files/{path}/refresh - Re-read one document into the index, for changes the
+/// watcher missed (e.g. on network filesystems). A file that no longer exists is dropped.
+pub async fn refresh_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    // Routed through the /api/files/{*path} wildcard, so the action is a path suffix
+    let path = match path.strip_suffix("/refresh") {
+        Some(p) => p.to_string(),
+        None => return Err(StatusCode::METHOD_NOT_ALLOWED.into()),
+    };
+    info!("POST /api/files/{}/refresh", path);
+
+    let full_path = match resolve_safe_path(&state.org_root, &path) {
+        Some(p) => p,
+        None => {
+            warn!("refresh rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+
+    let mut index = state.index.write().await;
+
+    if !full_path.is_file() {
+        if index.get_document(&path).is_none() {
+            return Err(StatusCode::NOT_FOUND.into());
+        }
+        index.remove_document(&full_path);
+        drop(index);
+
+        let msg = serde_json::json!({
+            "type": "remove",
+            "path": path,
+            "timestamp": chrono::Utc::now().timestamp_millis()
+        });
+        let _ = state.ws_tx.send(msg.to_string());
+        return Ok(Json(serde_json::json!({ "path": path, "removed": true })));
+    }
+
+    index.refresh_document(&full_path);
+    // Excluded files are never indexed
+    let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
+    let value = document_value(doc).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let msg = FileWatcher::update_message(&state, &index, &path, &full_path);
+    let _ = state.ws_tx.send(msg.to_string());
+
+    Ok(Json(value))
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveRequest {
+    path: String,
+}
+
+/// POST /api/files/archive - Move a document into the archive folder, drop its
+/// federation tracking, and point inbound links at the new location. When the archive
+/// folder isn't indexed (the default `archive` isn't), links are left as they are, since
+/// rewritten ones would point at a document the index no longer knows.
+pub async fn archive_file(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ArchiveRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let path = payload.path;
+    info!("POST /api/files/archive {}", path);
+
+    let full_path = match resolve_safe_path(&state.org_root, &path) {
+        Some(p) => p,
+        None => {
+            warn!("archive rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+    if !full_path.is_file() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let new_path = format!("{}/{}", state.config.archive_dir.value, path);
+    let new_full_path = resolve_safe_path(&state.org_root, &new_path).ok_or(StatusCode::FORBIDDEN)?;
+    if new_full_path.exists() {
+        return Err(StatusCode::CONFLICT.into());
+    }
+
+    // Move the file, stripping federation tracking on the way
+    let write_guard = state.write_locks.lock(&path).await;
+    let content = tokio::fs::read_to_string(&full_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(dir) = new_full_path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if let Err(e) = tokio::fs::write(&new_full_path, strip_federation_block(&content)).await {
+        error!("archive failed to write: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    if let Err(e) = tokio::fs::remove_file(&full_path).await {
+        error!("archive failed to remove original: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    drop(write_guard);
+
+    // Link targets that resolved to the old location (path without .md, plus stem)
+    let (backlinks, old_targets) = {
+        let index = state.index.read().await;
+        let backlinks = index
+            .get_document(&path)
+            .map(|d| d.backlinks.clone())
+            .unwrap_or_default();
+        let path_no_ext = path.strip_suffix(".md").unwrap_or(&path).to_string();
+        let mut targets = vec![path_no_ext];
+        if let Some(stem) = std::path::Path::new(&path).file_stem() {
+            let stem = stem.to_string_lossy().to_string();
+            let is_generic = matches!(stem.to_lowercase().as_str(), "readme" | "claude");
+            if !is_generic {
+                targets.push(stem);
+            }
+        }
+        (backlinks, targets)
+    };
+    let new_target = new_path.strip_suffix(".md").unwrap_or(&new_path).to_string();
+    let archive_indexed = !state.index.read().await.excludes(&new_full_path);
+    let rewritten = if archive_indexed {
+        rewrite_inbound_links(
+            &state,
+            &backlinks,
+            &[(old_targets, new_target)],
+            (&path, &new_path),
+        )
+        .await
+    } else {
+        Vec::new()
+    };
+
+    {
+        let mut index = state.index.write().await;
+        index.remove_document(&full_path);
+        for linking_path in &rewritten {
+            index.refresh_document(&state.org_root.join(linking_path));
+        }
+    }
+
+    // Notify WebSocket clients
+    let msg = serde_json::json!({
+        "type": "remove",
+        "path": path,
+        "timestamp": chrono::Utc::now().timestamp_millis()
+    });
+    let _ = state.ws_tx.send(msg.to_string());
+
+    info!("archive success: {} → {}", path, new_path);
+    state.activity.record(&path, "archive").await;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "path": new_path,
+        "rewritten": rewritten,
+    })))
+}
+
+/// Folder `template` names resolve against
+const TEMPLATES_DIR: &str = "templates";
+
+#[derive(Deserialize)]
+pub struct CreateFileRequest {
+    /// Vault-relative folder for the note; the root if omitted
+    #[serde(default)]
+    folder: String,
+    title: String,
+    #[serde(rename = "type")]
+    doc_type: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Name of a file under `templates/` (with or without `.md`) whose body becomes the
+    /// note's content, with `{{title}}` and `{{date}}` filled in
+    template: Option<String>,
+}
+
+/// Filename for a new note: the title lowercased, with runs of anything but letters and
+/// digits collapsed to `-`
+fn note_filename(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "untitled.md".to_string()
+    } else {
+        format!("{}.md", slug)
+    }
+}
+
+/// POST /api/files - Create a note from a title, with frontmatter and either an H1 or a
+/// template's body. Never overwrites: an existing file at the generated path is a 409.
+pub async fn create_file(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateFileRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let title = payload.title.trim();
+    if title.is_empty() {
+        return Err(ApiError::missing("title"));
+    }
+    let folder = payload.folder.trim_matches('/');
+    let path = if folder.is_empty() {
+        note_filename(title)
+    } else {
+        format!("{}/{}", folder, note_filename(title))
+    };
+    info!("POST /api/files {}", path);
+
+    let full_path = match resolve_safe_path(&state.org_root, &path) {
+        Some(p) => p,
+        None => {
+            warn!("create rejected - path traversal attempt: {}", path);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let body = match &payload.template {
+        Some(name) => {
+            let name = name.strip_suffix(".md").unwrap_or(name);
+            let template_path = format!("{}/{}.md", TEMPLATES_DIR, name);
+            let template_full =
+                resolve_safe_path(&state.org_root, &template_path).ok_or(StatusCode::FORBIDDEN)?;
+            let template = match tokio::fs::read_to_string(&template_full).await {
+                Ok(t) => t,
+                Err(_) => {
+                    warn!("create rejected - no template {}", template_path);
+                    return Err(ApiError::bad_request(
+                        "unknown_template",
+                        format!("No template at {}", template_path),
+                    ));
+                }
+            };
+            extract_body(&template)
+                .replace("{{title}}", title)
+                .replace("{{date}}", &date)
+        }
+        None => format!("# {}\n", title),
+    };
+
+    let mut frontmatter: HashMap<String, serde_json::Value> = HashMap::new();
+    if let Some(doc_type) = payload.doc_type.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        frontmatter.insert("type".to_string(), serde_json::json!(doc_type));
+    }
+    frontmatter.insert("created".to_string(), serde_json::json!(date));
+    frontmatter.insert("tags".to_string(), serde_json::json!(payload.tags));
+    let content = serialize_document(&frontmatter, &body);
+
+    let write_guard = state.write_locks.lock(&path).await;
+    if let Some(dir) = full_path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    // create_new so a file appearing since the path was chosen is never clobbered
+    let created = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&full_path)
+        .await;
+    let mut file = match created {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(StatusCode::CONFLICT.into());
+        }
+        Err(e) => {
+            error!("create failed to open {}: {}", path, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+    if let Err(e) = tokio::io::AsyncWriteExt::write_all(&mut file, content.as_bytes()).await {
+        error!("create failed to write: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    drop(file);
+    drop(write_guard);
+
+    // Index now rather than waiting on the watcher, so the caller can open the note at once
+    {
+        let mut index = state.index.write().await;
+        index.refresh_document(&full_path);
+        let msg = FileWatcher::update_message(&state, &index, &path, &full_path);
+        let _ = state.ws_tx.send(msg.to_string());
+    }
+
+    info!("create success: {}", path);
+    state.activity.record(&path, "edit").await;
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "path": path,
+            "checksum": compute_checksum(&content),
+        })),
+    ))
+}
+
+/// Apply each `(old targets, new target)` wikilink rewrite to the documents in `sources`,
+/// in order, then point their markdown links to `moved.0` at `moved.1`, under their write
+/// locks. Returns the paths that were changed on disk.
+async fn rewrite_inbound_links(
+    state: &AppState,
+    sources: &[String],
+    renames: &[(Vec<String>, String)],
+    moved: (&str, &str),
+) -> Vec<String> {
+    let mut rewritten: Vec<String> = Vec::new();
+    for linking_path in sources {
+        let _write_guard = state.write_locks.lock(linking_path).await;
+        let linking_full = state.org_root.join(linking_path);
+        let mut content = match tokio::fs::read_to_string(&linking_full).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let mut changed = false;
+        for (old_targets, new_target) in renames {
+            if let Some(updated) = rewrite_wikilinks(&content, old_targets, new_target) {
+                content = updated;
+                changed = true;
+            }
+        }
+        if let Some(updated) = rewrite_markdown_links(&content, linking_path, moved.0, moved.1) {
+            content = updated;
+            changed = true;
+        }
+        if changed && tokio::fs::write(&linking_full, content).await.is_ok() {
+            rewritten.push(linking_path.clone());
+        }
+    }
+    rewritten
+}
+
+/// Lowercased filename stem, or None for names too generic to link by (README, CLAUDE)
+fn linkable_stem(path: &str) -> Option<String> {
+    let stem = std::path::Path::new(path).file_stem()?.to_string_lossy().to_lowercase();
+    match stem.as_str() {
+        "readme" | "claude" => None,
+        _ => Some(stem),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MoveRequest {
+    from: String,
+    to: String,
+}
+
+/// POST /api/files/move - Move or rename a document and rewrite the links that pointed
+/// at it. Path-style links (`[[folder/old]]`) get the new path; stem links (`[[old]]`,
+/// `[[old#Heading]]`, `[[old|alias]]`) get the new stem, or the new path if that stem is
+/// taken; markdown links (`[text](old.md)`) get the new path relative to the linking note.
+/// A stem shared with another document is left alone, since those links never resolved
+/// here in the first place.
+pub async fn move_file(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MoveRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let from = payload.from.trim_matches('/').to_string();
+    let to = payload.to.trim_matches('/');
+    let to = if to.ends_with(".md") {
+        to.to_string()
+    } else {
+        format!("{}.md", to)
+    };
+    info!("POST /api/files/move {} → {}", from, to);
+
+    let (full_from, full_to) = match (
+        resolve_safe_path(&state.org_root, &from),
+        resolve_safe_path(&state.org_root, &to),
+    ) {
+        (Some(f), Some(t)) => (f, t),
+        _ => {
+            warn!("move rejected - path traversal attempt: {} → {}", from, to);
+            return Err(StatusCode::FORBIDDEN.into());
+        }
+    };
+    if from == to {
+        return Err(ApiError::bad_request("same_path", "`from` and `to` are the same path"));
+    }
+    if !full_from.is_file() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    // Work out the link rewrites before the index changes underneath us
+    let (backlinks, renames) = {
+        let index = state.index.read().await;
+        let backlinks = index
+            .get_document(&from)
+            .map(|d| d.backlinks.clone())
+            .unwrap_or_default();
+        let stem_taken = |stem: &str| {
+            index
+                .get_documents()
+                .iter()
+                .any(|d| d.path != from && linkable_stem(&d.path).as_deref() == Some(stem))
+        };
+
+        let from_no_ext = from.strip_suffix(".md").unwrap_or(&from).to_string();
+        let to_no_ext = to.strip_suffix(".md").unwrap_or(&to).to_string();
+        let mut renames = vec![(vec![from_no_ext], to_no_ext.clone())];
+        if let Some(old_stem) = linkable_stem(&from).filter(|s| !stem_taken(s)) {
+            let new_target = match linkable_stem(&to) {
+                Some(new_stem) if !stem_taken(&new_stem) => new_stem,
+                _ => to_no_ext,
+            };
+            renames.push((vec![old_stem], new_target));
+        }
+        (backlinks, renames)
+    };
+
+    let write_guard = state.write_locks.lock(&from).await;
+    if full_to.exists() {
+        return Err(StatusCode::CONFLICT.into());
+    }
+    if let Some(dir) = full_to.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if let Err(e) = tokio::fs::rename(&full_from, &full_to).await {
+        error!("move failed: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    }
+    drop(write_guard);
+    state.index.write().await.rename_document(&full_from, &full_to);
+
+    let rewritten = rewrite_inbound_links(&state, &backlinks, &renames, (&from, &to)).await;
+    {
+        let mut index = state.index.write().await;
+        for linking_path in &rewritten {
+            index.refresh_document(&state.org_root.join(linking_path));
+        }
+    }
+
+    // Notify WebSocket clients
+    let msg = serde_json::json!({
+        "type": "rename",
+        "oldPath": from,
+        "path": to,
+        "timestamp": chrono::Utc::now().timestamp_millis()
+    });
+    let _ = state.ws_tx.send(msg.to_string());
+
+    info!("move success: {} → {} ({} linking files rewritten)", from, to, rewritten.len());
+    state.activity.record(&to, "move").await;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "path": to,
+        "rewritten": rewritten,
+    })))
 }
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
     q: String,
+    /// `fuzzy` (default), `substring`, `word` or `regex`
+    #[serde(default)]
+    mode: SearchMode,
 }
 
 #[derive(Serialize)]
 pub struct SearchResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
     query: String,
     count: usize,
     total: usize,
@@ -240,55 +1148,979 @@ pub struct SearchResponse {
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
-) -> Json<SearchResponse> {
+) -> Result<Json<SearchResponse>, ApiError> {
     let index = state.index.read().await;
-    let results = index.search(&query.q);
+    let results = match index.search(&query.q, query.mode) {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("search rejected - {}: {}", e, query.q);
+            return Err(ApiError::bad_request("invalid_query", e));
+        }
+    };
 
     let items: Vec<serde_json::Value> = results
         .into_iter()
-        .map(|d| serde_json::to_value(d).unwrap())
+        .filter_map(|doc| {
+            let mut value = document_value(doc)?;
+            // Folder to show beside a title that several documents share
+            if doc.title_ambiguous {
+                value["context"] = serde_json::json!(folder_of(&doc.path));
+            }
+            Some(value)
+        })
         .collect();
 
-    Json(SearchResponse {
+    Ok(Json(SearchResponse {
+        index_generation: index.generation(),
         query: query.q,
         count: items.len(),
         total: items.len(),
         items,
-    })
+    }))
 }
 
-#[derive(Serialize)]
-pub struct GraphResponse {
-    nodes: Vec<GraphNode>,
-    links: Vec<GraphLink>,
+/// Parent folder of a document path ("" at the root)
+fn folder_of(path: &str) -> &str {
+    path.rsplit_once('/').map(|(folder, _)| folder).unwrap_or("")
 }
 
-#[derive(Serialize)]
-pub struct GraphNode {
-    id: String,
-    label: String,
-    #[serde(rename = "type")]
-    node_type: String,
-    status: Option<String>,
-    #[serde(rename = "linkCount")]
-    link_count: usize,
+#[derive(Deserialize)]
+pub struct ResolveQuery {
+    title: String,
 }
 
 #[derive(Serialize)]
-pub struct GraphLink {
-    source: String,
-    target: String,
+pub struct ResolveCandidate {
+    path: String,
+    title: String,
+    folder: String,
+    #[serde(rename = "type")]
+    doc_type: String,
+    updated: Option<String>,
 }
 
-pub async fn graph(State(state): State<Arc<AppState>>) -> Json<GraphResponse> {
+/// GET /api/resolve?title= - Documents with this title; several candidates when it's ambiguous
+pub async fn resolve_title(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ResolveQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let index = state.index.read().await;
+    let candidates: Vec<ResolveCandidate> = index
+        .documents_titled(&query.title)
+        .into_iter()
+        .map(|doc| ResolveCandidate {
+            path: doc.path.clone(),
+            title: doc.title.clone(),
+            folder: folder_of(&doc.path).to_string(),
+            doc_type: doc.doc_type.clone(),
+            updated: doc.updated.clone(),
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "title": query.title,
+        "ambiguous": candidates.len() > 1,
+        "candidates": candidates,
+    })))
+}
+
+/// Longest backlink snippet, in characters
+const BACKLINK_SNIPPET_MAX_CHARS: usize = 240;
+
+#[derive(Serialize)]
+pub struct BacklinkEntry {
+    path: String,
+    title: String,
+    /// Sentence in the linking document around its link to this one (empty if not found)
+    snippet: String,
+}
+
+#[derive(Serialize)]
+pub struct BacklinksResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    path: String,
+    backlinks: Vec<BacklinkEntry>,
+}
+
+/// GET /api/backlinks/{*path} - Documents linking to this one, each with the sentence that
+/// holds the link
+pub async fn backlinks(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<BacklinksResponse>, ApiError> {
+    let (index_generation, names, sources) = {
+        let index = state.index.read().await;
+        let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
+        let sources: Vec<(String, String)> = doc
+            .backlinks
+            .iter()
+            .filter_map(|p| index.get_document(p))
+            .map(|d| (d.path.clone(), d.title.clone()))
+            .collect();
+        (index.generation(), link_names(doc), sources)
+    };
+
+    let file_name = path.rsplit('/').next().unwrap_or(&path).to_lowercase();
+    let mut backlinks = Vec::with_capacity(sources.len());
+    for (source, title) in sources {
+        let snippet = match tokio::fs::read_to_string(state.org_root.join(&source)).await {
+            Ok(content) => backlink_snippet(&content, &names, &file_name),
+            Err(e) => {
+                error!("backlinks failed to read {}: {}", source, e);
+                String::new()
+            }
+        };
+        backlinks.push(BacklinkEntry {
+            path: source,
+            title,
+            snippet,
+        });
+    }
+
+    Ok(Json(BacklinksResponse {
+        index_generation,
+        path,
+        backlinks,
+    }))
+}
+
+/// Normalized names a wikilink can use to reach `doc`: its path without `.md`, its stem,
+/// its aliases and, for project files, the project folder (as the index resolves them)
+fn link_names(doc: &OrgDocument) -> Vec<String> {
+    let path_no_ext = doc.path.strip_suffix(".md").unwrap_or(&doc.path);
+    let mut names = vec![path_no_ext.to_string()];
+    if let Some(stem) = std::path::Path::new(&doc.path).file_stem() {
+        names.push(stem.to_string_lossy().to_string());
+    }
+    names.extend(doc.aliases.iter().cloned());
+    if let Some(project) = doc.path.strip_prefix("projects/").and_then(|p| p.split('/').next()) {
+        names.push(project.to_string());
+    }
+    names.iter().map(|n| normalize_link_name(n.trim())).collect()
+}
+
+/// The sentence around the first link in `content` that points at the document known by
+/// `names` (a `[[wikilink]]`) or `file_name` (a relative markdown link)
+fn backlink_snippet(content: &str, names: &[String], file_name: &str) -> String {
+    let wikilink_re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
+    let md_link_re = Regex::new(r"\]\(([^)\s]+)\)").unwrap();
+    let wikilink = wikilink_re.captures_iter(content).find_map(|cap| {
+        let target = cap[1].trim().trim_end_matches(".md");
+        let target = target.split('#').next().unwrap_or(target);
+        let whole = cap.get(0)?;
+        names
+            .contains(&normalize_link_name(target))
+            .then(|| (whole.start(), whole.end()))
+    });
+
+    let found = wikilink.or_else(|| {
+        md_link_re.captures_iter(content).find_map(|cap| {
+            let target = cap[1].split('#').next().unwrap_or("").to_lowercase();
+            let whole = cap.get(0)?;
+            (target.rsplit('/').next() == Some(file_name)).then(|| (whole.start(), whole.end()))
+        })
+    });
+
+    match found {
+        Some((start, end)) => sentence_around(content, start, end),
+        None => String::new(),
+    }
+}
+
+/// The sentence containing `content[start..end]`, bounded by `.`, `!` or `?` followed by
+/// whitespace, or by the line. List, quote and heading markers are dropped.
+fn sentence_around(content: &str, start: usize, end: usize) -> String {
+    let ends_sentence = |text: &str, i: usize, c: char| {
+        matches!(c, '.' | '!' | '?')
+            && text[i + c.len_utf8()..]
+                .chars()
+                .next()
+                .map(char::is_whitespace)
+                .unwrap_or(true)
+    };
+
+    let line_start = content[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = content[end..].find('\n').map(|i| end + i).unwrap_or(content.len());
+
+    let before = &content[line_start..start];
+    let from = before
+        .char_indices()
+        .rev()
+        .find(|&(i, c)| ends_sentence(before, i, c))
+        .map(|(i, c)| line_start + i + c.len_utf8())
+        .unwrap_or(line_start);
+    let after = &content[end..line_end];
+    let to = after
+        .char_indices()
+        .find(|&(i, c)| ends_sentence(after, i, c))
+        .map(|(i, c)| end + i + c.len_utf8())
+        .unwrap_or(line_end);
+
+    let sentence = content[from..to]
+        .trim()
+        .trim_start_matches(|c: char| matches!(c, '-' | '*' | '+' | '>' | '#') || c.is_whitespace());
+    if sentence.chars().count() > BACKLINK_SNIPPET_MAX_CHARS {
+        let mut snippet: String = sentence.chars().take(BACKLINK_SNIPPET_MAX_CHARS).collect();
+        snippet.push_str("...");
+        snippet
+    } else {
+        sentence.to_string()
+    }
+}
+
+/// Max documents returned by /api/unlinked-mentions
+const UNLINKED_MENTIONS_MAX: usize = 50;
+/// Titles and aliases shorter than this (in characters) are too noisy to scan for
+const MENTION_MIN_CHARS: usize = 3;
+/// Max concurrent file reads when filling the body cache
+const BODY_READ_CONCURRENCY: usize = 8;
+
+/// A document body and its lowercased form, as of the file's mtime
+pub struct CachedBody {
+    mtime: u64,
+    body: String,
+    lower: String,
+}
+
+impl CachedBody {
+    /// The sentence around `lower[start..end]`, from the original text when lowercasing
+    /// kept byte lengths (so the offsets line up with it) and from `lower` otherwise
+    fn snippet(&self, start: usize, end: usize) -> String {
+        let source = if self.lower.len() == self.body.len() {
+            &self.body
+        } else {
+            &self.lower
+        };
+        sentence_around(source, start, end)
+    }
+}
+
+/// Document bodies by path, refreshed when the indexed mtime changes
+pub type BodyCache = HashMap<String, Arc<CachedBody>>;
+
+#[derive(Serialize)]
+pub struct UnlinkedMention {
+    path: String,
+    title: String,
+    /// Sentence around the first mention
+    snippet: String,
+    /// Mentions in the document
+    count: usize,
+}
+
+#[derive(Serialize)]
+pub struct UnlinkedMentionsResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    path: String,
+    /// The title and aliases that were searched for
+    terms: Vec<String>,
+    count: usize,
+    /// More documents mention it than were returned
+    truncated: bool,
+    items: Vec<UnlinkedMention>,
+}
+
+/// GET /api/unlinked-mentions/{*path} - Documents whose body mentions this document's title
+/// or an alias (case-insensitive, whole words, outside `[[...]]`) without linking to it
+pub async fn unlinked_mentions(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<UnlinkedMentionsResponse>, ApiError> {
+    let (index_generation, terms, candidates) = {
+        let index = state.index.read().await;
+        let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
+
+        let mut terms: Vec<String> = std::iter::once(&doc.title)
+            .chain(doc.aliases.iter())
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| t.chars().count() >= MENTION_MIN_CHARS)
+            .collect();
+        terms.sort();
+        terms.dedup();
+
+        // Documents that already link here are backlinks, not unlinked mentions
+        let mut candidates: Vec<(String, String, u64)> = index
+            .get_documents()
+            .into_iter()
+            .filter(|d| d.path != doc.path && !doc.backlinks.contains(&d.path))
+            .map(|d| {
+                let mtime = index.get_document_mtime(&d.path).unwrap_or(0);
+                (d.path.clone(), d.title.clone(), mtime)
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+        (index.generation(), terms, candidates)
+    };
+
+    let live: HashSet<String> = candidates.iter().map(|(p, _, _)| p.clone()).collect();
+    let bodies: Vec<(String, String, Arc<CachedBody>)> = futures::stream::iter(candidates)
+        .map(|(doc_path, title, mtime)| {
+            let state = Arc::clone(&state);
+            async move {
+                let body = cached_body(&state, &doc_path, mtime).await?;
+                Some((doc_path, title, body))
+            }
+        })
+        .buffered(BODY_READ_CONCURRENCY)
+        .filter_map(|entry| async move { entry })
+        .collect()
+        .await;
+    state
+        .body_cache
+        .write()
+        .await
+        .retain(|p, _| live.contains(p) || *p == path);
+
+    let mut items = Vec::new();
+    let mut truncated = false;
+    for (doc_path, title, cached) in bodies {
+        let mentions = find_mentions(&cached.lower, &terms);
+        let (start, end) = match mentions.first() {
+            Some(&first) => first,
+            None => continue,
+        };
+        if items.len() == UNLINKED_MENTIONS_MAX {
+            truncated = true;
+            break;
+        }
+        items.push(UnlinkedMention {
+            path: doc_path,
+            title,
+            snippet: cached.snippet(start, end),
+            count: mentions.len(),
+        });
+    }
+
+    Ok(Json(UnlinkedMentionsResponse {
+        index_generation,
+        path,
+        terms,
+        count: items.len(),
+        truncated,
+        items,
+    }))
+}
+
+/// The body of `doc_path` from the cache, or read from disk if the cached copy is older
+/// than `mtime`
+async fn cached_body(state: &AppState, doc_path: &str, mtime: u64) -> Option<Arc<CachedBody>> {
+    if let Some(cached) = state.body_cache.read().await.get(doc_path) {
+        if cached.mtime == mtime {
+            return Some(Arc::clone(cached));
+        }
+    }
+
+    let content = match tokio::fs::read_to_string(state.org_root.join(doc_path)).await {
+        Ok(content) => content,
+        Err(e) => {
+            error!("unlinked mentions failed to read {}: {}", doc_path, e);
+            return None;
+        }
+    };
+    let body = extract_body(&content);
+    let cached = Arc::new(CachedBody {
+        mtime,
+        lower: body.to_lowercase(),
+        body,
+    });
+    state
+        .body_cache
+        .write()
+        .await
+        .insert(doc_path.to_string(), Arc::clone(&cached));
+    Some(cached)
+}
+
+/// Byte ranges in `lower` where one of `terms` appears as whole words and outside a
+/// `[[...]]` link, in order of position
+fn find_mentions(lower: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let is_word = |c: Option<char>| c.map(char::is_alphanumeric).unwrap_or(false);
+    let mut found: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        for (start, _) in lower.match_indices(term.as_str()) {
+            let end = start + term.len();
+            if is_word(lower[..start].chars().next_back()) || is_word(lower[end..].chars().next()) {
+                continue;
+            }
+            // Inside a wikilink when the last "[[" before it is still open
+            let before = &lower[..start];
+            let in_link = before
+                .rfind("[[")
+                .map(|open| !before[open..].contains("]]"))
+                .unwrap_or(false);
+            if !in_link && !found.iter().any(|&(s, e)| start < e && s < end) {
+                found.push((start, end));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+#[derive(Serialize)]
+pub struct IndexWarningsResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<DocumentWarnings>,
+    /// Wikilinks that matched several documents and were left unresolved
+    #[serde(rename = "ambiguousLinks")]
+    ambiguous_links: Vec<AmbiguousLink>,
+    /// Markdown files not indexed because they were too large or not valid UTF-8
+    #[serde(rename = "skippedFiles")]
+    skipped_files: Vec<IndexDiagnostic>,
+}
+
+#[derive(Serialize)]
+pub struct DocumentWarnings {
+    path: String,
+    warnings: Vec<String>,
+}
+
+/// GET /api/index/generation - Current index generation; refetch heavy endpoints when it changes
+pub async fn index_generation(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let index = state.index.read().await;
+    Json(serde_json::json!({ "indexGeneration": index.generation() }))
+}
+
+pub async fn index_warnings(State(state): State<Arc<AppState>>) -> Json<IndexWarningsResponse> {
+    let index = state.index.read().await;
+
+    let mut items: Vec<DocumentWarnings> = index
+        .get_documents()
+        .into_iter()
+        .filter(|d| !d.warnings.is_empty())
+        .map(|d| DocumentWarnings {
+            path: d.path.clone(),
+            warnings: d.warnings.clone(),
+        })
+        .collect();
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Json(IndexWarningsResponse {
+        index_generation: index.generation(),
+        count: items.len(),
+        items,
+        ambiguous_links: index.get_ambiguous_links().to_vec(),
+        skipped_files: index
+            .get_diagnostics()
+            .into_iter()
+            .filter(|d| matches!(d.kind, DiagnosticKind::TooLarge | DiagnosticKind::InvalidUtf8))
+            .cloned()
+            .collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct DiagnosticsQuery {
+    /// Only this kind (`excluded`, `tooLarge`, `invalidUtf8`, `parse`)
+    kind: Option<DiagnosticKind>,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticsResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<IndexDiagnostic>,
+}
+
+/// GET /api/diagnostics - Why files are missing from the index (excluded, too large, not
+/// UTF-8) and which indexed documents had parse problems
+pub async fn diagnostics(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DiagnosticsQuery>,
+) -> Json<DiagnosticsResponse> {
+    let index = state.index.read().await;
+    let items: Vec<IndexDiagnostic> = index
+        .get_diagnostics()
+        .into_iter()
+        .filter(|d| query.kind.map(|k| d.kind == k).unwrap_or(true))
+        .cloned()
+        .collect();
+
+    Json(DiagnosticsResponse {
+        index_generation: index.generation(),
+        count: items.len(),
+        items,
+    })
+}
+
+#[derive(Serialize)]
+pub struct TagsResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<TagEntry>,
+}
+
+#[derive(Serialize)]
+pub struct TagEntry {
+    tag: String,
+    count: usize,
+    paths: Vec<String>,
+}
+
+/// GET /api/tags - Every tag with its document count and paths
+pub async fn tags(State(state): State<Arc<AppState>>) -> Json<TagsResponse> {
+    let index = state.index.read().await;
+
+    let mut by_tag: HashMap<String, Vec<String>> = HashMap::new();
+    for doc in index.get_documents() {
+        for tag in &doc.tags {
+            by_tag.entry(tag.clone()).or_default().push(doc.path.clone());
+        }
+    }
+
+    let mut items: Vec<TagEntry> = by_tag
+        .into_iter()
+        .map(|(tag, mut paths)| {
+            paths.sort();
+            TagEntry {
+                tag,
+                count: paths.len(),
+                paths,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    Json(TagsResponse {
+        index_generation: index.generation(),
+        count: items.len(),
+        items,
+    })
+}
+
+#[derive(Serialize)]
+pub struct TagTreeNode {
+    /// Last segment of the tag (e.g. `health` for `area/health`)
+    name: String,
+    /// Full tag path
+    tag: String,
+    /// Documents tagged with exactly this tag
+    count: usize,
+    /// Distinct documents tagged with this tag or any descendant
+    total: usize,
+    children: Vec<TagTreeNode>,
+}
+
+#[derive(Serialize)]
+pub struct TagTreeResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    roots: Vec<TagTreeNode>,
+}
+
+#[derive(Default)]
+struct TagTreeBuilder<'a> {
+    exact: std::collections::BTreeSet<&'a str>,
+    rollup: std::collections::BTreeSet<&'a str>,
+    children: std::collections::BTreeMap<String, TagTreeBuilder<'a>>,
+}
+
+impl<'a> TagTreeBuilder<'a> {
+    fn into_nodes(self, prefix: &str) -> Vec<TagTreeNode> {
+        self.children
+            .into_iter()
+            .map(|(name, child)| {
+                let tag = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}{}{}", prefix, TAG_SEPARATOR, name)
+                };
+                let count = child.exact.len();
+                let total = child.rollup.len();
+                TagTreeNode {
+                    children: child.into_nodes(&tag),
+                    name,
+                    tag,
+                    count,
+                    total,
+                }
+            })
+            .collect()
+    }
+}
+
+/// GET /api/tags/tree - Tags nested by `/`-delimited namespace with rollup counts
+pub async fn tag_tree(State(state): State<Arc<AppState>>) -> Json<TagTreeResponse> {
+    let index = state.index.read().await;
+
+    let mut root = TagTreeBuilder::default();
+    for doc in index.get_documents() {
+        for tag in &doc.tags {
+            let segments: Vec<&str> = tag.split(TAG_SEPARATOR).filter(|s| !s.is_empty()).collect();
+            let mut node = &mut root;
+            for (i, segment) in segments.iter().enumerate() {
+                node = node.children.entry(segment.to_string()).or_default();
+                node.rollup.insert(doc.path.as_str());
+                if i == segments.len() - 1 {
+                    node.exact.insert(doc.path.as_str());
+                }
+            }
+        }
+    }
+
+    Json(TagTreeResponse {
+        index_generation: index.generation(),
+        roots: root.into_nodes(""),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct RenameTagRequest {
+    from: String,
+    to: String,
+}
+
+/// POST /api/tags/rename - Rewrite `from` to `to` in the `tags:` frontmatter of every
+/// document that has it, then refresh the index.
+pub async fn rename_tag(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RenameTagRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let from = payload.from.trim();
+    let to = payload.to.trim();
+    let is_valid = |t: &str| {
+        !t.is_empty() && !t.contains([',', '[', ']', '\n', '\r', '"', '\'', '#', ':'])
+    };
+    if !is_valid(from) || !is_valid(to) {
+        return Err(ApiError::bad_request(
+            "invalid_tag",
+            "Tags must be non-empty and can't contain , [ ] \" ' # : or line breaks",
+        ));
+    }
+    info!("POST /api/tags/rename {} → {}", from, to);
+
+    let paths: Vec<String> = {
+        let index = state.index.read().await;
+        index
+            .get_documents()
+            .into_iter()
+            .filter(|d| d.tags.iter().any(|t| t == from))
+            .map(|d| d.path.clone())
+            .collect()
+    };
+
+    let mut updated: Vec<String> = Vec::new();
+    for path in &paths {
+        let _write_guard = state.write_locks.lock(path).await;
+        let full_path = state.org_root.join(path);
+        let content = match tokio::fs::read_to_string(&full_path).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        if let Some(new_content) = document::rename_tag(&content, from, to) {
+            match tokio::fs::write(&full_path, new_content).await {
+                Ok(()) => updated.push(path.clone()),
+                Err(e) => error!("tag rename failed to write {}: {}", path, e),
+            }
+        }
+    }
+
+    {
+        let mut index = state.index.write().await;
+        for path in &updated {
+            index.refresh_document(&state.org_root.join(path));
+        }
+    }
+    for path in &updated {
+        state.activity.record(path, "edit").await;
+    }
+
+    info!("tag rename updated {} document(s)", updated.len());
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "from": from,
+        "to": to,
+        "updated": updated,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct TasksQuery {
+    /// "open" (default) | "done" | "all"
+    status: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TasksResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<TaskItem>,
+}
+
+#[derive(Serialize)]
+pub struct TaskItem {
+    path: String,
+    title: String,
+    line: usize,
+    text: String,
+    done: bool,
+}
+
+/// GET /api/tasks?status=open|done|all - Checkbox lines across the vault
+pub async fn tasks(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TasksQuery>,
+) -> Result<Json<TasksResponse>, ApiError> {
+    let (want_open, want_done) = match query.status.as_deref().unwrap_or("open") {
+        "open" => (true, false),
+        "done" => (false, true),
+        "all" => (true, true),
+        other => {
+            return Err(ApiError::bad_request(
+                "invalid_status",
+                format!("Unknown status `{}`; expected open, done or all", other),
+            ))
+        }
+    };
+
+    // Only read documents that have matching checkboxes, outside the index lock
+    let (index_generation, mut docs): (u64, Vec<(String, String)>) = {
+        let index = state.index.read().await;
+        let docs = index
+            .get_documents()
+            .into_iter()
+            .filter(|d| (want_open && d.tasks_open > 0) || (want_done && d.tasks_done > 0))
+            .map(|d| (d.path.clone(), d.title.clone()))
+            .collect();
+        (index.generation(), docs)
+    };
+    docs.sort();
+
+    let mut items: Vec<TaskItem> = Vec::new();
+    for (path, title) in docs {
+        let content = match tokio::fs::read_to_string(state.org_root.join(&path)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        for checkbox in extract_checkboxes(&content) {
+            if (checkbox.done && want_done) || (!checkbox.done && want_open) {
+                items.push(TaskItem {
+                    path: path.clone(),
+                    title: title.clone(),
+                    line: checkbox.line,
+                    text: checkbox.text,
+                    done: checkbox.done,
+                });
+            }
+        }
+    }
+
+    Ok(Json(TasksResponse {
+        index_generation,
+        count: items.len(),
+        items,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ExternalLinksResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    count: usize,
+    items: Vec<ExternalLink>,
+}
+
+#[derive(Serialize)]
+pub struct ExternalLink {
+    url: String,
+    /// Documents containing the URL
+    sources: Vec<String>,
+}
+
+/// GET /api/external-links - Every external URL in the vault and where it appears
+pub async fn external_links(State(state): State<Arc<AppState>>) -> Json<ExternalLinksResponse> {
+    let index = state.index.read().await;
+
+    let mut by_url: HashMap<String, Vec<String>> = HashMap::new();
+    for doc in index.get_documents() {
+        for url in &doc.external_links {
+            by_url.entry(url.clone()).or_default().push(doc.path.clone());
+        }
+    }
+
+    let mut items: Vec<ExternalLink> = by_url
+        .into_iter()
+        .map(|(url, mut sources)| {
+            sources.sort();
+            ExternalLink { url, sources }
+        })
+        .collect();
+    items.sort_by(|a, b| a.url.cmp(&b.url));
+
+    Json(ExternalLinksResponse {
+        index_generation: index.generation(),
+        count: items.len(),
+        items,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    /// RFC 3339 timestamp; only events at or after it are returned
+    since: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct ActivityResponse {
+    enabled: bool,
+    count: usize,
+    items: Vec<ActivityEvent>,
+}
+
+/// GET /api/activity?since=&limit= - Recent document view/edit events (oldest first)
+pub async fn activity(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityResponse>, ApiError> {
+    let since = match query.since.as_deref() {
+        Some(raw) => Some(
+            chrono::DateTime::parse_from_rfc3339(raw)
+                .map_err(|_| {
+                    ApiError::bad_request(
+                        "invalid_date",
+                        format!("`{}` is not an RFC 3339 timestamp", raw),
+                    )
+                })?
+                .with_timezone(&chrono::Utc),
+        ),
+        None => None,
+    };
+
+    let items = state
+        .activity
+        .read_since(since, query.limit.unwrap_or(500))
+        .await;
+
+    Ok(Json(ActivityResponse {
+        enabled: state.activity.is_enabled(),
+        count: items.len(),
+        items,
+    }))
+}
+
+/// Resolved runtime configuration, with the source (default/file/env/arg) of each value
+pub async fn effective_config(State(state): State<Arc<AppState>>) -> Json<ServerConfig> {
+    Json(state.config.clone())
+}
+
+#[derive(Serialize)]
+pub struct GraphResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    nodes: Vec<GraphNode>,
+    links: Vec<GraphLink>,
+}
+
+#[derive(Serialize)]
+pub struct GraphNode {
+    id: String,
+    label: String,
+    #[serde(rename = "type")]
+    node_type: String,
+    status: Option<String>,
+    #[serde(rename = "linkCount")]
+    link_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct GraphLink {
+    source: String,
+    target: String,
+}
+
+#[derive(Deserialize)]
+pub struct GraphQuery {
+    #[serde(rename = "type")]
+    doc_type: Option<String>,
+    tag: Option<String>,
+    /// Let `tag` match descendant tags too (`project` matches `project/alpha`)
+    prefix: Option<bool>,
+    /// Only documents in this folder or below, matched on whole path segments
+    folder: Option<String>,
+    /// Hide nodes with fewer edges than this (counted within the filtered graph)
+    #[serde(rename = "minDegree")]
+    min_degree: Option<usize>,
+    /// Include nodes with no edges (default true)
+    orphans: Option<bool>,
+}
+
+pub async fn graph(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<GraphQuery>,
+) -> Json<GraphResponse> {
     let index = state.index.read().await;
-    let docs = index.get_documents();
+
+    // Filter nodes before building edges
+    let docs: Vec<_> = index
+        .get_documents()
+        .into_iter()
+        .filter(|d| query.doc_type.as_ref().map(|t| d.doc_type == *t).unwrap_or(true))
+        .filter(|d| {
+            let prefix = query.prefix.unwrap_or(false);
+            query
+                .tag
+                .as_ref()
+                .map(|t| d.tags.iter().any(|tag| tag_matches(tag, t, prefix)))
+                .unwrap_or(true)
+        })
+        .filter(|d| {
+            query
+                .folder
+                .as_ref()
+                .map(|f| in_folder(&d.path, f))
+                .unwrap_or(true)
+        })
+        .collect();
 
     // Build node map
     let node_map: HashMap<String, &_> = docs.iter().map(|d| (d.path.clone(), *d)).collect();
 
+    // Build links from backlinks, dropping edges whose endpoint was filtered out
+    let mut links: Vec<GraphLink> = Vec::new();
+    for doc in &docs {
+        for backlink in &doc.backlinks {
+            if node_map.contains_key(backlink) {
+                links.push(GraphLink {
+                    source: backlink.clone(),
+                    target: doc.path.clone(),
+                });
+            }
+        }
+    }
+
+    // Degree-based pruning
+    let min_degree = query.min_degree.unwrap_or(0);
+    let include_orphans = query.orphans.unwrap_or(true);
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for link in &links {
+        *degree.entry(link.source.as_str()).or_insert(0) += 1;
+        *degree.entry(link.target.as_str()).or_insert(0) += 1;
+    }
+    let keep: std::collections::HashSet<String> = docs
+        .iter()
+        .filter(|d| {
+            let n = degree.get(d.path.as_str()).copied().unwrap_or(0);
+            n >= min_degree && (include_orphans || n > 0)
+        })
+        .map(|d| d.path.clone())
+        .collect();
+    links.retain(|l| keep.contains(&l.source) && keep.contains(&l.target));
+
     let nodes: Vec<GraphNode> = docs
         .iter()
+        .filter(|d| keep.contains(&d.path))
         .map(|d| GraphNode {
             id: d.path.clone(),
             label: d.title.clone(),
@@ -298,18 +2130,601 @@ pub async fn graph(State(state): State<Arc<AppState>>) -> Json<GraphResponse> {
         })
         .collect();
 
-    // Build links from backlinks
-    let mut links: Vec<GraphLink> = Vec::new();
-    for doc in docs {
+    Json(GraphResponse {
+        index_generation: index.generation(),
+        nodes,
+        links,
+    })
+}
+
+/// Maximum number of hops explored when searching for a path between documents
+const MAX_PATH_DEPTH: usize = 12;
+
+#[derive(Deserialize)]
+pub struct GraphPathQuery {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+pub struct GraphPathResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    from: String,
+    to: String,
+    /// Ordered document paths from `from` to `to`, inclusive
+    path: Vec<String>,
+    hops: usize,
+}
+
+/// Shortest chain of linked documents between two paths (links treated as undirected)
+pub async fn graph_path(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<GraphPathQuery>,
+) -> Result<Json<GraphPathResponse>, ApiError> {
+    let index = state.index.read().await;
+
+    if index.get_document(&query.from).is_none() || index.get_document(&query.to).is_none() {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    // Undirected adjacency from resolved backlinks
+    let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+    for doc in index.get_documents() {
         for backlink in &doc.backlinks {
-            if node_map.contains_key(backlink) {
-                links.push(GraphLink {
-                    source: backlink.clone(),
-                    target: doc.path.clone(),
-                });
+            adjacency.entry(doc.path.as_str()).or_default().push(backlink.as_str());
+            adjacency.entry(backlink.as_str()).or_default().push(doc.path.as_str());
+        }
+    }
+
+    // BFS, remembering each node's predecessor
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut frontier: Vec<&str> = vec![query.from.as_str()];
+    previous.insert(query.from.as_str(), query.from.as_str());
+    let mut depth = 0;
+
+    while !frontier.is_empty()
+        && !previous.contains_key(query.to.as_str())
+        && depth < MAX_PATH_DEPTH
+    {
+        let mut next = Vec::new();
+        for node in frontier {
+            for neighbor in adjacency.get(node).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if !previous.contains_key(neighbor) {
+                    previous.insert(neighbor, node);
+                    next.push(*neighbor);
+                }
             }
         }
+        frontier = next;
+        depth += 1;
+    }
+
+    if !previous.contains_key(query.to.as_str()) {
+        return Err(StatusCode::NOT_FOUND.into());
     }
 
-    Json(GraphResponse { nodes, links })
+    let mut path = vec![query.to.clone()];
+    let mut current = query.to.as_str();
+    while current != query.from {
+        current = previous[current];
+        path.push(current.to_string());
+    }
+    path.reverse();
+
+    Ok(Json(GraphPathResponse {
+        index_generation: index.generation(),
+        hops: path.len() - 1,
+        from: query.from,
+        to: query.to,
+        path,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct FolderTreeQuery {
+    /// Folder to list (e.g. `knowledge/`); the whole vault if omitted
+    #[serde(default)]
+    root: String,
+}
+
+#[derive(Serialize)]
+pub struct FolderTreeNode {
+    /// Folder or file name
+    name: String,
+    /// Vault-relative path
+    path: String,
+    #[serde(rename = "isDir")]
+    is_dir: bool,
+    /// Documents anywhere under a folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    doc_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    /// Subfolders first, then documents, each by name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<FolderTreeNode>>,
+}
+
+#[derive(Serialize)]
+pub struct FolderTreeResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    root: String,
+    count: usize,
+    children: Vec<FolderTreeNode>,
+}
+
+#[derive(Default)]
+struct FolderTreeBuilder<'a> {
+    count: usize,
+    folders: BTreeMap<String, FolderTreeBuilder<'a>>,
+    docs: Vec<(&'a str, &'a OrgDocument)>,
+}
+
+impl<'a> FolderTreeBuilder<'a> {
+    fn into_nodes(mut self, prefix: &str) -> Vec<FolderTreeNode> {
+        let join = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", prefix, name)
+            }
+        };
+        let mut nodes: Vec<FolderTreeNode> = Vec::new();
+        for (name, folder) in self.folders {
+            let path = join(&name);
+            nodes.push(FolderTreeNode {
+                count: Some(folder.count),
+                children: Some(folder.into_nodes(&path)),
+                name,
+                path,
+                is_dir: true,
+                title: None,
+                doc_type: None,
+                status: None,
+            });
+        }
+        self.docs.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, doc) in self.docs {
+            nodes.push(FolderTreeNode {
+                name: name.to_string(),
+                path: doc.path.clone(),
+                is_dir: false,
+                count: None,
+                title: Some(doc.title.clone()),
+                doc_type: Some(doc.doc_type.clone()),
+                status: doc.status.clone(),
+                children: None,
+            });
+        }
+        nodes
+    }
+}
+
+/// GET /api/tree - Folder hierarchy under `root` with per-folder document counts and
+/// each document's title and type, for a file-explorer sidebar. Built from the index, so
+/// excluded files never appear and neither do folders with no documents in them.
+pub async fn folder_tree(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FolderTreeQuery>,
+) -> Result<Json<FolderTreeResponse>, ApiError> {
+    let root = query.root.trim().trim_matches('/').to_string();
+    if !root.is_empty() {
+        let full_root = resolve_safe_path(&state.org_root, &root).ok_or(StatusCode::FORBIDDEN)?;
+        if !full_root.is_dir() {
+            return Err(StatusCode::NOT_FOUND.into());
+        }
+    }
+    let prefix = format!("{}/", root);
+
+    let index = state.index.read().await;
+    let mut tree = FolderTreeBuilder::default();
+    for doc in index.get_documents() {
+        let relative = if root.is_empty() {
+            doc.path.as_str()
+        } else {
+            match doc.path.strip_prefix(&prefix) {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        let mut segments: Vec<&str> = relative.split('/').collect();
+        let file_name = segments.pop().unwrap_or(relative);
+
+        tree.count += 1;
+        let mut node = &mut tree;
+        for segment in segments {
+            node = node.folders.entry(segment.to_string()).or_default();
+            node.count += 1;
+        }
+        node.docs.push((file_name, doc));
+    }
+
+    Ok(Json(FolderTreeResponse {
+        index_generation: index.generation(),
+        count: tree.count,
+        children: tree.into_nodes(&root),
+        root,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct FolderIndexQuery {
+    folder: String,
+    /// "markdown" (default) | "json"
+    format: Option<String>,
+    /// "type" | "tag" (default: no grouping)
+    #[serde(rename = "groupBy")]
+    group_by: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FolderIndexItem {
+    path: String,
+    title: String,
+    #[serde(rename = "type")]
+    doc_type: String,
+    backlinks: usize,
+}
+
+#[derive(Serialize)]
+pub struct FolderIndexGroup {
+    /// Group heading; `None` when the listing is ungrouped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    items: Vec<FolderIndexItem>,
+}
+
+#[derive(Serialize)]
+pub struct FolderIndexResponse {
+    #[serde(rename = "indexGeneration")]
+    index_generation: u64,
+    folder: String,
+    count: usize,
+    groups: Vec<FolderIndexGroup>,
+}
+
+/// Escape `|` and `]` so a title can't end the alias of a `[[path|title]]` link early
+fn escape_link_alias(title: &str) -> String {
+    title.replace('|', "\\|").replace(']', "\\]")
+}
+
+/// GET /api/folder-index - Listing of a folder's documents for a map-of-content note,
+/// as ready-to-paste markdown (`[[path|Title]]` wikilinks) or JSON.
+pub async fn folder_index(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FolderIndexQuery>,
+) -> Result<axum::response::Response, ApiError> {
+    let folder = query.folder.trim().trim_matches('/').to_string();
+    let prefix = format!("{}/", folder);
+    let as_markdown = match query.format.as_deref() {
+        None | Some("markdown") => true,
+        Some("json") => false,
+        Some(other) => {
+            return Err(ApiError::bad_request(
+                "invalid_format",
+                format!("Unknown format `{}`; expected markdown or json", other),
+            ))
+        }
+    };
+
+    let index = state.index.read().await;
+    let mut docs: Vec<_> = index
+        .get_documents()
+        .into_iter()
+        .filter(|d| folder.is_empty() || d.path.starts_with(&prefix))
+        .collect();
+    docs.sort_by_cached_key(|d| d.title.to_lowercase());
+
+    let item = |d: &OrgDocument| FolderIndexItem {
+        path: d.path.clone(),
+        title: d.title.clone(),
+        doc_type: d.doc_type.clone(),
+        backlinks: d.backlinks.len(),
+    };
+
+    let groups: Vec<FolderIndexGroup> = match query.group_by.as_deref() {
+        None => vec![FolderIndexGroup {
+            name: None,
+            items: docs.iter().map(|d| item(d)).collect(),
+        }],
+        Some("type") | Some("tag") => {
+            let by_tag = query.group_by.as_deref() == Some("tag");
+            let mut grouped: std::collections::BTreeMap<String, Vec<FolderIndexItem>> =
+                std::collections::BTreeMap::new();
+            for d in &docs {
+                let keys = if !by_tag {
+                    vec![d.doc_type.clone()]
+                } else if d.tags.is_empty() {
+                    vec!["untagged".to_string()]
+                } else {
+                    d.tags.clone()
+                };
+                for key in keys {
+                    grouped.entry(key).or_default().push(item(d));
+                }
+            }
+            grouped
+                .into_iter()
+                .map(|(name, items)| FolderIndexGroup {
+                    name: Some(name),
+                    items,
+                })
+                .collect()
+        }
+        Some(other) => {
+            return Err(ApiError::bad_request(
+                "invalid_group_by",
+                format!("Unknown groupBy `{}`; expected type or tag", other),
+            ))
+        }
+    };
+
+    if !as_markdown {
+        return Ok(Json(FolderIndexResponse {
+            index_generation: index.generation(),
+            folder,
+            count: docs.len(),
+            groups,
+        })
+        .into_response());
+    }
+
+    let mut markdown = String::new();
+    for group in &groups {
+        if let Some(name) = &group.name {
+            if !markdown.is_empty() {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("## {}\n\n", name));
+        }
+        for entry in &group.items {
+            let target = entry.path.strip_suffix(".md").unwrap_or(&entry.path);
+            markdown.push_str(&format!("- [[{}|{}]]", target, escape_link_alias(&entry.title)));
+            if entry.backlinks > 0 {
+                markdown.push_str(&format!(
+                    " ({} backlink{})",
+                    entry.backlinks,
+                    if entry.backlinks == 1 { "" } else { "s" }
+                ));
+            }
+            markdown.push('\n');
+        }
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        markdown,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_prefix_matches_descendants_only() {
+        assert!(tag_matches("project/alpha", "project", true));
+        assert!(tag_matches("project/alpha", "project/", true));
+        assert!(tag_matches("project/alpha/ui", "project", true));
+        assert!(tag_matches("project", "project", true));
+        assert!(!tag_matches("projects/alpha", "project", true));
+        assert!(!tag_matches("projects/alpha", "project/", true));
+        assert!(!tag_matches("project", "project/alpha", true));
+    }
+
+    #[test]
+    fn tag_match_is_exact_without_prefix() {
+        assert!(tag_matches("project", "project", false));
+        assert!(!tag_matches("project/alpha", "project", false));
+        assert!(!tag_matches("project", "project/", false));
+        assert!(!tag_matches("Project", "project", false));
+    }
+
+    #[test]
+    fn empty_wanted_tag_matches_nothing_by_prefix() {
+        assert!(!tag_matches("project", "", true));
+        assert!(!tag_matches("project", "/", true));
+        assert!(!tag_matches("project", "", false));
+    }
+
+    #[test]
+    fn tag_ancestors_skip_empty_segments() {
+        let tags: Vec<&str> = tag_with_ancestors("a/b/c").collect();
+        assert_eq!(tags, vec!["a", "a/b", "a/b/c"]);
+        let tags: Vec<&str> = tag_with_ancestors("/a").collect();
+        assert_eq!(tags, vec!["/a"]);
+        let tags: Vec<&str> = tag_with_ancestors("solo").collect();
+        assert_eq!(tags, vec!["solo"]);
+    }
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn cached(body: &str) -> CachedBody {
+        CachedBody {
+            mtime: 0,
+            body: body.to_string(),
+            lower: body.to_lowercase(),
+        }
+    }
+
+    #[test]
+    fn mentions_need_word_boundaries() {
+        let lower = "alpha, alphabet and realpha. (alpha)\nalpha2 xalpha";
+        assert_eq!(
+            find_mentions(lower, &terms(&["alpha"])),
+            vec![(0, 5), (30, 35)]
+        );
+        assert!(find_mentions("the alphabet", &terms(&["alpha"])).is_empty());
+    }
+
+    #[test]
+    fn overlapping_terms_keep_the_first_listed() {
+        let lower = "the project alpha plan, and alpha alone";
+        let found = find_mentions(lower, &terms(&["project alpha", "alpha"]));
+        assert_eq!(found, vec![(4, 17), (28, 33)]);
+    }
+
+    #[test]
+    fn mentions_inside_wikilinks_are_skipped() {
+        let lower = "see [[alpha]] and [[notes/alpha|the alpha note]], but alpha here";
+        assert_eq!(find_mentions(lower, &terms(&["alpha"])), vec![(54, 59)]);
+        // An unclosed `[[` still hides what follows it
+        assert!(find_mentions("broken [[alpha", &terms(&["alpha"])).is_empty());
+    }
+
+    #[test]
+    fn mentions_in_non_ascii_bodies() {
+        // "İ" lowercases to two characters, three bytes instead of two
+        let body = cached("İstanbul trip. Met Alpha there. Ünïcode ok");
+        assert_ne!(body.lower.len(), body.body.len());
+        let found = find_mentions(&body.lower, &terms(&["alpha"]));
+        assert_eq!(found.len(), 1);
+        let (start, end) = found[0];
+        assert_eq!(&body.lower[start..end], "alpha");
+        assert_eq!(body.snippet(start, end), "met alpha there.");
+
+        // Case is kept when lowercasing didn't shift offsets
+        let body = cached("Über notes. Met Alpha there.");
+        assert_eq!(body.lower.len(), body.body.len());
+        let (start, end) = find_mentions(&body.lower, &terms(&["alpha"]))[0];
+        assert_eq!(body.snippet(start, end), "Met Alpha there.");
+    }
+
+    fn alpha() -> Vec<String> {
+        vec![
+            normalize_link_name("notes/alpha"),
+            normalize_link_name("alpha"),
+        ]
+    }
+
+    #[test]
+    fn backlink_snippet_takes_the_sentence_around_a_mid_line_wikilink() {
+        let content = "# Log\nFirst point. See [[Alpha|the alpha doc]] for details! Then more.\n";
+        assert_eq!(
+            backlink_snippet(content, &alpha(), "alpha.md"),
+            "See [[Alpha|the alpha doc]] for details!"
+        );
+        let content = "Other [[beta]] link.\n## [[notes/alpha#Setup]] steps\n";
+        assert_eq!(
+            backlink_snippet(content, &alpha(), "alpha.md"),
+            "[[notes/alpha#Setup]] steps"
+        );
+    }
+
+    #[test]
+    fn backlink_snippet_falls_back_to_markdown_links() {
+        let content = "- Read [the doc](../notes/Alpha.md#intro) today. Later.\n";
+        assert_eq!(
+            backlink_snippet(content, &alpha(), "alpha.md"),
+            "Read [the doc](../notes/Alpha.md#intro) today."
+        );
+        assert_eq!(
+            backlink_snippet("[x](alphabet.md)", &alpha(), "alpha.md"),
+            ""
+        );
+    }
+
+    #[test]
+    fn backlink_snippet_handles_multibyte_neighbours() {
+        let content = "Résumé. Voir [[alpha]]… puis « suite »! Fin.";
+        assert_eq!(
+            backlink_snippet(content, &alpha(), "alpha.md"),
+            "Voir [[alpha]]… puis « suite »!"
+        );
+        let content = "> 日本語。[[alpha]]！";
+        assert_eq!(
+            backlink_snippet(content, &alpha(), "alpha.md"),
+            "日本語。[[alpha]]！"
+        );
+    }
+
+    #[test]
+    fn backlink_snippet_is_truncated() {
+        let content = format!("{} [[alpha]] {}", "é".repeat(200), "word ".repeat(40));
+        let snippet = backlink_snippet(&content, &alpha(), "alpha.md");
+        assert!(snippet.ends_with("..."));
+        assert_eq!(snippet.chars().count(), BACKLINK_SNIPPET_MAX_CHARS + 3);
+        assert!(snippet.starts_with("éé"));
+
+        let fits = format!("{} [[alpha]]", "a".repeat(BACKLINK_SNIPPET_MAX_CHARS - 10));
+        assert_eq!(backlink_snippet(&fits, &alpha(), "alpha.md"), fits);
+    }
+
+    fn utc(raw: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(raw)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn parse_date_reads_rfc3339_with_an_offset() {
+        assert_eq!(
+            parse_date("2026-03-01T10:00:00+02:00"),
+            Some(utc("2026-03-01T08:00:00Z"))
+        );
+        assert_eq!(
+            parse_date(" 2026-03-01T10:00:00Z "),
+            Some(utc("2026-03-01T10:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn parse_date_takes_bare_dates_as_midnight_utc() {
+        assert_eq!(parse_date("2026-03-01"), Some(utc("2026-03-01T00:00:00Z")));
+        // A time that isn't RFC 3339 is dropped, keeping the day
+        assert_eq!(
+            parse_date("2026-03-01 14:30"),
+            Some(utc("2026-03-01T00:00:00Z"))
+        );
+        assert_eq!(
+            parse_date("2026-03-01T14:30"),
+            Some(utc("2026-03-01T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn parse_date_rejects_garbage() {
+        for raw in [
+            "",
+            "yesterday",
+            "2026-13-01",
+            "2026-02-30",
+            "01/03/2026",
+            "ééééééé",
+        ] {
+            assert_eq!(parse_date(raw), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn note_filename_slugs_the_title() {
+        assert_eq!(note_filename("Weekly Review"), "weekly-review.md");
+        assert_eq!(note_filename("  Q3 -- plan!!  "), "q3-plan.md");
+        assert_eq!(note_filename("Café Ünïcode"), "café-ünïcode.md");
+    }
+
+    #[test]
+    fn note_filename_drops_separators_and_reserved_characters() {
+        assert_eq!(note_filename("notes/2026\\march"), "notes-2026-march.md");
+        assert_eq!(note_filename("../../etc/passwd"), "etc-passwd.md");
+        assert_eq!(note_filename(".."), "untitled.md");
+        assert_eq!(note_filename("a:b*c?d\"e<f>g|h"), "a-b-c-d-e-f-g-h.md");
+        assert_eq!(note_filename("C:\\Windows"), "c-windows.md");
+    }
+
+    #[test]
+    fn note_filename_falls_back_for_empty_titles() {
+        assert_eq!(note_filename(""), "untitled.md");
+        assert_eq!(note_filename("   \t\n"), "untitled.md");
+        assert_eq!(note_filename("?!/"), "untitled.md");
+    }
 }
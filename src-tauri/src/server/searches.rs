@@ -0,0 +1,154 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+use crate::server::config::state_file;
+use crate::server::error::ApiError;
+use crate::server::AppState;
+
+const SAVED_SEARCHES_FILE: &str = ".vitrum-searches.json";
+
+/// A named set of query params for `/api/search` or `/api/files`. The server only stores
+/// them; the client replays `params` against whichever endpoint it likes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    /// e.g. `{ "type": "task", "status": "open" }` or `{ "q": "release" }`
+    pub params: BTreeMap<String, String>,
+    /// RFC 3339 time the search was last saved
+    #[serde(rename = "updatedAt", default)]
+    pub updated_at: String,
+}
+
+/// Saved searches for the vault (`.vitrum-searches.json` in the state dir), sorted by name
+pub struct SavedSearches {
+    path: PathBuf,
+    searches: RwLock<Vec<SavedSearch>>,
+}
+
+impl SavedSearches {
+    pub fn new(org_root: &std::path::Path, state_dir: &std::path::Path) -> Self {
+        let path = state_file(org_root, state_dir, SAVED_SEARCHES_FILE);
+        let searches = match std::fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str::<Vec<SavedSearch>>(&raw).unwrap_or_else(|e| {
+                error!("Failed to parse {}: {}", SAVED_SEARCHES_FILE, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        SavedSearches {
+            path,
+            searches: RwLock::new(searches),
+        }
+    }
+
+    pub async fn list(&self) -> Vec<SavedSearch> {
+        self.searches.read().await.clone()
+    }
+
+    /// Add `search`, or replace the one with the same name
+    pub async fn upsert(&self, search: SavedSearch) -> Result<(), String> {
+        let mut searches = self.searches.write().await;
+        let mut updated = searches.clone();
+        updated.retain(|s| s.name != search.name);
+        updated.push(search);
+        updated.sort_by_cached_key(|s| s.name.to_lowercase());
+        self.save(&updated)?;
+        *searches = updated;
+        Ok(())
+    }
+
+    /// Remove the search called `name`. Returns false if there was none.
+    pub async fn remove(&self, name: &str) -> Result<bool, String> {
+        let mut searches = self.searches.write().await;
+        if !searches.iter().any(|s| s.name == name) {
+            return Ok(false);
+        }
+        let updated: Vec<SavedSearch> =
+            searches.iter().filter(|s| s.name != name).cloned().collect();
+        self.save(&updated)?;
+        *searches = updated;
+        Ok(true)
+    }
+
+    fn save(&self, searches: &[SavedSearch]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(searches).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write {}: {}", SAVED_SEARCHES_FILE, e))
+    }
+}
+
+// --- Handlers ---
+
+#[derive(Serialize)]
+pub struct SavedSearchesResponse {
+    count: usize,
+    items: Vec<SavedSearch>,
+}
+
+/// GET /api/saved-searches
+pub async fn list_saved_searches(
+    State(state): State<Arc<AppState>>,
+) -> Json<SavedSearchesResponse> {
+    let items = state.saved_searches.list().await;
+    Json(SavedSearchesResponse {
+        count: items.len(),
+        items,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct SaveSearchRequest {
+    name: String,
+    #[serde(default)]
+    params: BTreeMap<String, String>,
+}
+
+/// POST /api/saved-searches - Save a search, replacing any with the same name
+pub async fn save_search(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SaveSearchRequest>,
+) -> Result<Json<SavedSearch>, ApiError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::missing("name"));
+    }
+    info!("POST /api/saved-searches {}", name);
+
+    let search = SavedSearch {
+        name: name.to_string(),
+        params: payload.params,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+    match state.saved_searches.upsert(search.clone()).await {
+        Ok(()) => Ok(Json(search)),
+        Err(e) => {
+            error!("{}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+/// DELETE /api/saved-searches/:name
+pub async fn delete_saved_search(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    info!("DELETE /api/saved-searches/{}", name);
+    match state.saved_searches.remove(&name).await {
+        Ok(true) => Ok(Json(serde_json::json!({ "success": true, "name": name }))),
+        Ok(false) => Err(StatusCode::NOT_FOUND.into()),
+        Err(e) => {
+            error!("{}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
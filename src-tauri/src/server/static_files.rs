@@ -1,27 +1,35 @@
 use axum::{
     body::Body,
     extract::Request,
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
 use rust_embed::Embed;
 
+// Folder is resolved by build.rs: VITRUM_CLIENT_DIST, defaulting to ../packages/client/dist
 #[derive(Embed)]
-#[folder = "../packages/client/dist"]
+#[folder = "$VITRUM_CLIENT_DIST"]
 struct ClientDist;
 
+/// Boundary for multi-range (`multipart/byteranges`) responses
+const BYTERANGES_BOUNDARY: &str = "vitrum-byteranges";
+
+/// More ranges than this in one request are ignored and the full body is served
+const MAX_RANGES: usize = 16;
+
 /// Serve embedded static files, with SPA fallback to index.html
 pub async fn static_handler(req: Request<Body>) -> impl IntoResponse {
     let path = req.uri().path().trim_start_matches('/');
+    let headers = req.headers();
 
     // Try the exact path first
     if let Some(file) = ClientDist::get(path) {
-        return serve_file(path, &file.data);
+        return serve_file(path, &file.data, headers);
     }
 
     // SPA fallback: serve index.html for non-file paths
     if let Some(file) = ClientDist::get("index.html") {
-        return serve_file("index.html", &file.data);
+        return serve_file("index.html", &file.data, headers);
     }
 
     Response::builder()
@@ -30,7 +38,73 @@ pub async fn static_handler(req: Request<Body>) -> impl IntoResponse {
         .unwrap()
 }
 
-fn serve_file(path: &str, data: &[u8]) -> Response<Body> {
+/// Result of interpreting a `Range` request header against a body length
+enum ByteRanges {
+    /// No (usable) Range header: serve the whole body
+    Full,
+    /// Inclusive `(start, end)` ranges, all within the body
+    Partial(Vec<(usize, usize)>),
+    /// Syntactically valid, but no range overlaps the body
+    Unsatisfiable,
+}
+
+/// Parse `bytes=0-99,200-,-500`. Malformed headers (or other units) are ignored, as RFC 9110 allows.
+fn parse_range(header: Option<&str>, len: usize) -> ByteRanges {
+    let spec = match header.and_then(|h| h.trim().strip_prefix("bytes=")) {
+        Some(s) => s,
+        None => return ByteRanges::Full,
+    };
+
+    let mut ranges = Vec::new();
+    let mut parts = 0;
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        parts += 1;
+        if parts > MAX_RANGES {
+            return ByteRanges::Full;
+        }
+
+        let (first, last) = match part.split_once('-') {
+            Some(pair) => pair,
+            None => return ByteRanges::Full,
+        };
+        let range = match (first.trim(), last.trim()) {
+            // Suffix range: the last N bytes
+            ("", n) => match n.parse::<usize>() {
+                Ok(0) => None,
+                Ok(n) => (len > 0).then(|| (len.saturating_sub(n), len - 1)),
+                Err(_) => return ByteRanges::Full,
+            },
+            (start, end) => {
+                let start = match start.parse::<usize>() {
+                    Ok(s) => s,
+                    Err(_) => return ByteRanges::Full,
+                };
+                let end = if end.is_empty() {
+                    len.saturating_sub(1)
+                } else {
+                    match end.parse::<usize>() {
+                        Ok(e) if e >= start => e.min(len.saturating_sub(1)),
+                        _ => return ByteRanges::Full,
+                    }
+                };
+                (start < len).then_some((start, end))
+            }
+        };
+        if let Some(range) = range {
+            ranges.push(range);
+        }
+    }
+
+    if parts == 0 {
+        ByteRanges::Full
+    } else if ranges.is_empty() {
+        ByteRanges::Unsatisfiable
+    } else {
+        ByteRanges::Partial(ranges)
+    }
+}
+
+fn serve_file(path: &str, data: &[u8], headers: &HeaderMap) -> Response<Body> {
     let mime = mime_guess::from_path(path)
         .first_or_octet_stream()
         .to_string();
@@ -45,10 +119,60 @@ fn serve_file(path: &str, data: &[u8]) -> Response<Body> {
         "public, max-age=3600" // 1 hour — icons, manifest, service worker
     };
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, mime)
+    let builder = Response::builder()
         .header(header::CACHE_CONTROL, cache_control)
-        .body(Body::from(data.to_vec()))
-        .unwrap()
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    match parse_range(range, data.len()) {
+        ByteRanges::Full => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, mime)
+            .body(Body::from(data.to_vec()))
+            .unwrap(),
+        ByteRanges::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", data.len()))
+            .body(Body::empty())
+            .unwrap(),
+        ByteRanges::Partial(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, mime)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, data.len()),
+                )
+                .body(Body::from(data[start..=end].to_vec()))
+                .unwrap()
+        }
+        ByteRanges::Partial(ranges) => {
+            let mut body = Vec::new();
+            for (start, end) in ranges {
+                body.extend_from_slice(
+                    format!(
+                        "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        BYTERANGES_BOUNDARY,
+                        mime,
+                        start,
+                        end,
+                        data.len()
+                    )
+                    .as_bytes(),
+                );
+                body.extend_from_slice(&data[start..=end]);
+            }
+            body.extend_from_slice(format!("\r\n--{}--\r\n", BYTERANGES_BOUNDARY).as_bytes());
+
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={}", BYTERANGES_BOUNDARY),
+                )
+                .body(Body::from(body))
+                .unwrap()
+        }
+    }
 }
@@ -1,15 +1,88 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{RwLock, Semaphore};
+use tracing::{error, info, warn};
 
+use crate::server::document::{frontmatter_bounds, normalize_text};
 use crate::server::index::DocumentIndex;
-use crate::server::log_to_file;
-use crate::server::peers::PeerRegistry;
+use crate::server::peers::{
+    split_host_port, with_peer_auth, PeerRegistry, INSTANCE_HEADER, TRANSFER_TIMEOUT,
+};
+use crate::server::resolve_safe_path;
 
+/// Default for `self.syncIntervalSecs` in `.vitrum-peers.json`
 const SYNC_POLL_INTERVAL_SECS: u64 = 60;
 
+/// Directory (relative to org root) holding adopted-body snapshots used as 3-way merge bases
+const BASE_SNAPSHOT_DIR: &str = ".vitrum/base";
+
+/// Origin-side record of which peers adopted which of our documents (path → peer names, or
+/// instance IDs in older files); only those peers may push edits back.
+const ADOPTIONS_FILE: &str = ".vitrum/adoptions.json";
+
+/// Origin-side revision counters for shared documents (path → rev + body checksum)
+const REVS_FILE: &str = ".vitrum/revs.json";
+
+/// Frontmatter keys an inbox document always sets itself; a sender's values for these
+/// (and its `federation` block) are not carried over.
+const INBOX_KEYS: &[&str] = &[
+    "type",
+    "created",
+    "source",
+    "from-name",
+    "from-instance",
+    "from-host",
+    "original-path",
+    "content-checksum",
+    "tags",
+    "federation",
+];
+
+static CHECKSUM_ALGORITHM: OnceLock<ChecksumAlgorithm> = OnceLock::new();
+
+// --- Checksums ---
+
+/// Hash algorithm used for document checksums. The checksum string is prefixed with
+/// the algorithm name (e.g. `sha256:...`, `blake3:...`) so peers can tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sha256" | "sha-256" => Some(ChecksumAlgorithm::Sha256),
+            "blake3" => Some(ChecksumAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    fn digest(&self, content: &str) -> String {
+        match self {
+            ChecksumAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                hasher.update(content.as_bytes());
+                format!("{:x}", hasher.finalize())
+            }
+            ChecksumAlgorithm::Blake3 => blake3::hash(content.as_bytes()).to_hex().to_string(),
+        }
+    }
+}
+
 // --- Federation frontmatter types ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +105,19 @@ pub struct FederationMeta {
     pub sync_status: String,
     #[serde(rename = "last-sync-check")]
     pub last_sync_check: String,
+    /// Local revision: equals `origin-rev` when synced, bumped past it on local edits
+    #[serde(default)]
+    pub rev: u64,
+    /// Origin revision as of the last sync (or last origin change we flagged)
+    #[serde(rename = "origin-rev", default)]
+    pub origin_rev: u64,
+}
+
+/// Origin-side revision of a shared document; bumped when its body checksum changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DocumentRev {
+    rev: u64,
+    checksum: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -57,6 +143,8 @@ pub struct ConflictDiff {
     pub local_checksum: String,
     #[serde(rename = "originChecksum")]
     pub origin_checksum: String,
+    #[serde(rename = "originRev")]
+    pub origin_rev: u64,
 }
 
 /// Callback type for sync status changes
@@ -77,6 +165,22 @@ pub struct SyncStatusEvent {
     pub timestamp: i64,
 }
 
+/// Callback type for batch progress updates
+pub type BatchProgressCallback = Box<
+    dyn Fn(BatchProgressEvent) + Send + Sync,
+>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgressEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub operation: String,
+    pub item: String,
+    pub success: bool,
+    pub completed: usize,
+    pub total: usize,
+}
+
 // --- SyncService ---
 
 pub struct SyncService {
@@ -84,7 +188,11 @@ pub struct SyncService {
     index: Arc<RwLock<DocumentIndex>>,
     peer_registry: Arc<PeerRegistry>,
     on_status_change: RwLock<Option<SyncStatusCallback>>,
+    on_batch_progress: RwLock<Option<BatchProgressCallback>>,
     local_host: RwLock<Option<(String, u16)>>,
+    batch_concurrency: usize,
+    /// Serializes read-modify-write of the origin-side adoptions and revs files
+    origin_files_lock: Mutex<()>,
 }
 
 impl SyncService {
@@ -92,13 +200,17 @@ impl SyncService {
         org_root: &Path,
         index: Arc<RwLock<DocumentIndex>>,
         peer_registry: Arc<PeerRegistry>,
+        batch_concurrency: usize,
     ) -> Self {
         SyncService {
             org_root: org_root.to_path_buf(),
             index,
             peer_registry,
             on_status_change: RwLock::new(None),
+            on_batch_progress: RwLock::new(None),
             local_host: RwLock::new(None),
+            batch_concurrency: batch_concurrency.max(1),
+            origin_files_lock: Mutex::new(()),
         }
     }
 
@@ -110,6 +222,52 @@ impl SyncService {
         *self.on_status_change.write().await = Some(callback);
     }
 
+    pub async fn on_batch_progress(&self, callback: BatchProgressCallback) {
+        *self.on_batch_progress.write().await = Some(callback);
+    }
+
+    /// Run a batch of labelled jobs with bounded concurrency (semaphore-gated), emitting a
+    /// progress event as each one finishes. A failing job doesn't abort the rest.
+    /// `limit` overrides the global `ORG_VIEWER_BATCH_CONCURRENCY` for this operation.
+    /// Results are returned in input order.
+    pub async fn run_batch<T, Fut>(
+        &self,
+        operation: &str,
+        jobs: Vec<(String, Fut)>,
+        limit: Option<usize>,
+    ) -> Vec<(String, Result<T, String>)>
+    where
+        Fut: Future<Output = Result<T, String>>,
+    {
+        let total = jobs.len();
+        let semaphore = Semaphore::new(limit.unwrap_or(self.batch_concurrency).max(1));
+        let completed = AtomicUsize::new(0);
+
+        let runs = jobs.into_iter().map(|(label, job)| {
+            let semaphore = &semaphore;
+            let completed = &completed;
+            async move {
+                let _permit = semaphore.acquire().await;
+                let result = job.await;
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+
+                self.emit_batch_progress(BatchProgressEvent {
+                    event_type: "batch-progress".to_string(),
+                    operation: operation.to_string(),
+                    item: label.clone(),
+                    success: result.is_ok(),
+                    completed: done,
+                    total,
+                })
+                .await;
+
+                (label, result)
+            }
+        });
+
+        futures::future::join_all(runs).await
+    }
+
     /// Adopt a document from a peer: fetch, write locally with federation frontmatter.
     pub async fn adopt_document(
         &self,
@@ -121,19 +279,24 @@ impl SyncService {
         source_path: &str,
         target_path: Option<&str>,
     ) -> Result<(String, String), String> {
+        // The target comes from the request, so keep it inside the vault before fetching
+        let local_path = target_path.unwrap_or(source_path);
+        let full_local_path = resolve_safe_path(&self.org_root, local_path).ok_or_else(|| {
+            warn!("Adoption rejected - path outside the vault: {}", local_path);
+            format!("Forbidden: {} is outside the vault", local_path)
+        })?;
+
+        // `adopt=true` lets the origin record us as an adopter (required to push edits back)
         let url = format!(
-            "{}://{}:{}/api/federation/files/{}",
+            "{}://{}:{}/api/federation/files/{}?adopt=true",
             peer_protocol, peer_host, peer_port, source_path
         );
 
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .danger_accept_invalid_certs(true)
-            .build()
-            .map_err(|e| format!("HTTP client error: {}", e))?;
-
-        let resp = client
-            .get(&url)
+        let self_id = self.peer_registry.get_self().await.instance_id;
+        let token = self.peer_registry.auth_token_for(peer_host, peer_port).await;
+        let resp = with_peer_auth(self.peer_registry.client().get(&url), token.as_deref())
+            .timeout(TRANSFER_TIMEOUT)
+            .header(INSTANCE_HEADER, self_id)
             .send()
             .await
             .map_err(|e| format!("Failed to fetch from peer: {}", e))?;
@@ -154,9 +317,7 @@ impl SyncService {
             .as_str()
             .unwrap_or("")
             .to_string();
-
-        let local_path = target_path.unwrap_or(source_path);
-        let full_local_path = self.org_root.join(local_path);
+        let rev = peer_doc["rev"].as_u64().unwrap_or(0).to_string();
 
         // Ensure directory exists
         if let Some(dir) = full_local_path.parent() {
@@ -172,60 +333,41 @@ impl SyncService {
             checksum.clone()
         };
 
-        // Extract original frontmatter fields from peer doc
-        let mut frontmatter_lines = vec!["---".to_string()];
-
-        // Copy type, tags from peer doc frontmatter if present
-        if let Some(fm) = peer_doc.get("frontmatter") {
-            if let Some(t) = fm.get("type").and_then(|v| v.as_str()) {
-                frontmatter_lines.push(format!("type: {}", t));
-            }
-            if let Some(status) = fm.get("status").and_then(|v| v.as_str()) {
-                frontmatter_lines.push(format!("status: {}", status));
-            }
-            if let Some(created) = fm.get("created").and_then(|v| v.as_str()) {
-                frontmatter_lines.push(format!("created: {}", created));
-            }
-            if let Some(tags) = fm.get("tags").and_then(|v| v.as_array()) {
-                let tag_strs: Vec<String> = tags
-                    .iter()
-                    .filter_map(|t| t.as_str().map(String::from))
-                    .collect();
-                if tag_strs.is_empty() {
-                    frontmatter_lines.push("tags: []".to_string());
-                } else {
-                    frontmatter_lines.push(format!("tags: [{}]", tag_strs.join(", ")));
-                }
-            }
-        }
-
-        // Add federation block
-        frontmatter_lines.push("federation:".to_string());
-        frontmatter_lines.push(format!("  origin-peer: '{}'", peer_id));
-        frontmatter_lines.push(format!("  origin-name: '{}'", peer_name));
-        frontmatter_lines.push(format!("  origin-host: '{}:{}'", peer_host, peer_port));
-        frontmatter_lines.push(format!("  origin-path: '{}'", source_path));
-        frontmatter_lines.push(format!("  adopted-at: '{}'", now));
-        frontmatter_lines.push(format!("  origin-checksum: '{}'", computed_checksum));
-        frontmatter_lines.push(format!("  local-checksum: '{}'", computed_checksum));
-        frontmatter_lines.push("  sync-status: 'synced'".to_string());
-        frontmatter_lines.push(format!("  last-sync-check: '{}'", now));
-        frontmatter_lines.push("---".to_string());
+        // Keep the origin's frontmatter (all fields), replacing any federation block it had
+        let origin_host = format!("{}:{}", peer_host, peer_port);
+        let frontmatter = adopted_frontmatter(
+            peer_doc.get("frontmatter").and_then(|v| v.as_object()),
+            &[
+                ("origin-peer", peer_id),
+                ("origin-name", peer_name),
+                ("origin-host", &origin_host),
+                ("origin-path", source_path),
+                ("adopted-at", &now),
+                ("origin-checksum", &computed_checksum),
+                ("local-checksum", &computed_checksum),
+                ("sync-status", "synced"),
+                ("last-sync-check", &now),
+                ("rev", &rev),
+                ("origin-rev", &rev),
+            ],
+        );
 
-        let full_content = format!("{}\n{}", frontmatter_lines.join("\n"), content);
+        let full_content = format!("{}\n{}", frontmatter, content);
 
         std::fs::write(&full_local_path, &full_content)
             .map_err(|e| format!("Failed to write file: {}", e))?;
 
-        log_to_file(&format!(
-            "Adopted document: {} → {} (from {})",
-            source_path, local_path, peer_name
-        ));
+        // Keep the adopted body as the common ancestor for later 3-way diffs
+        self.save_base_snapshot(peer_id, source_path, content);
+
+        info!("Adopted document: {} → {} (from {})", source_path, local_path, peer_name);
 
         Ok((local_path.to_string(), computed_checksum))
     }
 
-    /// Write an incoming document (sent by a peer) to the inbox.
+    /// Write an incoming document (sent by a peer) to the inbox. Unless `force` is set, a
+    /// resend of content already in the inbox (same sender, original path and checksum) is
+    /// not written again. Returns the inbox path and whether a new file was written.
     pub fn write_incoming_document(
         &self,
         from_instance_id: &str,
@@ -236,7 +378,18 @@ impl SyncService {
         tags: &[String],
         source_path: &str,
         message: Option<&str>,
-    ) -> Result<String, String> {
+        extra_frontmatter: Option<&serde_json::Map<String, serde_json::Value>>,
+        force: bool,
+    ) -> Result<(String, bool), String> {
+        let checksum = compute_checksum(content);
+        if !force {
+            let duplicate = self.find_inbox_duplicate(from_instance_id, source_path, content);
+            if let Some(existing) = duplicate {
+                warn!("Duplicate document from {} ignored: {}", from_display_name, existing);
+                return Ok((existing, false));
+            }
+        }
+
         let timestamp = chrono::Utc::now()
             .format("%Y-%m-%dT%H-%M-%S")
             .to_string();
@@ -274,16 +427,30 @@ impl SyncService {
             )
         };
 
-        let frontmatter = format!(
-            "---\ntype: inbox\ncreated: '{}'\nsource: peer\nfrom-name: {}\nfrom-instance: {}\nfrom-host: {}\noriginal-path: {}\ntags: {}\n---",
+        let mut frontmatter = format!(
+            "---\ntype: inbox\ncreated: '{}'\nsource: peer\nfrom-name: {}\nfrom-instance: {}\nfrom-host: {}\noriginal-path: {}\ncontent-checksum: {}\ntags: {}",
             chrono::Utc::now().format("%Y-%m-%d"),
             from_display_name,
             from_instance_id,
             from_host,
             source_path,
+            checksum,
             tags_str,
         );
 
+        // Sender's frontmatter, minus the keys the inbox document sets itself
+        if let Some(extra) = extra_frontmatter {
+            for (key, value) in extra {
+                let is_plain_key = !key.is_empty()
+                    && key.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+                if !is_plain_key || INBOX_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                frontmatter.push_str(&format!("\n{}: {}", key, yaml_value(value)));
+            }
+        }
+        frontmatter.push_str("\n---");
+
         let mut body = format!("# {}\n\n", title);
         if let Some(msg) = message {
             body.push_str(&format!(
@@ -300,12 +467,58 @@ impl SyncService {
         std::fs::write(&inbox_path, &full)
             .map_err(|e| format!("Failed to write inbox: {}", e))?;
 
-        log_to_file(&format!(
-            "Received document from {}: {}",
-            from_display_name, filename
-        ));
+        info!("Received document from {}: {}", from_display_name, filename);
 
-        Ok(format!("inbox/{}", filename))
+        Ok((format!("inbox/{}", filename), true))
+    }
+
+    /// Inbox document already holding `content` from this sender and original path
+    fn find_inbox_duplicate(
+        &self,
+        from_instance_id: &str,
+        source_path: &str,
+        content: &str,
+    ) -> Option<String> {
+        let entries = std::fs::read_dir(self.org_root.join("inbox")).ok()?;
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.extension().map(|e| e == "md").unwrap_or(false) {
+                continue;
+            }
+            let existing = match std::fs::read_to_string(&path) {
+                Ok(existing) => existing,
+                Err(_) => continue,
+            };
+            let fm = match extract_frontmatter(&existing) {
+                Some(fm) => fm,
+                None => continue,
+            };
+
+            let mut instance = None;
+            let mut original = None;
+            let mut checksum = None;
+            for line in fm.lines().filter(|l| !l.starts_with(' ')) {
+                match parse_yaml_field(line) {
+                    Some((key, value)) if key == "from-instance" => instance = Some(value),
+                    Some((key, value)) if key == "original-path" => original = Some(value),
+                    Some((key, value)) if key == "content-checksum" => checksum = Some(value),
+                    _ => {}
+                }
+            }
+
+            let is_duplicate = instance.as_deref() == Some(from_instance_id)
+                && original.as_deref() == Some(source_path)
+                && checksum
+                    .as_deref()
+                    .map(|c| c == compute_checksum_like(c, content))
+                    .unwrap_or(false);
+            if is_duplicate {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                return Some(format!("inbox/{}", name));
+            }
+        }
+        None
     }
 
     /// Get all adopted (shared) documents by scanning files for federation frontmatter.
@@ -334,6 +547,13 @@ impl SyncService {
         shared
     }
 
+    /// Get federation metadata for a single document, or None if it isn't federated.
+    pub async fn get_federation_meta(&self, path: &str) -> Option<FederationMeta> {
+        let full_path = self.org_root.join(path);
+        let content = tokio::fs::read_to_string(&full_path).await.ok()?;
+        extract_federation_meta(&content)
+    }
+
     /// Handle a local file change — check if it's a federation doc and update sync status.
     pub async fn handle_local_change(&self, path: &str) {
         let full_path = self.org_root.join(path);
@@ -353,7 +573,7 @@ impl SyncService {
 
         // Extract body content (after frontmatter)
         let body = extract_body(&content);
-        let current_checksum = compute_checksum(&body);
+        let current_checksum = compute_checksum_like(&fed.local_checksum, &body);
 
         if current_checksum != fed.local_checksum {
             let old_status = fed.sync_status.clone();
@@ -364,11 +584,14 @@ impl SyncService {
             };
 
             if old_status != new_status {
+                // First local edit since the last sync moves us one rev past the origin
+                let rev = fed.rev.max(fed.origin_rev + 1).to_string();
                 self.update_federation_field(
                     path,
                     &[
                         ("local-checksum", &current_checksum),
                         ("sync-status", new_status),
+                        ("rev", &rev),
                     ],
                 );
 
@@ -385,12 +608,166 @@ impl SyncService {
         }
     }
 
+    /// Push a `local-modified` adopted document's body back to its origin peer. The origin
+    /// only accepts it if it still has the body we last synced (`origin-checksum`). On
+    /// success both checksums are updated and the document is `synced` again.
+    /// Returns the origin's new checksum.
+    pub async fn push_to_origin(&self, local_path: &str) -> Result<String, String> {
+        let full_path = self.org_root.join(local_path);
+        let content = std::fs::read_to_string(&full_path)
+            .map_err(|e| format!("Failed to read {}: {}", local_path, e))?;
+        let fed = extract_federation_meta(&content).ok_or("Not a federated document")?;
+
+        if fed.sync_status != "local-modified" {
+            return Err(format!("Sync status is '{}', not 'local-modified'", fed.sync_status));
+        }
+
+        let (host, port) = split_host_port(&fed.origin_host)
+            .ok_or_else(|| format!("Invalid origin-host '{}'", fed.origin_host))?;
+
+        let peers = self.peer_registry.get_peer_status().await;
+        let peer = peers
+            .iter()
+            .find(|p| p.host == host && p.port == port && p.status == "online")
+            .ok_or("Origin peer is offline")?;
+
+        let url = format!(
+            "{}://{}:{}/api/federation/files/{}",
+            peer.protocol, peer.host, peer.port, fed.origin_path
+        );
+
+        let body = extract_body(&content);
+        let payload = serde_json::json!({
+            "content": body,
+            "baseChecksum": fed.origin_checksum,
+        });
+
+        let self_id = self.peer_registry.get_self().await.instance_id;
+        let token = self.peer_registry.auth_token_for(&peer.host, peer.port).await;
+        let resp = with_peer_auth(self.peer_registry.client().put(&url), token.as_deref())
+            .timeout(TRANSFER_TIMEOUT)
+            .header(INSTANCE_HEADER, self_id)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to push to peer: {}", e))?;
+
+        if !resp.status().is_success() {
+            return Err(format!("Peer returned {}", resp.status()));
+        }
+
+        let data: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse peer response: {}", e))?;
+        let origin_checksum = data["checksum"].as_str().unwrap_or("").to_string();
+        let rev = data["rev"].as_u64().unwrap_or(fed.rev).to_string();
+
+        // What we pushed is now the common ancestor
+        self.save_base_snapshot(&fed.origin_peer, &fed.origin_path, &body);
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let local_checksum = compute_checksum(&body);
+        self.update_federation_field(
+            local_path,
+            &[
+                ("origin-checksum", &origin_checksum),
+                ("local-checksum", &local_checksum),
+                ("sync-status", "synced"),
+                ("last-sync-check", &now),
+                ("rev", &rev),
+                ("origin-rev", &rev),
+            ],
+        );
+
+        self.emit_status_change(SyncStatusEvent {
+            event_type: "sync-status-changed".to_string(),
+            path: local_path.to_string(),
+            old_status: fed.sync_status.clone(),
+            new_status: "synced".to_string(),
+            peer: Some(fed.origin_name.clone()),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+        .await;
+
+        info!("Sync: pushed {} to {} ({})", local_path, fed.origin_name, fed.origin_path);
+
+        Ok(origin_checksum)
+    }
+
+    /// Remember that the configured peer `peer` adopted our document at `path`.
+    pub fn record_adoption(&self, path: &str, peer: &str) {
+        let _guard = self.origin_files_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut adoptions = self.load_adoptions();
+        let adopters = adoptions.entry(path.to_string()).or_default();
+        if adopters.iter().any(|id| id == peer) {
+            return;
+        }
+        adopters.push(peer.to_string());
+
+        let file = self.org_root.join(ADOPTIONS_FILE);
+        if let Some(dir) = file.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let json = serde_json::to_string_pretty(&adoptions).unwrap_or_default();
+        if let Err(e) = std::fs::write(&file, json) {
+            error!("Failed to record adoption of {}: {}", path, e);
+        }
+    }
+
+    /// Whether a peer known by any of `identities` has adopted our document at `path`.
+    pub fn is_adopted_by(&self, path: &str, identities: &[String]) -> bool {
+        let _guard = self.origin_files_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.load_adoptions()
+            .get(path)
+            .map(|adopters| adopters.iter().any(|id| identities.contains(id)))
+            .unwrap_or(false)
+    }
+
+    fn load_adoptions(&self) -> HashMap<String, Vec<String>> {
+        std::fs::read_to_string(self.org_root.join(ADOPTIONS_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Current revision of our shared document at `path` with the given body. The counter
+    /// starts at 1 and is bumped whenever the body differs from the one last seen.
+    pub fn document_rev(&self, path: &str, body: &str) -> u64 {
+        let _guard = self.origin_files_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let file = self.org_root.join(REVS_FILE);
+        let mut revs: HashMap<String, DocumentRev> = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let checksum = compute_checksum(body);
+        let entry = revs.entry(path.to_string()).or_default();
+        if entry.checksum == checksum {
+            return entry.rev;
+        }
+        entry.rev += 1;
+        entry.checksum = checksum;
+        let rev = entry.rev;
+
+        if let Some(dir) = file.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let json = serde_json::to_string_pretty(&revs).unwrap_or_default();
+        if let Err(e) = std::fs::write(&file, json) {
+            error!("Failed to record rev of {}: {}", path, e);
+        }
+        rev
+    }
+
     /// Start periodic origin-checksum polling.
     pub fn start_sync_polling(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
         let service = Arc::clone(self);
         tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(std::time::Duration::from_secs(SYNC_POLL_INTERVAL_SECS));
+            let self_info = service.peer_registry.get_self().await;
+            let period = self_info.sync_interval(SYNC_POLL_INTERVAL_SECS);
+            info!("Checking origins every {}s", period.as_secs());
+            let mut interval = tokio::time::interval(period);
             loop {
                 interval.tick().await;
                 service.check_all_origins().await;
@@ -419,9 +796,9 @@ impl SyncService {
 
         // Find peer
         let peers = self.peer_registry.get_peer_status().await;
-        let parts: Vec<&str> = origin_host.split(':').collect();
-        let host = parts[0];
-        let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+        let Some((host, port)) = split_host_port(origin_host) else {
+            return;
+        };
 
         let peer = peers
             .iter()
@@ -437,31 +814,66 @@ impl SyncService {
             peer.protocol, peer.host, peer.port, origin_path
         );
 
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(5))
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap_or_default();
-
-        match client.get(&url).send().await {
+        let client = self.peer_registry.client();
+        let self_id = self.peer_registry.get_self().await.instance_id;
+        let token = self.peer_registry.auth_token_for(&peer.host, peer.port).await;
+        match with_peer_auth(client.get(&url), token.as_deref())
+            .header(INSTANCE_HEADER, self_id)
+            .send()
+            .await
+        {
             Ok(resp) if resp.status().is_success() => {
                 if let Ok(data) = resp.json::<serde_json::Value>().await {
                     let remote_checksum = data["checksum"].as_str().unwrap_or("");
+                    let comparable = checksums_comparable(remote_checksum, &fed.origin_checksum);
+                    // Documents adopted before revs existed (origin-rev 0) compare checksums only
+                    let remote_rev = data["rev"].as_u64().filter(|_| fed.origin_rev > 0);
+
+                    // Version vector: each side is ahead once its rev passes the last synced
+                    // origin-rev. Equal revs with different content means both sides claim the
+                    // same version — a true conflict.
+                    let (origin_ahead, diverged) = match remote_rev {
+                        Some(rev) => (
+                            rev > fed.origin_rev,
+                            rev == fed.origin_rev
+                                && comparable
+                                && remote_checksum != fed.origin_checksum,
+                        ),
+                        None if !comparable => {
+                            // Different algorithms on each side — can't tell whether it changed
+                            warn!(
+                                "Sync: {} cannot compare checksums ({} vs {})",
+                                local_path,
+                                checksum_prefix(remote_checksum).unwrap_or("?"),
+                                checksum_prefix(&fed.origin_checksum).unwrap_or("?")
+                            );
+                            (false, false)
+                        }
+                        None => (remote_checksum != fed.origin_checksum, false),
+                    };
+                    let local_ahead = fed.rev > fed.origin_rev || fed.sync_status == "local-modified";
 
-                    if remote_checksum != fed.origin_checksum {
+                    if origin_ahead || diverged {
                         let old_status = fed.sync_status.clone();
-                        let new_status = if old_status == "local-modified" {
+                        let new_status = if local_ahead || diverged {
                             "conflict"
                         } else {
                             "origin-modified"
                         };
 
+                        // Both sides changed — try a line-based 3-way merge before flagging conflict
+                        if new_status == "conflict" && self.try_auto_merge(local_path, fed).await {
+                            return;
+                        }
+
                         if old_status != new_status {
                             let now = chrono::Utc::now().to_rfc3339();
+                            let origin_rev = remote_rev.unwrap_or(fed.origin_rev).to_string();
                             self.update_federation_field(
                                 local_path,
                                 &[
                                     ("origin-checksum", remote_checksum),
+                                    ("origin-rev", &origin_rev),
                                     ("sync-status", new_status),
                                     ("last-sync-check", &now),
                                 ],
@@ -477,18 +889,30 @@ impl SyncService {
                             })
                             .await;
 
-                            log_to_file(&format!(
-                                "Sync: {} → {} (origin changed)",
-                                local_path, new_status
-                            ));
+                            info!("Sync: {} → {} (origin changed)", local_path, new_status);
                         }
                     } else {
-                        // Just update last-sync-check
                         let now = chrono::Utc::now().to_rfc3339();
-                        self.update_federation_field(
-                            local_path,
-                            &[("last-sync-check", &now)],
-                        );
+                        match data["rev"].as_u64() {
+                            // Pre-rev document still in sync: start tracking the origin's rev
+                            Some(rev)
+                                if fed.origin_rev == 0 && fed.sync_status == "synced" && comparable =>
+                            {
+                                let rev = rev.to_string();
+                                self.update_federation_field(
+                                    local_path,
+                                    &[
+                                        ("rev", &rev),
+                                        ("origin-rev", &rev),
+                                        ("last-sync-check", &now),
+                                    ],
+                                );
+                            }
+                            _ => self.update_federation_field(
+                                local_path,
+                                &[("last-sync-check", &now)],
+                            ),
+                        }
                     }
                 }
             }
@@ -498,6 +922,64 @@ impl SyncService {
         }
     }
 
+    /// Attempt a 3-way merge of local and origin edits against the base snapshot.
+    /// On a clean merge, writes the result, marks the document synced and emits an
+    /// `auto-merged` event. Returns false (leaving status untouched) if there is no
+    /// base snapshot or the edits overlap.
+    async fn try_auto_merge(&self, local_path: &str, fed: &FederationMeta) -> bool {
+        let diff = match self.get_conflict_diff(local_path).await {
+            Some(d) if !d.base_content.is_empty() => d,
+            _ => return false,
+        };
+
+        let merged = match diffy::merge(&diff.base_content, &diff.local_content, &diff.origin_content) {
+            Ok(m) => m,
+            Err(_) => return false, // overlapping hunks
+        };
+
+        let full_path = self.org_root.join(local_path);
+        let content = match std::fs::read_to_string(&full_path) {
+            Ok(c) => c,
+            Err(_) => return false,
+        };
+        let fm_end = find_frontmatter_end(&content);
+        let new_file = format!("{}\n{}", &content[..fm_end], merged);
+        if std::fs::write(&full_path, &new_file).is_err() {
+            return false;
+        }
+
+        // Origin's current body is the new common ancestor
+        self.save_base_snapshot(&fed.origin_peer, &fed.origin_path, &diff.origin_content);
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let merged_checksum = compute_checksum(&merged);
+        let rev = diff.origin_rev.to_string();
+        self.update_federation_field(
+            local_path,
+            &[
+                ("local-checksum", &merged_checksum),
+                ("origin-checksum", &diff.origin_checksum),
+                ("sync-status", "synced"),
+                ("last-sync-check", &now),
+                ("rev", &rev),
+                ("origin-rev", &rev),
+            ],
+        );
+
+        self.emit_status_change(SyncStatusEvent {
+            event_type: "auto-merged".to_string(),
+            path: local_path.to_string(),
+            old_status: fed.sync_status.clone(),
+            new_status: "synced".to_string(),
+            peer: Some(fed.origin_name.clone()),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+        .await;
+
+        info!("Sync: {} auto-merged with origin", local_path);
+        true
+    }
+
     /// Get 3-way diff for conflict resolution.
     pub async fn get_conflict_diff(&self, local_path: &str) -> Option<ConflictDiff> {
         let full_path = self.org_root.join(local_path);
@@ -507,9 +989,7 @@ impl SyncService {
         let origin_host = &fed.origin_host;
         let origin_path = &fed.origin_path;
 
-        let parts: Vec<&str> = origin_host.split(':').collect();
-        let host = parts[0];
-        let port: u16 = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
+        let (host, port) = split_host_port(origin_host)?;
 
         let peers = self.peer_registry.get_peer_status().await;
         let peer = peers
@@ -521,13 +1001,15 @@ impl SyncService {
             peer.protocol, peer.host, peer.port, origin_path
         );
 
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .danger_accept_invalid_certs(true)
-            .build()
+        let client = self.peer_registry.client();
+        let self_id = self.peer_registry.get_self().await.instance_id;
+        let token = self.peer_registry.auth_token_for(&peer.host, peer.port).await;
+        let resp = with_peer_auth(client.get(&url), token.as_deref())
+            .timeout(TRANSFER_TIMEOUT)
+            .header(INSTANCE_HEADER, self_id)
+            .send()
+            .await
             .ok()?;
-
-        let resp = client.get(&url).send().await.ok()?;
         if !resp.status().is_success() {
             return None;
         }
@@ -538,16 +1020,23 @@ impl SyncService {
             .as_str()
             .unwrap_or("")
             .to_string();
+        let origin_rev = origin_doc["rev"].as_u64().unwrap_or(fed.origin_rev);
 
         let local_body = extract_body(&content);
         let local_checksum = compute_checksum(&local_body);
 
+        // Documents adopted before base snapshots existed fall back to an empty base
+        let base_content = self
+            .load_base_snapshot(&fed.origin_peer, origin_path)
+            .unwrap_or_default();
+
         Some(ConflictDiff {
             local_content: local_body,
             origin_content: origin_content.to_string(),
-            base_content: String::new(),
+            base_content,
             local_checksum,
             origin_checksum,
+            origin_rev,
         })
     }
 
@@ -583,7 +1072,9 @@ impl SyncService {
                 let fm_end = find_frontmatter_end(&content);
                 let new_file = format!("{}\n{}", &content[..fm_end], diff.origin_content);
                 let _ = std::fs::write(&full_path, &new_file);
+                self.save_base_snapshot(&fed.origin_peer, &fed.origin_path, &diff.origin_content);
 
+                let rev = diff.origin_rev.to_string();
                 self.update_federation_field(
                     local_path,
                     &[
@@ -591,13 +1082,21 @@ impl SyncService {
                         ("origin-checksum", &diff.origin_checksum),
                         ("sync-status", "synced"),
                         ("last-sync-check", &now),
+                        ("rev", &rev),
+                        ("origin-rev", &rev),
                     ],
                 );
             }
             "keep-local" => {
+                // origin-rev already holds the origin version we flagged; we're caught up to it
+                let rev = fed.origin_rev.to_string();
                 self.update_federation_field(
                     local_path,
-                    &[("sync-status", "synced"), ("last-sync-check", &now)],
+                    &[
+                        ("sync-status", "synced"),
+                        ("last-sync-check", &now),
+                        ("rev", &rev),
+                    ],
                 );
             }
             "merge" => {
@@ -611,12 +1110,14 @@ impl SyncService {
                 let _ = std::fs::write(&full_path, &new_file);
 
                 let new_checksum = compute_checksum(merged);
+                let rev = fed.origin_rev.to_string();
                 self.update_federation_field(
                     local_path,
                     &[
                         ("local-checksum", &new_checksum),
                         ("sync-status", "synced"),
                         ("last-sync-check", &now),
+                        ("rev", &rev),
                     ],
                 );
             }
@@ -625,16 +1126,13 @@ impl SyncService {
 
                 // Send rejection comment back to origin
                 if let Some(cmt) = comment {
-                    let origin_host = &fed.origin_host;
-                    let parts: Vec<&str> = origin_host.split(':').collect();
-                    let host = parts[0];
-                    let port: u16 =
-                        parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(3847);
-
                     let peers = self.peer_registry.get_peer_status().await;
-                    if let Some(peer) = peers.iter().find(|p| {
-                        p.host == host && p.port == port && p.status == "online"
-                    }) {
+                    let origin = split_host_port(&fed.origin_host).and_then(|(host, port)| {
+                        peers
+                            .iter()
+                            .find(|p| p.host == host && p.port == port && p.status == "online")
+                    });
+                    if let Some(peer) = origin {
                         let self_info = self.peer_registry.get_self().await;
                         let local_host = self.local_host.read().await;
                         let host_str = local_host
@@ -658,14 +1156,10 @@ impl SyncService {
                             "comment": cmt,
                         });
 
-                        let client = reqwest::Client::builder()
-                            .timeout(std::time::Duration::from_secs(5))
-                            .danger_accept_invalid_certs(true)
-                            .build()
-                            .unwrap_or_default();
-
-                        let _ = client
-                            .post(&url)
+                        let client = self.peer_registry.client();
+                        let token =
+                            self.peer_registry.auth_token_for(&peer.host, peer.port).await;
+                        let _ = with_peer_auth(client.post(&url), token.as_deref())
                             .json(&body)
                             .send()
                             .await;
@@ -678,6 +1172,66 @@ impl SyncService {
         true
     }
 
+    /// Stop tracking an adopted document: drop its `federation:` block and base snapshot,
+    /// leaving an ordinary note. Returns the metadata it had, or None if it wasn't federated.
+    pub async fn unadopt(&self, local_path: &str) -> Result<Option<FederationMeta>, String> {
+        let full_path = self.org_root.join(local_path);
+        let content = std::fs::read_to_string(&full_path)
+            .map_err(|e| format!("Failed to read {}: {}", local_path, e))?;
+        let fed = match extract_federation_meta(&content) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+
+        std::fs::write(&full_path, strip_federation_block(&content))
+            .map_err(|e| format!("Failed to write {}: {}", local_path, e))?;
+
+        let snapshot = self.base_snapshot_path(&fed.origin_peer, &fed.origin_path);
+        if snapshot.exists() {
+            if let Err(e) = std::fs::remove_file(&snapshot) {
+                error!("Failed to remove base snapshot for {}: {}", local_path, e);
+            }
+        }
+
+        self.emit_status_change(SyncStatusEvent {
+            event_type: "unadopted".to_string(),
+            path: local_path.to_string(),
+            old_status: fed.sync_status.clone(),
+            new_status: "untracked".to_string(),
+            peer: Some(fed.origin_name.clone()),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+        .await;
+
+        info!("Unadopted {} (was {} from {})", local_path, fed.origin_path, fed.origin_name);
+        Ok(Some(fed))
+    }
+
+    /// Path of the base snapshot for an origin document, keyed by origin peer + path
+    /// so it survives local renames.
+    fn base_snapshot_path(&self, origin_peer: &str, origin_path: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}:{}", origin_peer, origin_path).as_bytes());
+        self.org_root
+            .join(BASE_SNAPSHOT_DIR)
+            .join(format!("{:x}.md", hasher.finalize()))
+    }
+
+    /// Store the body both sides last agreed on (used as the 3-way merge base).
+    fn save_base_snapshot(&self, origin_peer: &str, origin_path: &str, body: &str) {
+        let path = self.base_snapshot_path(origin_peer, origin_path);
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&path, body) {
+            error!("Failed to write base snapshot for {}: {}", origin_path, e);
+        }
+    }
+
+    fn load_base_snapshot(&self, origin_peer: &str, origin_path: &str) -> Option<String> {
+        std::fs::read_to_string(self.base_snapshot_path(origin_peer, origin_path)).ok()
+    }
+
     /// Update specific federation fields in a document's frontmatter.
     fn update_federation_field(&self, local_path: &str, updates: &[(&str, &str)]) {
         let full_path = self.org_root.join(local_path);
@@ -690,17 +1244,19 @@ impl SyncService {
             Err(_) => return,
         };
 
-        let mut result = content;
-        for (key, value) in updates {
-            // Simple regex replace in federation YAML block
-            let pattern = format!(r"({}:)\s*'[^']*'", regex::escape(key));
-            if let Ok(re) = regex::Regex::new(&pattern) {
-                let replacement = format!("${{1}} '{}'", value.replace('\'', "''"));
-                result = re.replace_all(&result, replacement.as_str()).to_string();
+        match set_federation_fields(&content, updates) {
+            Some(result) => {
+                let _ = std::fs::write(&full_path, &result);
             }
+            None => warn!("Sync: no federation block in {}, skipping field update", local_path),
         }
+    }
 
-        let _ = std::fs::write(&full_path, &result);
+    async fn emit_batch_progress(&self, event: BatchProgressEvent) {
+        let cb = self.on_batch_progress.read().await;
+        if let Some(callback) = cb.as_ref() {
+            callback(event);
+        }
     }
 
     async fn emit_status_change(&self, event: SyncStatusEvent) {
@@ -713,11 +1269,49 @@ impl SyncService {
 
 // --- Utility functions ---
 
+/// Set the process-wide checksum algorithm. Only the first call takes effect;
+/// called once at startup from the resolved `ServerConfig`.
+pub fn set_checksum_algorithm(algorithm: ChecksumAlgorithm) {
+    let _ = CHECKSUM_ALGORITHM.set(algorithm);
+}
+
+/// Configured checksum algorithm (default SHA-256).
+pub fn checksum_algorithm() -> ChecksumAlgorithm {
+    CHECKSUM_ALGORITHM
+        .get()
+        .copied()
+        .unwrap_or(ChecksumAlgorithm::Sha256)
+}
+
 pub fn compute_checksum(content: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    let result = hasher.finalize();
-    format!("sha256:{:x}", result)
+    compute_checksum_with(checksum_algorithm(), content)
+}
+
+pub fn compute_checksum_with(algorithm: ChecksumAlgorithm, content: &str) -> String {
+    format!("{}:{}", algorithm.prefix(), algorithm.digest(content))
+}
+
+/// Compute a checksum using the same algorithm as `reference` (by its prefix), so a
+/// stored checksum can be re-verified even if the configured algorithm has changed.
+pub fn compute_checksum_like(reference: &str, content: &str) -> String {
+    let algorithm = checksum_prefix(reference)
+        .and_then(ChecksumAlgorithm::from_name)
+        .unwrap_or_else(checksum_algorithm);
+    compute_checksum_with(algorithm, content)
+}
+
+/// Algorithm prefix of a checksum ("sha256" for "sha256:abcd..."), if any.
+pub fn checksum_prefix(checksum: &str) -> Option<&str> {
+    checksum.split_once(':').map(|(prefix, _)| prefix)
+}
+
+/// Two checksums can only be compared if they were produced by the same algorithm.
+/// Unprefixed (legacy/empty) checksums are treated as comparable.
+pub fn checksums_comparable(a: &str, b: &str) -> bool {
+    match (checksum_prefix(a), checksum_prefix(b)) {
+        (Some(pa), Some(pb)) => pa == pb,
+        _ => true,
+    }
 }
 
 /// Extract federation metadata from raw file content by parsing the YAML block.
@@ -740,6 +1334,8 @@ pub fn extract_federation_meta(content: &str) -> Option<FederationMeta> {
     let mut local_checksum = String::new();
     let mut sync_status = String::new();
     let mut last_sync_check = String::new();
+    let mut rev = 0;
+    let mut origin_rev = 0;
 
     for line in fm.lines() {
         let trimmed = line.trim();
@@ -765,6 +1361,8 @@ pub fn extract_federation_meta(content: &str) -> Option<FederationMeta> {
                     "local-checksum" => local_checksum = value,
                     "sync-status" => sync_status = value,
                     "last-sync-check" => last_sync_check = value,
+                    "rev" => rev = value.parse().unwrap_or(0),
+                    "origin-rev" => origin_rev = value.parse().unwrap_or(0),
                     _ => {}
                 }
             }
@@ -785,21 +1383,151 @@ pub fn extract_federation_meta(content: &str) -> Option<FederationMeta> {
         local_checksum,
         sync_status,
         last_sync_check,
+        rev,
+        origin_rev,
     })
 }
 
-/// Extract frontmatter string from markdown content.
-fn extract_frontmatter(content: &str) -> Option<String> {
-    if !content.starts_with("---") {
-        return None;
+/// Remove the `federation:` block from a document's frontmatter, leaving the rest intact.
+/// If the federation block was the only frontmatter, the frontmatter is dropped entirely.
+pub fn strip_federation_block(content: &str) -> String {
+    let fm = match extract_frontmatter(content) {
+        Some(fm) => fm,
+        None => return content.to_string(),
+    };
+
+    let mut kept: Vec<&str> = Vec::new();
+    let mut in_fed = false;
+    for line in fm.lines() {
+        if line.trim() == "federation:" {
+            in_fed = true;
+            continue;
+        }
+        if in_fed {
+            // Still inside the block while lines are indented (or blank)
+            if line.starts_with(' ') || line.starts_with('\t') || line.trim().is_empty() {
+                continue;
+            }
+            in_fed = false;
+        }
+        kept.push(line);
     }
-    let rest = &content[3..];
-    let end = rest.find("---")?;
-    Some(rest[..end].to_string())
+
+    let body = extract_body(content);
+    if kept.iter().all(|l| l.trim().is_empty()) {
+        return body;
+    }
+
+    let kept: Vec<&str> = kept.into_iter().filter(|l| !l.trim().is_empty()).collect();
+    format!("---\n{}\n---\n{}", kept.join("\n"), body)
+}
+
+/// Set fields inside the frontmatter `federation:` block. The frontmatter is parsed as
+/// YAML, updated and serialized again, so fields are found whatever quoting style or
+/// nesting they were written in; the body is kept verbatim (line endings are normalized
+/// to LF). Returns `None` if there is no block or the frontmatter isn't valid YAML.
+pub fn set_federation_fields(content: &str, updates: &[(&str, &str)]) -> Option<String> {
+    let content = &*normalize_text(content);
+    let (inner, end) = frontmatter_bounds(content)?;
+    let mut frontmatter: serde_yaml::Mapping = serde_yaml::from_str(&content[inner]).ok()?;
+
+    let federation = frontmatter.get_mut("federation")?;
+    if federation.is_null() {
+        *federation = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    }
+    let federation = federation.as_mapping_mut()?;
+    for (key, value) in updates {
+        federation.insert((*key).into(), (*value).into());
+    }
+
+    let yaml = serde_yaml::to_string(&frontmatter).ok()?;
+    Some(format!("---\n{}---{}", yaml, &content[end..]))
+}
+
+/// Quote a scalar for the federation block. Values with line breaks or other control
+/// characters use double quotes with escapes so they stay on one line.
+fn yaml_quote(value: &str) -> String {
+    if value.chars().any(|c| c.is_control()) {
+        let mut out = String::with_capacity(value.len() + 2);
+        out.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+/// Render a JSON value as a one-line YAML value. Lists and maps use JSON flow syntax,
+/// which YAML accepts as-is.
+fn yaml_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => yaml_quote(s),
+        serde_json::Value::Null => "null".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Frontmatter for an adopted document: the origin's fields (values re-serialized with
+/// proper quoting) followed by a fresh `federation:` block. Keys that aren't plain
+/// identifiers are quoted too.
+fn adopted_frontmatter(
+    origin: Option<&serde_json::Map<String, serde_json::Value>>,
+    federation: &[(&str, &str)],
+) -> String {
+    let mut lines = vec!["---".to_string()];
+
+    for (key, value) in origin.into_iter().flatten() {
+        if key == "federation" {
+            continue;
+        }
+        let is_plain_key = !key.is_empty()
+            && key.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        let key = if is_plain_key {
+            key.clone()
+        } else {
+            yaml_quote(key)
+        };
+        lines.push(format!("{}: {}", key, yaml_value(value)));
+    }
+
+    lines.push("federation:".to_string());
+    for (key, value) in federation {
+        lines.push(format!("  {}: {}", key, yaml_quote(value)));
+    }
+    lines.push("---".to_string());
+    lines.join("\n")
+}
+
+/// Replace everything after the frontmatter with `body`, keeping the frontmatter verbatim.
+pub fn replace_body(content: &str, body: &str) -> String {
+    let fm_end = find_frontmatter_end(content);
+    if fm_end == 0 {
+        return body.to_string();
+    }
+    format!("{}\n{}", &content[..fm_end], body)
+}
+
+/// Extract frontmatter string (the text between the fences) from markdown content.
+pub fn extract_frontmatter(content: &str) -> Option<String> {
+    let content = &*normalize_text(content);
+    let (inner, _) = frontmatter_bounds(content)?;
+    Some(content[inner].to_string())
 }
 
 /// Extract body content (after frontmatter) from markdown.
-fn extract_body(content: &str) -> String {
+pub fn extract_body(content: &str) -> String {
+    let content = &*normalize_text(content);
     let end = find_frontmatter_end(content);
     let body = &content[end..];
     // Trim leading newline
@@ -810,16 +1538,10 @@ fn extract_body(content: &str) -> String {
     }
 }
 
-/// Find the byte offset of the end of frontmatter (after closing ---).
+/// Find the byte offset of the end of frontmatter (after closing ---), or 0 if there is
+/// none. The offset is into `content` as given, so a leading BOM is skipped, not stripped.
 fn find_frontmatter_end(content: &str) -> usize {
-    if !content.starts_with("---") {
-        return 0;
-    }
-    let rest = &content[3..];
-    match rest.find("---") {
-        Some(idx) => 3 + idx + 3, // skip opening "---" + content + closing "---"
-        None => 0,
-    }
+    frontmatter_bounds(content).map(|(_, end)| end).unwrap_or(0)
 }
 
 /// Parse a simple YAML field line like "  key: 'value'" or "  key: value"
@@ -828,14 +1550,172 @@ fn parse_yaml_field(line: &str) -> Option<(String, String)> {
     let key = line[..colon_idx].trim().to_string();
     let value = line[colon_idx + 1..].trim().to_string();
 
-    // Strip quotes
-    let value = if (value.starts_with('\'') && value.ends_with('\''))
-        || (value.starts_with('"') && value.ends_with('"'))
-    {
-        value[1..value.len() - 1].to_string()
+    // Strip quotes, undoing the escapes written by `yaml_quote`
+    let value = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        unescape_double_quoted(&value[1..value.len() - 1])
     } else {
         value
     };
 
     Some((key, value))
 }
+
+fn unescape_double_quoted(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    out.push(c);
+                }
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sync_service(org_root: &Path) -> SyncService {
+        let index = Arc::new(RwLock::new(DocumentIndex::new(org_root)));
+        let registry = Arc::new(PeerRegistry::new(org_root, org_root));
+        SyncService::new(org_root, index, registry, 1)
+    }
+
+    #[tokio::test]
+    async fn adoption_refuses_targets_outside_the_vault() {
+        let parent = tempfile::tempdir().unwrap();
+        let vault = parent.path().join("vault");
+        std::fs::create_dir(&vault).unwrap();
+        let sync = sync_service(&vault);
+
+        for target in [
+            "../../.bashrc",
+            "../escaped.md",
+            "/etc/passwd",
+            "notes/../../x.md",
+        ] {
+            let err = sync
+                .adopt_document("peer", "127.0.0.1", 9, "http", "Peer", "a.md", Some(target))
+                .await
+                .unwrap_err();
+            assert!(err.starts_with("Forbidden"), "{}: {}", target, err);
+        }
+        let err = sync
+            .adopt_document("peer", "127.0.0.1", 9, "http", "Peer", "../a.md", None)
+            .await
+            .unwrap_err();
+        assert!(err.starts_with("Forbidden"), "{}", err);
+        assert!(!parent.path().join("escaped.md").exists());
+    }
+
+    fn frontmatter(content: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(&extract_frontmatter(content).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn federation_fields_replace_quoted_values() {
+        let content = "---\ntitle: 'Note'\nfederation:\n  origin: \"peer-a\"\n  \
+                       status: 'synced'\n---\n# Body\n";
+        let updated = set_federation_fields(content, &[("status", "modified")]).unwrap();
+        let fm = frontmatter(&updated);
+        assert_eq!(fm["title"], "Note");
+        assert_eq!(fm["federation"]["origin"], "peer-a");
+        assert_eq!(fm["federation"]["status"], "modified");
+        assert_eq!(extract_body(&updated), "# Body\n");
+    }
+
+    #[test]
+    fn federation_fields_replace_unquoted_values_and_add_missing_ones() {
+        let content = "---\nfederation:\n    origin: peer-a\n    status: synced\n---\nBody";
+        let updated = set_federation_fields(
+            content,
+            &[("status", "conflict"), ("lastSync", "2026-01-02")],
+        )
+        .unwrap();
+        let fm = frontmatter(&updated);
+        assert_eq!(fm["federation"]["status"], "conflict");
+        assert_eq!(fm["federation"]["lastSync"], "2026-01-02");
+        assert_eq!(fm["federation"]["origin"], "peer-a");
+        assert!(updated.ends_with("---\nBody"));
+    }
+
+    #[test]
+    fn federation_fields_keep_nested_blocks() {
+        let content = "---\nmeta:\n  owner: alex\nfederation:\n  status: synced\n  \
+                       history:\n    - at: 2026-01-01\n      status: synced\n  \
+                       originHash: abc\ntags: [a, b]\n---\nBody\n";
+        let updated = set_federation_fields(content, &[("originHash", "def")]).unwrap();
+        let fm = frontmatter(&updated);
+        assert_eq!(fm["meta"]["owner"], "alex");
+        assert_eq!(fm["federation"]["history"][0]["status"], "synced");
+        assert_eq!(fm["federation"]["originHash"], "def");
+        assert_eq!(fm["tags"][1], "b");
+    }
+
+    #[test]
+    fn federation_fields_round_trip_awkward_values() {
+        let content = "---\nfederation:\n  status: synced\n---\n";
+        let value = "it's \"quoted\": with a colon\nand a line break";
+        let updated = set_federation_fields(content, &[("note", value)]).unwrap();
+        assert_eq!(frontmatter(&updated)["federation"]["note"], value);
+    }
+
+    #[test]
+    fn adopted_frontmatter_quotes_origin_fields() {
+        let origin = serde_json::json!({
+            "title": "Re: \"Launch\" plan: v2",
+            "summary": "it's done: mostly",
+            "odd: key": "value # not a comment",
+            "tags": ["a", "b: c"],
+            "priority": 2,
+            "federation": { "origin-peer": "someone-else" },
+        });
+        let written = adopted_frontmatter(
+            origin.as_object(),
+            &[
+                ("origin-host", "laptop.local:3847"),
+                ("origin-name", "Alex's \"Vault\""),
+            ],
+        );
+        let fm = frontmatter(&format!("{}\nBody", written));
+
+        assert_eq!(fm["title"], "Re: \"Launch\" plan: v2");
+        assert_eq!(fm["summary"], "it's done: mostly");
+        assert_eq!(fm["odd: key"], "value # not a comment");
+        assert_eq!(fm["tags"][1], "b: c");
+        assert_eq!(fm["priority"], 2);
+        assert_eq!(fm["federation"]["origin-host"], "laptop.local:3847");
+        assert_eq!(fm["federation"]["origin-name"], "Alex's \"Vault\"");
+        assert!(fm["federation"].get("origin-peer").is_none());
+    }
+
+    #[test]
+    fn adopted_frontmatter_without_origin_fields() {
+        let written = adopted_frontmatter(None, &[("sync-status", "synced")]);
+        let fm = frontmatter(&format!("{}\nBody", written));
+        assert_eq!(fm["federation"]["sync-status"], "synced");
+    }
+
+    #[test]
+    fn federation_fields_need_a_block() {
+        assert!(set_federation_fields("---\ntitle: x\n---\nBody", &[("status", "x")]).is_none());
+        assert!(set_federation_fields("No frontmatter", &[("status", "x")]).is_none());
+        assert!(set_federation_fields("---\nfederation: [\n---\n", &[("status", "x")]).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use axum_server::tls_rustls::RustlsConfig;
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+const CERT_FILE: &str = "selfsigned.crt";
+const KEY_FILE: &str = "selfsigned.key";
+
+/// Quiet period after a cert/key change before reloading: renewals write the two files
+/// separately, and the pair must match
+const CERT_RELOAD_DEBOUNCE_MS: u64 = 1000;
+
+/// Where the generated certificate lives: per machine rather than per vault, so peers that
+/// pinned it keep working whichever vault is open
+pub fn self_signed_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("vitrum")
+        .join("tls")
+}
+
+/// SHA-256 of a DER certificate in the colon-separated form `certFingerprint` accepts
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Names the certificate is issued for: localhost, the hostname (and its `.local` mDNS
+/// name) and every non-loopback interface address
+fn subject_alt_names() -> Vec<String> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Some(host) = hostname::get().ok().and_then(|h| h.into_string().ok()) {
+        if !host.is_empty() {
+            names.push(format!("{}.local", host.trim_end_matches(".local")));
+            names.push(host);
+        }
+    }
+    match if_addrs::get_if_addrs() {
+        Ok(addrs) => {
+            for addr in addrs.into_iter().filter(|a| !a.is_loopback()) {
+                names.push(addr.ip().to_string());
+            }
+        }
+        Err(e) => warn!("Could not list network interfaces for the TLS certificate: {}", e),
+    }
+    names.dedup();
+    names
+}
+
+/// Certificate and key PEM for `ORG_VIEWER_TLS_SELFSIGNED`, generated on first use and
+/// reused after that so the fingerprint stays stable. Delete the files in `dir` to issue a
+/// new one (e.g. after the machine's addresses change).
+pub fn load_or_create_self_signed(dir: &Path) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cert_path = dir.join(CERT_FILE);
+    let key_path = dir.join(KEY_FILE);
+
+    if let (Ok(cert), Ok(key)) = (std::fs::read(&cert_path), std::fs::read(&key_path)) {
+        info!("Using self-signed certificate {:?}", cert_path);
+        return Ok((cert, key));
+    }
+
+    let names = subject_alt_names();
+    let generated = rcgen::generate_simple_self_signed(names.clone())
+        .map_err(|e| format!("Failed to generate certificate: {}", e))?;
+    let cert = generated.cert.pem();
+    let key = generated.key_pair.serialize_pem();
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    std::fs::write(&cert_path, &cert)
+        .and_then(|_| std::fs::write(&key_path, &key))
+        .map_err(|e| format!("Failed to save certificate to {:?}: {}", dir, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600));
+    }
+    info!("Generated self-signed certificate {:?} for {}", cert_path, names.join(", "));
+
+    Ok((cert.into_bytes(), key.into_bytes()))
+}
+
+/// Fingerprint of the first certificate in a PEM bundle
+pub fn pem_fingerprint(pem: &[u8]) -> Option<String> {
+    let der = CertificateDer::from_pem_slice(pem).ok()?;
+    Some(fingerprint(der.as_ref()))
+}
+
+/// Reload `config` whenever the cert or key file changes (e.g. `tailscale cert` renewing
+/// them), so the HTTPS listener serves the new certificate without a restart. The parent
+/// folders are watched, since renewals often replace the files rather than edit them. A
+/// failed reload keeps the current certificate.
+pub fn watch_cert_files(
+    config: RustlsConfig,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let targets: Vec<PathBuf> =
+            [&cert_path, &key_path].into_iter().filter_map(|p| canonical_in_dir(p)).collect();
+        let (tx, mut rx) = mpsc::channel::<()>(16);
+
+        let watched = targets.clone();
+        let created = RecommendedWatcher::new(
+            move |res: Result<Event, notify::Error>| {
+                if let Ok(event) = res {
+                    if event.paths.iter().any(|p| watched.contains(p)) {
+                        let _ = tx.try_send(());
+                    }
+                }
+            },
+            Config::default(),
+        );
+        let mut watcher = match created {
+            Ok(w) => w,
+            Err(e) => {
+                error!("Failed to watch TLS certificate files: {}", e);
+                return;
+            }
+        };
+        let dirs: BTreeSet<&Path> = targets.iter().filter_map(|p| p.parent()).collect();
+        for dir in dirs {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                error!("Failed to watch {:?} for certificate renewals: {}", dir, e);
+                return;
+            }
+        }
+        info!("Watching {:?} and {:?} for certificate renewals", cert_path, key_path);
+
+        while rx.recv().await.is_some() {
+            // Wait for the rest of the renewal to land
+            loop {
+                let quiet = Duration::from_millis(CERT_RELOAD_DEBOUNCE_MS);
+                match tokio::time::timeout(quiet, rx.recv()).await {
+                    Ok(Some(())) => continue,
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            match config.reload_from_pem_file(&cert_path, &key_path).await {
+                Ok(()) => {
+                    let fp = std::fs::read(&cert_path).ok().and_then(|pem| pem_fingerprint(&pem));
+                    info!(
+                        "Reloaded TLS certificate from {:?} (fingerprint {})",
+                        cert_path,
+                        fp.as_deref().unwrap_or("?")
+                    );
+                }
+                Err(e) => {
+                    error!("Failed to reload TLS certificate, keeping the current one: {}", e)
+                }
+            }
+        }
+    })
+}
+
+/// `path` with its folder canonicalized, matching the paths notify reports for that folder
+fn canonical_in_dir(path: &Path) -> Option<PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.canonicalize().ok()?.join(path.file_name()?))
+}
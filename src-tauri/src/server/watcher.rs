@@ -1,34 +1,142 @@
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::{error, info, warn};
 
-use crate::server::sync::SyncService;
-use crate::server::{log_to_file, AppState};
+use crate::server::index::{matches_excluded_file, walk_vault, DocumentIndex};
+use crate::server::sync::{compute_checksum, SyncService};
+use crate::server::{AbortOnDrop, AppState};
+
+/// First restart delay after the watcher dies; doubles on each consecutive failure
+const RESTART_BACKOFF_MIN_SECS: u64 = 1;
+const RESTART_BACKOFF_MAX_SECS: u64 = 60;
+/// A watcher that ran this long before dying counts as healthy (backoff resets)
+const HEALTHY_RUN_SECS: u64 = 60;
+/// Events arriving within this long of the previous one are handled as one batch
+const BATCH_WINDOW_MS: u64 = 200;
+/// Upper bound on how long a continuous burst is collected before it is handled
+const BATCH_MAX_MS: u64 = 2000;
+/// A batch touching this many documents (or any whole folder) is applied in bulk
+const BULK_THRESHOLD: usize = 20;
+/// Recursive watches here don't descend into symlinked folders, so with
+/// `followSymlinks` the link targets are watched too and their event paths mapped back
+const WATCH_LINK_TARGETS: bool = cfg!(any(windows, target_os = "macos"));
 
 pub struct FileWatcher;
 
 impl FileWatcher {
-    pub async fn watch(state: Arc<AppState>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let (tx, mut rx) = mpsc::channel(100);
+    /// Run `watch_with_sync` under a supervisor that restarts it with exponential
+    /// backoff whenever it exits, errors, or panics.
+    pub fn spawn_supervised(
+        state: Arc<AppState>,
+        sync_service: Arc<SyncService>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut backoff = RESTART_BACKOFF_MIN_SECS;
+            let mut restarts: u64 = 0;
+
+            loop {
+                let started = std::time::Instant::now();
+                let watcher_state = Arc::clone(&state);
+                let watcher_sync = Arc::clone(&sync_service);
+                // Aborting the supervisor must also stop the watcher it is waiting on
+                let mut watcher = AbortOnDrop(tokio::spawn(async move {
+                    Self::watch_with_sync(watcher_state, watcher_sync).await
+                }));
+                let result = (&mut watcher.0).await;
+
+                match result {
+                    Ok(Ok(())) => warn!("File watcher exited unexpectedly"),
+                    Ok(Err(e)) => error!("File watcher error: {}", e),
+                    Err(e) => error!("File watcher task panicked: {}", e),
+                }
+
+                if started.elapsed() >= Duration::from_secs(HEALTHY_RUN_SECS) {
+                    backoff = RESTART_BACKOFF_MIN_SECS;
+                }
+
+                restarts += 1;
+                warn!("Restarting file watcher in {}s (restart #{})", backoff, restarts);
+                tokio::time::sleep(Duration::from_secs(backoff)).await;
+                backoff = (backoff * 2).min(RESTART_BACKOFF_MAX_SECS);
+            }
+        })
+    }
 
+    /// Watch the org root recursively, plus symlink targets where the platform needs it
+    fn create_watcher(
+        state: &AppState,
+        tx: mpsc::Sender<Event>,
+    ) -> notify::Result<RecommendedWatcher> {
+        let follow_symlinks = state.config.follow_symlinks.value;
+        let links = if follow_symlinks && WATCH_LINK_TARGETS {
+            Self::linked_dirs(state)
+        } else {
+            Vec::new()
+        };
+
+        let targets = links.clone();
         let mut watcher = RecommendedWatcher::new(
             move |res: Result<Event, notify::Error>| {
-                if let Ok(event) = res {
+                if let Ok(mut event) = res {
+                    // Report changes under a link target at their path inside the vault
+                    for path in event.paths.iter_mut() {
+                        let linked = targets.iter().find(|(t, _)| path.starts_with(t));
+                        if let Some((target, link)) = linked {
+                            if let Ok(rest) = path.strip_prefix(target) {
+                                *path = link.join(rest);
+                            }
+                        }
+                    }
                     let _ = tx.blocking_send(event);
                 }
             },
-            Config::default().with_poll_interval(Duration::from_secs(2)),
+            Config::default()
+                .with_poll_interval(Duration::from_secs(2))
+                .with_follow_symlinks(follow_symlinks),
         )?;
 
         watcher.watch(&state.org_root, RecursiveMode::Recursive)?;
+        for (target, link) in &links {
+            match watcher.watch(target, RecursiveMode::Recursive) {
+                Ok(()) => info!("Watching {:?} (linked at {:?})", target, link),
+                Err(e) => error!("Failed to watch {:?}: {}", target, e),
+            }
+        }
+        Ok(watcher)
+    }
 
-        log_to_file(&format!("File watcher started for {:?}", state.org_root));
+    /// Symlinked folders under the org root whose targets lie outside it, as
+    /// (canonical target, link path in the vault), deepest target first
+    fn linked_dirs(state: &AppState) -> Vec<(PathBuf, PathBuf)> {
+        let root = state
+            .org_root
+            .canonicalize()
+            .unwrap_or_else(|_| state.org_root.clone());
+        let mut links: Vec<(PathBuf, PathBuf)> =
+            walk_vault(&state.org_root, &state.config.exclude_files.value, true, |_, _| {})
+                .filter(|e| e.path_is_symlink() && e.file_type().is_dir())
+                .filter_map(|e| {
+                    let target = e.path().canonicalize().ok()?;
+                    (!target.starts_with(&root)).then(|| (target, e.path().to_path_buf()))
+                })
+                .collect();
+        links.sort_by_key(|(target, _)| std::cmp::Reverse(target.as_os_str().len()));
+        links
+    }
+
+    pub async fn watch(state: Arc<AppState>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (tx, mut rx) = mpsc::channel(100);
+        let _watcher = Self::create_watcher(&state, tx)?;
+
+        info!("File watcher started for {:?}", state.org_root);
 
         // Keep watcher alive and process events
-        while let Some(event) = rx.recv().await {
-            Self::handle_event(&state, &event, None).await;
+        while let Some(batch) = Self::next_batch(&mut rx).await {
+            Self::handle_batch(&state, &batch, None).await;
         }
 
         Ok(())
@@ -40,46 +148,168 @@ impl FileWatcher {
         sync_service: Arc<SyncService>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let (tx, mut rx) = mpsc::channel(100);
+        let _watcher = Self::create_watcher(&state, tx)?;
 
-        let mut watcher = RecommendedWatcher::new(
-            move |res: Result<Event, notify::Error>| {
-                if let Ok(event) = res {
-                    let _ = tx.blocking_send(event);
-                }
-            },
-            Config::default().with_poll_interval(Duration::from_secs(2)),
-        )?;
-
-        watcher.watch(&state.org_root, RecursiveMode::Recursive)?;
-
-        log_to_file(&format!(
-            "File watcher started for {:?} (with sync)",
-            state.org_root
-        ));
+        info!("File watcher started for {:?} (with sync)", state.org_root);
 
         // Keep watcher alive and process events
-        while let Some(event) = rx.recv().await {
-            Self::handle_event(&state, &event, Some(&sync_service)).await;
+        while let Some(batch) = Self::next_batch(&mut rx).await {
+            Self::handle_batch(&state, &batch, Some(&sync_service)).await;
         }
 
         Ok(())
     }
 
+    /// Wait for an event, then collect the rest of its burst
+    async fn next_batch(rx: &mut mpsc::Receiver<Event>) -> Option<Vec<Event>> {
+        let mut batch = vec![rx.recv().await?];
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(BATCH_MAX_MS);
+
+        while tokio::time::Instant::now() < deadline {
+            match tokio::time::timeout(Duration::from_millis(BATCH_WINDOW_MS), rx.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                _ => break,
+            }
+        }
+        Some(batch)
+    }
+
+    /// Handle a burst of events: small ones event by event, directory-scale ones (folder
+    /// deletes/moves, or many files at once) as a single bulk update.
+    async fn handle_batch(
+        state: &AppState,
+        batch: &[Event],
+        sync_service: Option<&Arc<SyncService>>,
+    ) {
+        let (removed, refreshed, folder_level) = {
+            let index = state.index.read().await;
+            Self::collect_changes(state, &index, batch)
+        };
+
+        if !folder_level && removed.len() + refreshed.len() < BULK_THRESHOLD {
+            for event in batch {
+                Self::handle_event(state, event, sync_service).await;
+            }
+            return;
+        }
+
+        let relative = |p: &Path| {
+            p.strip_prefix(&state.org_root)
+                .unwrap_or(p)
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+        let removed_rel: Vec<String> = removed.iter().map(|p| relative(p)).collect();
+        let updated_rel: Vec<String> = refreshed.iter().map(|p| relative(p)).collect();
+
+        info!("Bulk change: {} removed, {} updated", removed_rel.len(), updated_rel.len());
+        state.index.write().await.apply_bulk(&removed, &refreshed);
+
+        let msg = serde_json::json!({
+            "type": "bulk",
+            "removed": removed_rel,
+            "updated": updated_rel,
+            "timestamp": chrono::Utc::now().timestamp_millis()
+        });
+        let _ = state.ws_tx.send(msg.to_string());
+
+        if let Some(sync) = sync_service {
+            for path in &updated_rel {
+                sync.handle_local_change(path).await;
+            }
+        }
+    }
+
+    /// Net document removals and refreshes for a batch of events, expanding folder-level
+    /// events to the documents inside. The flag is set if any folder-level event was seen.
+    fn collect_changes(
+        state: &AppState,
+        index: &DocumentIndex,
+        batch: &[Event],
+    ) -> (Vec<PathBuf>, Vec<PathBuf>, bool) {
+        use notify::event::ModifyKind;
+        use notify::EventKind;
+
+        let mut touched: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut folder_level = false;
+
+        for event in batch {
+            for path in &event.paths {
+                if path.extension().map(|e| e == "md").unwrap_or(false) {
+                    if !Self::is_excluded(state, path) {
+                        touched.insert(path.clone());
+                    }
+                    continue;
+                }
+
+                let relative = path
+                    .strip_prefix(&state.org_root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+
+                match event.kind {
+                    // A folder that's gone: everything indexed under it
+                    EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+                        if !path.exists() =>
+                    {
+                        let under = index.paths_under(&relative);
+                        folder_level |= !under.is_empty();
+                        touched.extend(under.into_iter().map(|p| state.org_root.join(p)));
+                    }
+                    // A folder that appeared (created or moved in): every document inside
+                    EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                        if path.is_dir() =>
+                    {
+                        for entry in walkdir::WalkDir::new(path)
+                            .follow_links(state.config.follow_symlinks.value)
+                            .into_iter()
+                            .filter_map(|e| e.ok())
+                        {
+                            let file = entry.path();
+                            if Self::is_indexable(state, file) && file.is_file() {
+                                folder_level = true;
+                                touched.insert(file.to_path_buf());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Net effect: whatever exists now is refreshed, the rest removed
+        let (refreshed, removed): (Vec<PathBuf>, Vec<PathBuf>) =
+            touched.into_iter().partition(|p| p.exists());
+        (removed, refreshed, folder_level)
+    }
+
     async fn handle_event(
         state: &AppState,
         event: &Event,
         sync_service: Option<&Arc<SyncService>>,
     ) {
+        use notify::event::{ModifyKind, RenameMode};
         use notify::EventKind;
 
+        // Rename/move with both sides reported: move the index entry instead of remove+add
+        if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+            if let [from, to] = event.paths.as_slice() {
+                if Self::is_indexable(state, from) && Self::is_indexable(state, to) {
+                    Self::handle_rename(state, from, to, sync_service).await;
+                    return;
+                }
+            }
+        }
+
         for path in &event.paths {
             // Only handle markdown files
             if !path.extension().map(|e| e == "md").unwrap_or(false) {
                 continue;
             }
 
-            // Skip excluded directories
-            if Self::is_excluded(path, &state.org_root) {
+            // Skip excluded directories and files
+            if Self::is_excluded(state, path) {
                 continue;
             }
 
@@ -89,50 +319,123 @@ impl FileWatcher {
                 .to_string_lossy()
                 .replace('\\', "/");
 
-            match event.kind {
-                EventKind::Create(_) | EventKind::Modify(_) => {
-                    log_to_file(&format!("File changed: {}", relative_path));
-                    let mut index = state.index.write().await;
-                    index.refresh_document(path);
-
-                    // Notify WebSocket clients
-                    let msg = serde_json::json!({
-                        "type": "update",
-                        "path": relative_path,
-                        "timestamp": chrono::Utc::now().timestamp_millis()
-                    });
-                    let _ = state.ws_tx.send(msg.to_string());
-
-                    // Drop index lock before calling sync service
-                    drop(index);
-
-                    // Check if this is a federation-tracked document
-                    if let Some(sync) = sync_service {
-                        sync.handle_local_change(&relative_path).await;
-                    }
-                }
-                EventKind::Remove(_) => {
-                    log_to_file(&format!("File removed: {}", relative_path));
-                    let mut index = state.index.write().await;
-                    index.remove_document(path);
-
-                    // Notify WebSocket clients
-                    let msg = serde_json::json!({
-                        "type": "remove",
-                        "path": relative_path,
-                        "timestamp": chrono::Utc::now().timestamp_millis()
-                    });
-                    let _ = state.ws_tx.send(msg.to_string());
+            // Platforms that only report one side of a rename: the old path no longer exists
+            let is_removal = match event.kind {
+                EventKind::Remove(_) => true,
+                EventKind::Modify(ModifyKind::Name(_)) => !path.exists(),
+                _ => false,
+            };
+
+            if is_removal {
+                info!("File removed: {}", relative_path);
+                let mut index = state.index.write().await;
+                index.remove_document(path);
+
+                // Notify WebSocket clients
+                let msg = serde_json::json!({
+                    "type": "remove",
+                    "path": relative_path,
+                    "timestamp": chrono::Utc::now().timestamp_millis()
+                });
+                let _ = state.ws_tx.send(msg.to_string());
+                continue;
+            }
+
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                info!("File changed: {}", relative_path);
+                let mut index = state.index.write().await;
+                index.refresh_document(path);
+
+                // Notify WebSocket clients
+                let msg = Self::update_message(state, &index, &relative_path, path);
+                let _ = state.ws_tx.send(msg.to_string());
+
+                // Drop index lock before calling sync service
+                drop(index);
+
+                // Check if this is a federation-tracked document
+                if let Some(sync) = sync_service {
+                    sync.handle_local_change(&relative_path).await;
                 }
-                _ => {}
             }
         }
     }
 
-    fn is_excluded(path: &Path, org_root: &Path) -> bool {
-        let relative = path.strip_prefix(org_root).unwrap_or(path);
+    /// WebSocket `update` message for a refreshed document. With `wsUpdateMetadata` on it
+    /// also carries the refreshed metadata so clients can update in place.
+    pub fn update_message(
+        state: &AppState,
+        index: &DocumentIndex,
+        relative_path: &str,
+        path: &Path,
+    ) -> serde_json::Value {
+        let mut msg = serde_json::json!({
+            "type": "update",
+            "path": relative_path,
+            "timestamp": chrono::Utc::now().timestamp_millis()
+        });
+
+        if state.config.ws_update_metadata.value {
+            if let Some(doc) = index.get_document(relative_path) {
+                msg["title"] = serde_json::json!(doc.title);
+                msg["docType"] = serde_json::json!(doc.doc_type);
+                msg["tags"] = serde_json::json!(doc.tags);
+            }
+            if let Ok(content) = std::fs::read_to_string(path) {
+                msg["checksum"] = serde_json::json!(compute_checksum(&content));
+            }
+        }
+        msg
+    }
+
+    /// Handle a rename/move where both the old and new paths are known.
+    async fn handle_rename(
+        state: &AppState,
+        from: &Path,
+        to: &Path,
+        sync_service: Option<&Arc<SyncService>>,
+    ) {
+        let relative = |p: &Path| {
+            p.strip_prefix(&state.org_root)
+                .unwrap_or(p)
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+        let old_relative = relative(from);
+        let new_relative = relative(to);
+
+        info!("File renamed: {} → {}", old_relative, new_relative);
+        let mut index = state.index.write().await;
+        index.rename_document(from, to);
+        drop(index);
+
+        // Notify WebSocket clients
+        let msg = serde_json::json!({
+            "type": "rename",
+            "oldPath": old_relative,
+            "path": new_relative,
+            "timestamp": chrono::Utc::now().timestamp_millis()
+        });
+        let _ = state.ws_tx.send(msg.to_string());
+
+        if let Some(sync) = sync_service {
+            sync.handle_local_change(&new_relative).await;
+        }
+    }
+
+    /// Markdown file outside excluded directories and files
+    fn is_indexable(state: &AppState, path: &Path) -> bool {
+        path.extension().map(|e| e == "md").unwrap_or(false) && !Self::is_excluded(state, path)
+    }
+
+    fn is_excluded(state: &AppState, path: &Path) -> bool {
+        let relative = path.strip_prefix(&state.org_root).unwrap_or(path);
         let path_str = relative.to_string_lossy();
 
+        if matches_excluded_file(&path_str.replace('\\', "/"), &state.config.exclude_files.value) {
+            return true;
+        }
+
         let excluded = [
             "node_modules",
             ".git",
@@ -142,6 +445,7 @@ impl FileWatcher {
             "build",
             ".next",
             "target",
+            ".vitrum/", // Federation base snapshots
         ];
 
         for exc in &excluded {